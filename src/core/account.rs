@@ -0,0 +1,530 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::{FromStr, Split};
+use super::amount::Amount;
+use super::balance::Balance;
+use super::intern::Name;
+use super::transaction::Posting;
+
+
+/// A full account name, such as `Expenses:Food:Groceries`. Account names are interned, so they
+/// are cheap to copy, compare and hash.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AccountName(Name);
+
+impl AccountName {
+    pub fn new(full_account: &str) -> AccountName {
+        AccountName(Name::new(full_account))
+    }
+
+    /// Builds an account name from its sub-accounts.
+    pub fn from_sub_accounts<S>(sub_accounts: &[S]) -> AccountName
+    where S: AsRef<str> {
+        let parts: Vec<&str> = sub_accounts.iter().map(|s| s.as_ref()).collect();
+        AccountName::new(&parts.join(":"))
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        self.0.as_str()
+    }
+
+    /// The sub-accounts making up this account, from the top level down.
+    pub fn sub_accounts(&self) -> Split<'static, char> {
+        self.as_str().split(':')
+    }
+
+    /// The account this one is nested under, if it isn't a top-level account.
+    pub fn parent(&self) -> Option<AccountName> {
+        let full_account = self.as_str();
+        full_account.rfind(':').map(|index| AccountName::new(&full_account[..index]))
+    }
+
+    /// The last sub-account, e.g. `Groceries` for `Expenses:Food:Groceries`.
+    pub fn leaf(&self) -> &'static str {
+        let full_account = self.as_str();
+        match full_account.rfind(':') {
+            Some(index) => &full_account[index + 1..],
+            None => full_account,
+        }
+    }
+
+    /// The number of levels above this account; top-level accounts have depth 0.
+    pub fn depth(&self) -> usize {
+        self.as_str().matches(':').count()
+    }
+
+    /// This account, or its ancestor at the given depth if it is nested more deeply.
+    pub fn truncate(&self, depth: usize) -> AccountName {
+        match self.as_str().match_indices(':').nth(depth) {
+            Some((index, _)) => AccountName::new(&self.as_str()[..index]),
+            None => *self,
+        }
+    }
+}
+
+impl fmt::Display for AccountName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+
+/// The kind of a top-level account, which decides where it appears in net worth and income
+/// statements and which way its balance is shown.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AccountType {
+    Asset,
+    Liability,
+    Equity,
+    Income,
+    Expense,
+}
+
+/// Whether an account's balance is shown as it is stored, or negated so that the usual balance
+/// of the account reads as a positive number.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SignConvention {
+    Normal,
+    Inverted,
+}
+
+impl AccountType {
+    /// Liabilities, equity and income normally carry negative (credit) balances, so they are
+    /// shown inverted.
+    pub fn sign_convention(&self) -> SignConvention {
+        match *self {
+            AccountType::Asset | AccountType::Expense => SignConvention::Normal,
+            AccountType::Liability | AccountType::Equity | AccountType::Income =>
+                SignConvention::Inverted,
+        }
+    }
+
+    /// Returns the amount as it should be shown for an account of this type.
+    pub fn display_amount(&self, amount: &Amount) -> Amount {
+        match self.sign_convention() {
+            SignConvention::Normal => *amount,
+            SignConvention::Inverted => Amount::new(-amount.quantity(), amount.symbol()),
+        }
+    }
+}
+
+impl FromStr for AccountType {
+    type Err = String;
+
+    /// Parses an account type name, singular or plural and ignoring case, e.g. `Asset` or
+    /// `liabilities`. `Revenue` is accepted for income.
+    fn from_str(s: &str) -> Result<AccountType, String> {
+        match &s.trim().to_lowercase()[..] {
+            "asset" | "assets" => Ok(AccountType::Asset),
+            "liability" | "liabilities" => Ok(AccountType::Liability),
+            "equity" => Ok(AccountType::Equity),
+            "income" | "revenue" | "revenues" => Ok(AccountType::Income),
+            "expense" | "expenses" => Ok(AccountType::Expense),
+            _ => Err(format!("Unknown account type: {}", s)),
+        }
+    }
+}
+
+/// Classifies accounts by type. An account's type comes from the nearest `account` directive
+/// declaring a type for it or one of its parents, falling back to the name of its top-level
+/// account.
+#[derive(Debug)]
+pub struct AccountTypes {
+    roots: HashMap<String, AccountType>,
+    declared: HashMap<AccountName, AccountType>,
+}
+
+impl AccountTypes {
+    /// An empty classification, with no root names mapped.
+    pub fn new() -> AccountTypes {
+        AccountTypes {
+            roots: HashMap::new(),
+            declared: HashMap::new(),
+        }
+    }
+
+    /// Maps a top-level account name to a type, replacing any existing mapping for that name.
+    pub fn map_root<S>(&mut self, root: S, account_type: AccountType)
+    where S: Into<String> {
+        self.roots.insert(root.into(), account_type);
+    }
+
+    /// Records the type of an account, as declared by an `account` directive. The type also
+    /// applies to accounts nested under it.
+    pub fn declare(&mut self, account: AccountName, account_type: AccountType) {
+        self.declared.insert(account, account_type);
+    }
+
+    pub fn classify(&self, account: AccountName) -> Option<AccountType> {
+        let mut current = Some(account);
+        while let Some(name) = current {
+            if let Some(account_type) = self.declared.get(&name) {
+                return Some(*account_type);
+            }
+            current = name.parent();
+        }
+
+        let root = account.sub_accounts().next().expect("account has a top-level name");
+        self.roots.get(root).cloned()
+    }
+}
+
+impl Default for AccountTypes {
+    /// The conventional English top-level account names.
+    fn default() -> AccountTypes {
+        let mut types = AccountTypes::new();
+        types.map_root("Assets", AccountType::Asset);
+        types.map_root("Liabilities", AccountType::Liability);
+        types.map_root("Equity", AccountType::Equity);
+        types.map_root("Income", AccountType::Income);
+        types.map_root("Revenue", AccountType::Income);
+        types.map_root("Expenses", AccountType::Expense);
+        types
+    }
+}
+
+/// An account in an `AccountTree`, with the postings made directly to it and its balances.
+#[derive(Debug)]
+pub struct AccountNode {
+    account: AccountName,
+    parent: Option<AccountName>,
+    children: Vec<AccountName>,
+    postings: Vec<Posting>,
+    balance: Balance,
+    inclusive_balance: Balance,
+}
+
+impl AccountNode {
+    fn new(account: AccountName) -> AccountNode {
+        AccountNode {
+            account: account,
+            parent: account.parent(),
+            children: Vec::new(),
+            postings: Vec::new(),
+            balance: Balance::new(),
+            inclusive_balance: Balance::new(),
+        }
+    }
+
+    /// The last sub-account of this account's name.
+    pub fn name(&self) -> &'static str {
+        self.account.leaf()
+    }
+
+    /// The full path of this account.
+    pub fn account(&self) -> AccountName {
+        self.account
+    }
+
+    pub fn depth(&self) -> usize {
+        self.account.depth()
+    }
+
+    pub fn parent(&self) -> Option<AccountName> {
+        self.parent
+    }
+
+    /// The accounts directly under this one, in sorted order.
+    pub fn children(&self) -> &[AccountName] {
+        &self.children
+    }
+
+    /// The postings made directly to this account.
+    pub fn postings(&self) -> &[Posting] {
+        &self.postings
+    }
+
+    /// The sum of the postings made directly to this account.
+    pub fn balance(&self) -> &Balance {
+        &self.balance
+    }
+
+    /// The sum of the postings made to this account and all accounts under it.
+    pub fn inclusive_balance(&self) -> &Balance {
+        &self.inclusive_balance
+    }
+}
+
+/// The hierarchy of accounts that postings were made to, with balances rolled up to every
+/// parent account.
+#[derive(Debug, Default)]
+pub struct AccountTree {
+    nodes: HashMap<AccountName, AccountNode>,
+    roots: Vec<AccountName>,
+}
+
+impl AccountTree {
+    pub fn new() -> AccountTree {
+        AccountTree {
+            nodes: HashMap::new(),
+            roots: Vec::new(),
+        }
+    }
+
+    pub fn from_postings<'a, I>(postings: I) -> AccountTree
+    where I: IntoIterator<Item=&'a Posting> {
+        let mut tree = AccountTree::new();
+        for posting in postings {
+            tree.add_posting(posting.clone());
+        }
+        tree
+    }
+
+    /// Adds a posting to its account, creating the account and any missing parents.
+    pub fn add_posting(&mut self, posting: Posting) {
+        let account = posting.account();
+        self.ensure_account(account);
+
+        let mut ancestor = Some(account);
+        while let Some(current) = ancestor {
+            let node = self.nodes.get_mut(&current).expect("account in tree");
+            node.inclusive_balance.add_amount(posting.amount());
+            ancestor = node.parent;
+        }
+
+        let node = self.nodes.get_mut(&account).expect("account in tree");
+        node.balance.add_amount(posting.amount());
+        node.postings.push(posting);
+    }
+
+    /// Adds an account with no postings, along with any missing parents.
+    pub fn ensure_account(&mut self, account: AccountName) {
+        if self.nodes.contains_key(&account) {
+            return;
+        }
+
+        self.nodes.insert(account, AccountNode::new(account));
+        let siblings =
+            match account.parent() {
+                Some(parent) => {
+                    self.ensure_account(parent);
+                    &mut self.nodes.get_mut(&parent).expect("parent in tree").children
+                },
+                None => &mut self.roots,
+            };
+        let index = siblings.binary_search(&account).unwrap_or_else(|index| index);
+        siblings.insert(index, account);
+    }
+
+    pub fn get(&self, account: AccountName) -> Option<&AccountNode> {
+        self.nodes.get(&account)
+    }
+
+    /// Looks up an account by its full path, e.g. `Expenses:Food`.
+    pub fn find(&self, path: &str) -> Option<&AccountNode> {
+        self.get(AccountName::new(path))
+    }
+
+    /// The top-level accounts, in sorted order.
+    pub fn roots(&self) -> &[AccountName] {
+        &self.roots
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Iterates over every account depth-first, visiting parents before their children and
+    /// siblings in sorted order.
+    pub fn iter(&self) -> DepthFirst<'_> {
+        DepthFirst {
+            tree: self,
+            stack: self.roots.iter().rev().cloned().collect(),
+        }
+    }
+
+    /// Returns a copy of this tree with accounts nested deeper than `max_depth` folded into
+    /// their ancestor at that depth. A `max_depth` of 0 keeps only the top-level accounts.
+    pub fn prune(&self, max_depth: usize) -> AccountTree {
+        let mut pruned = AccountTree::new();
+        for node in self.iter() {
+            let account = node.account.truncate(max_depth);
+            pruned.ensure_account(account);
+            for posting in &node.postings {
+                pruned.add_posting(Posting::new(account, *posting.amount(), posting.amount_source(),
+                    posting.comment().map(|c| c.to_string()))
+                    .with_cost(posting.cost().cloned())
+                    .with_line(posting.line()));
+            }
+        }
+        pruned
+    }
+}
+
+/// Depth-first iterator over the accounts in an `AccountTree`.
+pub struct DepthFirst<'a> {
+    tree: &'a AccountTree,
+    stack: Vec<AccountName>,
+}
+
+impl<'a> Iterator for DepthFirst<'a> {
+    type Item = &'a AccountNode;
+
+    fn next(&mut self) -> Option<&'a AccountNode> {
+        self.stack.pop().map(|account| {
+            let node = &self.tree.nodes[&account];
+            self.stack.extend(node.children.iter().rev().cloned());
+            node
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use decimal::d128;
+    use core::amount::Amount;
+    use core::symbol::*;
+    use core::transaction::{AmountSource, Posting};
+
+    fn posting(account: &str, quantity: d128) -> Posting {
+        Posting::new(
+            AccountName::new(account),
+            Amount::new(quantity, Symbol::new("$", QuoteOption::Unquoted)),
+            AmountSource::Provided,
+            None)
+    }
+
+    fn sample_tree() -> AccountTree {
+        AccountTree::from_postings(&[
+            posting("Expenses:Food:Groceries", d128!(45.00)),
+            posting("Liabilities:Credit", d128!(-45.00)),
+            posting("Expenses:Food", d128!(10.00)),
+            posting("Expenses:Auto:Gas", d128!(30.00)),
+            posting("Assets:Checking", d128!(-40.00)),
+        ])
+    }
+
+    #[test]
+    fn account_name_from_sub_accounts() {
+        let result = AccountName::from_sub_accounts(&["Expenses", "Food", "Groceries"]);
+        assert_eq!(result, AccountName::new("Expenses:Food:Groceries"));
+    }
+
+    #[test]
+    fn account_name_sub_accounts() {
+        let result: Vec<&str> = AccountName::new("Expenses:Food:Groceries").sub_accounts().collect();
+        assert_eq!(result, vec!["Expenses", "Food", "Groceries"]);
+    }
+
+    #[test]
+    fn account_name_parent() {
+        assert_eq!(AccountName::new("Expenses:Food:Groceries").parent(),
+            Some(AccountName::new("Expenses:Food")));
+        assert_eq!(AccountName::new("Expenses").parent(), None);
+    }
+
+    #[test]
+    fn account_name_leaf_and_depth() {
+        let account = AccountName::new("Expenses:Food:Groceries");
+        assert_eq!(account.leaf(), "Groceries");
+        assert_eq!(account.depth(), 2);
+        assert_eq!(account.truncate(1), AccountName::new("Expenses:Food"));
+        assert_eq!(account.truncate(5), account);
+    }
+
+    #[test]
+    fn account_tree_creates_parents() {
+        let tree = sample_tree();
+        let food = tree.find("Expenses:Food").unwrap();
+        assert_eq!(food.name(), "Food");
+        assert_eq!(food.parent(), Some(AccountName::new("Expenses")));
+        assert_eq!(food.children(), &[AccountName::new("Expenses:Food:Groceries")]);
+        assert_eq!(food.postings().len(), 1);
+        assert_eq!(tree.roots(), &[
+            AccountName::new("Assets"),
+            AccountName::new("Expenses"),
+            AccountName::new("Liabilities"),
+        ]);
+    }
+
+    #[test]
+    fn account_tree_rolls_up_balances() {
+        let tree = sample_tree();
+        let dollars = Symbol::new("$", QuoteOption::Unquoted);
+        let food = tree.find("Expenses:Food").unwrap();
+        assert_eq!(food.balance().quantity(dollars), d128!(10.00));
+        assert_eq!(food.inclusive_balance().quantity(dollars), d128!(55.00));
+        let expenses = tree.find("Expenses").unwrap();
+        assert!(expenses.balance().is_zero());
+        assert_eq!(expenses.inclusive_balance().quantity(dollars), d128!(85.00));
+    }
+
+    #[test]
+    fn account_tree_iter_depth_first_sorted() {
+        let tree = sample_tree();
+        let result: Vec<&str> = tree.iter().map(|node| node.account().as_str()).collect();
+        assert_eq!(result, vec![
+            "Assets",
+            "Assets:Checking",
+            "Expenses",
+            "Expenses:Auto",
+            "Expenses:Auto:Gas",
+            "Expenses:Food",
+            "Expenses:Food:Groceries",
+            "Liabilities",
+            "Liabilities:Credit",
+        ]);
+    }
+
+    #[test]
+    fn account_tree_prune() {
+        let tree = sample_tree().prune(1);
+        let dollars = Symbol::new("$", QuoteOption::Unquoted);
+        assert!(tree.find("Expenses:Food:Groceries").is_none());
+        let food = tree.find("Expenses:Food").unwrap();
+        assert_eq!(food.balance().quantity(dollars), d128!(55.00));
+        assert_eq!(food.postings().len(), 2);
+        assert_eq!(tree.find("Expenses").unwrap().inclusive_balance().quantity(dollars),
+            d128!(85.00));
+        assert_eq!(tree.len(), 7);
+    }
+
+    #[test]
+    fn account_type_from_str() {
+        assert_eq!("Assets".parse(), Ok(AccountType::Asset));
+        assert_eq!("liability".parse(), Ok(AccountType::Liability));
+        assert_eq!("Revenue".parse(), Ok(AccountType::Income));
+        assert!("Actifs".parse::<AccountType>().is_err());
+    }
+
+    #[test]
+    fn account_type_display_amount() {
+        let amount = Amount::new(d128!(-45.00), Symbol::new("$", QuoteOption::Unquoted));
+        assert_eq!(AccountType::Liability.display_amount(&amount),
+            Amount::new(d128!(45.00), Symbol::new("$", QuoteOption::Unquoted)));
+        assert_eq!(AccountType::Asset.display_amount(&amount), amount);
+    }
+
+    #[test]
+    fn account_types_default_roots() {
+        let types = AccountTypes::default();
+        assert_eq!(types.classify(AccountName::new("Expenses:Food:Groceries")),
+            Some(AccountType::Expense));
+        assert_eq!(types.classify(AccountName::new("Liabilities")), Some(AccountType::Liability));
+        assert_eq!(types.classify(AccountName::new("Actifs:Banque")), None);
+    }
+
+    #[test]
+    fn account_types_configured_root() {
+        let mut types = AccountTypes::default();
+        types.map_root("Actifs", AccountType::Asset);
+        assert_eq!(types.classify(AccountName::new("Actifs:Banque")), Some(AccountType::Asset));
+    }
+
+    #[test]
+    fn account_types_declared_overrides_root() {
+        let mut types = AccountTypes::default();
+        types.declare(AccountName::new("Assets:Loans"), AccountType::Liability);
+        assert_eq!(types.classify(AccountName::new("Assets:Loans:Car")),
+            Some(AccountType::Liability));
+        assert_eq!(types.classify(AccountName::new("Assets:Savings")), Some(AccountType::Asset));
+    }
+}
@@ -1,106 +1,221 @@
 use decimal::d128;
+use rust_core::str::FromStr;
 use std::fmt;
+use super::commodity::CommodityRegistry;
 use super::symbol::Symbol;
 
 
-#[derive(PartialEq, Debug)]
-pub enum SymbolPosition {
-    Left,
-    Right,
+/// How to round a quantity that falls between two representable values.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RoundingMode {
+    /// Round to the nearest value, with ties going to the even neighbour (banker's rounding).
+    HalfEven,
+    /// Round to the nearest value, with ties going away from zero.
+    HalfUp,
+    /// Drop the extra digits, rounding towards zero.
+    Truncate,
 }
 
-#[derive(PartialEq, Debug)]
-pub enum Spacing {
-    Space,
-    NoSpace,
-}
-
-#[derive(PartialEq, Debug)]
-pub struct RenderOptions {
-    symbol_position: SymbolPosition,
-    spacing: Spacing,
-}
-
-impl RenderOptions {
-    pub fn new(position: SymbolPosition, spacing: Spacing) -> RenderOptions {
-        RenderOptions {
-            symbol_position: position,
-            spacing: spacing,
-        }
-    }
-}
-
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Amount {
     quantity: d128,
     symbol: Symbol,
-    render_options: RenderOptions,
 }
 
 impl Amount {
-    pub fn new(quantity: d128, symbol: Symbol, render_opts: RenderOptions) -> Amount {
+    pub fn new(quantity: d128, symbol: Symbol) -> Amount {
         Amount {
             quantity: quantity,
             symbol: symbol,
-            render_options: render_opts,
+        }
+    }
+
+    pub fn quantity(&self) -> d128 {
+        self.quantity
+    }
+
+    pub fn symbol(&self) -> Symbol {
+        self.symbol
+    }
+
+    /// Returns this amount rounded to the given number of decimal places. The amount itself
+    /// keeps its full precision.
+    pub fn round_to(&self, precision: u32, mode: RoundingMode) -> Amount {
+        Amount {
+            quantity: round(self.quantity, precision, mode),
+            symbol: self.symbol,
+        }
+    }
+
+    /// Returns a value that renders this amount using the formatting of its commodity.
+    pub fn display<'a>(&'a self, commodities: &'a CommodityRegistry) -> AmountDisplay<'a> {
+        AmountDisplay {
+            amount: self,
+            commodities: commodities,
         }
     }
 }
 
-impl fmt::Display for Amount {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let spacing =
-            match self.render_options.spacing {
-                Spacing::Space => " ",
-                Spacing::NoSpace => "",
-            };
-
-        match self.render_options.symbol_position {
-            SymbolPosition::Left => write!(f, "{}{}{}", self.symbol, spacing, self.quantity),
-            SymbolPosition::Right => write!(f, "{}{}{}", self.quantity, spacing, self.symbol),
+/// An amount along with how it renders, for messages that are built where the commodity
+/// registry is at hand but displayed where it isn't, such as load errors.
+#[derive(Clone, PartialEq, Debug)]
+pub struct RenderedAmount {
+    amount: Amount,
+    rendered: String,
+}
+
+impl RenderedAmount {
+    /// Renders an amount using its commodity's formatting, without dropping any digits.
+    pub fn new(amount: Amount, commodities: &CommodityRegistry) -> RenderedAmount {
+        RenderedAmount {
+            amount: amount,
+            rendered: commodities.format_exact(amount.quantity, amount.symbol),
         }
     }
+
+    pub fn amount(&self) -> &Amount {
+        &self.amount
+    }
+}
+
+impl fmt::Display for RenderedAmount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.rendered)
+    }
+}
+
+pub struct AmountDisplay<'a> {
+    amount: &'a Amount,
+    commodities: &'a CommodityRegistry,
+}
+
+impl<'a> fmt::Display for AmountDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.commodities.format(self.amount.quantity, self.amount.symbol))
+    }
+}
+
+
+
+// HELPERS
+
+/// The number of digits after the decimal point a quantity is written with.
+pub fn decimal_places(quantity: d128) -> u32 {
+    let rendered = format!("{}", quantity);
+    match rendered.find('.') {
+        Some(index) => (rendered.len() - index - 1) as u32,
+        None => 0,
+    }
+}
+
+/// Rounds a quantity to the given number of decimal places.
+pub fn round(quantity: d128, precision: u32, mode: RoundingMode) -> d128 {
+    let step = d128::from_str(&format!("1E-{}", precision)).expect("rounding step");
+    let nearest = quantity.quantize(step);
+    let away_from_zero = nearest.abs() > quantity.abs();
+    let towards_zero =
+        if quantity.is_negative() {
+            nearest + step
+        } else {
+            nearest - step
+        };
+
+    match mode {
+        RoundingMode::HalfEven => nearest,
+        RoundingMode::Truncate => if away_from_zero { towards_zero } else { nearest },
+        RoundingMode::HalfUp => {
+            let is_tie = (quantity - nearest).abs() == step / d128!(2);
+            if is_tie && !away_from_zero {
+                if quantity.is_negative() {
+                    nearest - step
+                } else {
+                    nearest + step
+                }
+            } else {
+                nearest
+            }
+        },
+    }
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use core::commodity::*;
     use core::symbol::*;
 
     #[test]
-    fn amount_fmt_symbol_left_with_space() {
-        let result = format!("{}", Amount::new(
-            d128!(13245.00),
-            Symbol::new("US$", QuoteOption::Unquoted),
-            RenderOptions::new(SymbolPosition::Left, Spacing::Space)));
-        assert_eq!(result, "US$ 13245.00");
+    fn round_half_even() {
+        assert_eq!(round(d128!(2.345), 2, RoundingMode::HalfEven), d128!(2.34));
+        assert_eq!(round(d128!(2.355), 2, RoundingMode::HalfEven), d128!(2.36));
+        assert_eq!(round(d128!(-2.345), 2, RoundingMode::HalfEven), d128!(-2.34));
     }
 
     #[test]
-    fn amount_fmt_symbol_left_no_space() {
-        let result = format!("{}", Amount::new(
-                d128!(13245.00),
-                Symbol::new("$", QuoteOption::Unquoted),
-                RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace)));
-        assert_eq!(result, "$13245.00");
+    fn round_half_up() {
+        assert_eq!(round(d128!(2.345), 2, RoundingMode::HalfUp), d128!(2.35));
+        assert_eq!(round(d128!(2.344), 2, RoundingMode::HalfUp), d128!(2.34));
+        assert_eq!(round(d128!(-2.345), 2, RoundingMode::HalfUp), d128!(-2.35));
     }
 
     #[test]
-    fn amount_fmt_symbol_right_with_space() {
-        let result = format!("{}", Amount::new(
-                d128!(13245.463),
-                Symbol::new("MUTF2351", QuoteOption::Quoted),
-                RenderOptions::new(SymbolPosition::Right, Spacing::Space)));
-        assert_eq!(result, "13245.463 \"MUTF2351\"");
+    fn round_truncate() {
+        assert_eq!(round(d128!(2.349), 2, RoundingMode::Truncate), d128!(2.34));
+        assert_eq!(round(d128!(-2.349), 2, RoundingMode::Truncate), d128!(-2.34));
+        assert_eq!(round(d128!(2.3), 2, RoundingMode::Truncate), d128!(2.30));
     }
 
     #[test]
-    fn amount_fmt_symbol_right_no_space() {
-        let result = format!("{}", Amount::new(
-                d128!(13245.463),
-                Symbol::new("RUST", QuoteOption::Unquoted),
-                RenderOptions::new(SymbolPosition::Right, Spacing::NoSpace)));
-        assert_eq!(result, "13245.463RUST");
-    }
-}
\ No newline at end of file
+    fn decimal_places_test() {
+        assert_eq!(decimal_places(d128!(5.4123)), 4);
+        assert_eq!(decimal_places(d128!(5.410)), 3);
+        assert_eq!(decimal_places(d128!(1110)), 0);
+    }
+
+    #[test]
+    fn amount_round_to_keeps_symbol() {
+        let amount = Amount::new(d128!(13245.463) * d128!(5.41),
+            Symbol::new("$", QuoteOption::Unquoted));
+        assert_eq!(amount.round_to(2, RoundingMode::HalfEven),
+            Amount::new(d128!(71657.95), Symbol::new("$", QuoteOption::Unquoted)));
+        assert_eq!(amount.quantity(), d128!(71657.95483));
+    }
+
+    #[test]
+    fn amount_display_uses_registry() {
+        let mut commodities = CommodityRegistry::new();
+        commodities.observe(Commodity::new(
+            Symbol::new("US$", QuoteOption::Unquoted),
+            RenderOptions::new(SymbolPosition::Left, Spacing::Space,
+                ThousandsSeparator::Comma, 2)));
+        let amount = Amount::new(d128!(13245), Symbol::new("US$", QuoteOption::Unquoted));
+        let result = format!("{}", amount.display(&commodities));
+        assert_eq!(result, "US$ 13,245.00");
+    }
+
+    #[test]
+    fn amount_display_same_commodity_renders_consistently() {
+        let mut commodities = CommodityRegistry::new();
+        commodities.observe(Commodity::new(
+            Symbol::new("$", QuoteOption::Unquoted),
+            RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+                ThousandsSeparator::NoSeparator, 2)));
+        let first = Amount::new(d128!(5), Symbol::new("$", QuoteOption::Unquoted));
+        let second = Amount::new(d128!(5.00), Symbol::new("$", QuoteOption::Unquoted));
+        assert_eq!(format!("{}", first.display(&commodities)), "$5.00");
+        assert_eq!(format!("{}", second.display(&commodities)), "$5.00");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn amount_json_round_trip() {
+        let amount = Amount::new(d128!(13245.463), Symbol::new("MUTF2351", QuoteOption::Quoted));
+        let json = ::serde_json::to_string(&amount).unwrap();
+        assert_eq!(json,
+            "{\"quantity\":\"13245.463\",\"symbol\":{\"value\":\"MUTF2351\",\"quote_option\":\"Quoted\"}}");
+        let result: Amount = ::serde_json::from_str(&json).unwrap();
+        assert_eq!(result, amount);
+    }
+}
@@ -0,0 +1,91 @@
+use decimal::d128;
+use std::collections::BTreeMap;
+use super::amount::Amount;
+use super::symbol::Symbol;
+
+
+/// A sum of amounts that may be in several commodities, kept as one quantity per symbol.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Balance {
+    quantities: BTreeMap<Symbol, d128>,
+}
+
+impl Balance {
+    pub fn new() -> Balance {
+        Balance {
+            quantities: BTreeMap::new(),
+        }
+    }
+
+    pub fn add_amount(&mut self, amount: &Amount) {
+        let quantity = self.quantities.entry(amount.symbol()).or_insert_with(d128::zero);
+        *quantity += amount.quantity();
+    }
+
+    pub fn add_balance(&mut self, other: &Balance) {
+        for (symbol, quantity) in &other.quantities {
+            let total = self.quantities.entry(*symbol).or_insert_with(d128::zero);
+            *total += *quantity;
+        }
+    }
+
+    /// The quantity held of the given symbol, which is zero if the symbol has never been added.
+    pub fn quantity(&self, symbol: Symbol) -> d128 {
+        self.quantities.get(&symbol).cloned().unwrap_or_else(d128::zero)
+    }
+
+    /// The amounts making up this balance, ordered by symbol. Commodities that sum to zero are
+    /// left out.
+    pub fn amounts(&self) -> Vec<Amount> {
+        self.quantities.iter()
+            .filter(|&(_, quantity)| !quantity.is_zero())
+            .map(|(symbol, quantity)| Amount::new(*quantity, *symbol))
+            .collect()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.quantities.values().all(|quantity| quantity.is_zero())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::symbol::*;
+
+    #[test]
+    fn balance_sums_per_commodity() {
+        let dollars = Symbol::new("$", QuoteOption::Unquoted);
+        let fund = Symbol::new("MUTF2351", QuoteOption::Quoted);
+        let mut balance = Balance::new();
+        balance.add_amount(&Amount::new(d128!(45.00), dollars));
+        balance.add_amount(&Amount::new(d128!(13.508), fund));
+        balance.add_amount(&Amount::new(d128!(-5.00), dollars));
+        assert_eq!(balance.quantity(dollars), d128!(40.00));
+        assert_eq!(balance.quantity(fund), d128!(13.508));
+    }
+
+    #[test]
+    fn balance_amounts_skip_zero_commodities() {
+        let dollars = Symbol::new("$", QuoteOption::Unquoted);
+        let fund = Symbol::new("MUTF2351", QuoteOption::Quoted);
+        let mut balance = Balance::new();
+        balance.add_amount(&Amount::new(d128!(45.00), dollars));
+        balance.add_amount(&Amount::new(d128!(-45.00), dollars));
+        balance.add_amount(&Amount::new(d128!(13.508), fund));
+        assert_eq!(balance.amounts(), vec![Amount::new(d128!(13.508), fund)]);
+        assert!(!balance.is_zero());
+    }
+
+    #[test]
+    fn balance_add_balance() {
+        let dollars = Symbol::new("$", QuoteOption::Unquoted);
+        let mut first = Balance::new();
+        first.add_amount(&Amount::new(d128!(45.00), dollars));
+        let mut second = Balance::new();
+        second.add_amount(&Amount::new(d128!(-45.00), dollars));
+        first.add_balance(&second);
+        assert!(first.is_zero());
+    }
+}
@@ -0,0 +1,389 @@
+use decimal::d128;
+use std::collections::{HashMap, HashSet};
+use super::amount::{decimal_places, round, RoundingMode};
+use super::symbol::Symbol;
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SymbolPosition {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Spacing {
+    Space,
+    NoSpace,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ThousandsSeparator {
+    Comma,
+    NoSeparator,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RenderOptions {
+    symbol_position: SymbolPosition,
+    spacing: Spacing,
+    thousands_separator: ThousandsSeparator,
+    precision: u32,
+}
+
+impl RenderOptions {
+    pub fn new(position: SymbolPosition, spacing: Spacing, separator: ThousandsSeparator,
+    precision: u32) -> RenderOptions {
+        RenderOptions {
+            symbol_position: position,
+            spacing: spacing,
+            thousands_separator: separator,
+            precision: precision,
+        }
+    }
+
+    pub fn precision(&self) -> u32 {
+        self.precision
+    }
+
+    /// Returns a copy of these render options with a different display precision.
+    pub fn with_precision(&self, precision: u32) -> RenderOptions {
+        RenderOptions {
+            precision: precision,
+            .. *self
+        }
+    }
+}
+
+/// A commodity and how amounts of it should be rendered.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Commodity {
+    symbol: Symbol,
+    render_options: RenderOptions,
+}
+
+impl Commodity {
+    pub fn new(symbol: Symbol, render_options: RenderOptions) -> Commodity {
+        Commodity {
+            symbol: symbol,
+            render_options: render_options,
+        }
+    }
+
+    pub fn symbol(&self) -> Symbol {
+        self.symbol
+    }
+
+    pub fn render_options(&self) -> &RenderOptions {
+        &self.render_options
+    }
+
+    /// Renders a quantity of this commodity, rounded to the commodity's display precision.
+    pub fn format(&self, quantity: d128) -> String {
+        let options = &self.render_options;
+        let spacing =
+            match options.spacing {
+                Spacing::Space => " ",
+                Spacing::NoSpace => "",
+            };
+        let quantity = format_quantity(quantity, options.precision, options.thousands_separator);
+
+        match options.symbol_position {
+            SymbolPosition::Left => format!("{}{}{}", self.symbol, spacing, quantity),
+            SymbolPosition::Right => format!("{}{}{}", quantity, spacing, self.symbol),
+        }
+    }
+}
+
+/// Registry of known commodities, which decides how amounts of each symbol are rendered.
+///
+/// Render options come from a `commodity` directive if there is one, otherwise from the first
+/// amount seen with that symbol. Undeclared commodities are displayed with the largest precision
+/// any amount of that symbol has been written with.
+#[derive(Debug)]
+pub struct CommodityRegistry {
+    commodities: HashMap<Symbol, Commodity>,
+    declared: HashSet<Symbol>,
+}
+
+impl CommodityRegistry {
+    pub fn new() -> CommodityRegistry {
+        CommodityRegistry {
+            commodities: HashMap::new(),
+            declared: HashSet::new(),
+        }
+    }
+
+    /// Records a commodity from a directive, replacing any observed render options.
+    pub fn declare(&mut self, commodity: Commodity) {
+        self.declared.insert(commodity.symbol);
+        self.commodities.insert(commodity.symbol, commodity);
+    }
+
+    /// Records a commodity as it was used in an amount. The first use of a symbol decides its
+    /// render options, and later uses can only raise its precision. Declared commodities are
+    /// never changed.
+    pub fn observe(&mut self, commodity: Commodity) {
+        if self.declared.contains(&commodity.symbol) {
+            return;
+        }
+
+        let precision = commodity.render_options.precision;
+        let existing = self.commodities.entry(commodity.symbol).or_insert(commodity);
+        if precision > existing.render_options.precision {
+            existing.render_options = existing.render_options.with_precision(precision);
+        }
+    }
+
+    pub fn get(&self, symbol: Symbol) -> Option<&Commodity> {
+        self.commodities.get(&symbol)
+    }
+
+    /// The display precision of the given symbol, if the registry has seen it.
+    pub fn precision(&self, symbol: Symbol) -> Option<u32> {
+        self.get(symbol).map(|commodity| commodity.render_options.precision)
+    }
+
+    /// The symbol the registry knows by the given name, however it is quoted.
+    pub fn find(&self, name: &str) -> Option<Symbol> {
+        self.commodities.keys().filter(|symbol| symbol.name() == name).min().cloned()
+    }
+
+    pub fn is_declared(&self, symbol: Symbol) -> bool {
+        self.declared.contains(&symbol)
+    }
+
+    pub fn len(&self) -> usize {
+        self.commodities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commodities.is_empty()
+    }
+
+    /// Renders a quantity of the given symbol. Symbols the registry has never seen are rendered
+    /// as the raw quantity followed by the symbol.
+    pub fn format(&self, quantity: d128, symbol: Symbol) -> String {
+        match self.get(symbol) {
+            Some(commodity) => commodity.format(quantity),
+            None => format!("{} {}", quantity, symbol),
+        }
+    }
+
+    /// Renders a quantity like `format`, but widens the precision if needed so no digits are
+    /// lost, e.g. for a residual smaller than the commodity's display precision.
+    pub fn format_exact(&self, quantity: d128, symbol: Symbol) -> String {
+        match self.get(symbol) {
+            Some(commodity) => {
+                let options = commodity.render_options();
+                let precision = ::std::cmp::max(options.precision(), decimal_places(quantity));
+                Commodity::new(symbol, options.with_precision(precision)).format(quantity)
+            },
+            None => format!("{} {}", quantity, symbol),
+        }
+    }
+}
+
+impl Default for CommodityRegistry {
+    fn default() -> CommodityRegistry {
+        CommodityRegistry::new()
+    }
+}
+
+
+
+// HELPERS
+
+/// Rounds a quantity to the given number of decimal places and renders it, optionally grouping
+/// the integer part into thousands.
+fn format_quantity(quantity: d128, precision: u32, separator: ThousandsSeparator) -> String {
+    let rounded = round(quantity, precision, RoundingMode::HalfEven);
+    let rendered =
+        if rounded.is_zero() {
+            format!("{}", rounded.abs())
+        } else {
+            format!("{}", rounded)
+        };
+
+    match separator {
+        ThousandsSeparator::NoSeparator => rendered,
+        ThousandsSeparator::Comma => {
+            let (sign, unsigned) =
+                match rendered.strip_prefix('-') {
+                    Some(unsigned) => ("-", unsigned),
+                    None => ("", &rendered[..]),
+                };
+            let (integer, fraction) =
+                match unsigned.find('.') {
+                    Some(index) => (&unsigned[..index], &unsigned[index..]),
+                    None => (unsigned, ""),
+                };
+
+            let mut grouped = String::new();
+            for (index, digit) in integer.chars().enumerate() {
+                if index > 0 && (integer.len() - index) % 3 == 0 {
+                    grouped.push(',');
+                }
+                grouped.push(digit);
+            }
+
+            format!("{}{}{}", sign, grouped, fraction)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::symbol::*;
+
+    fn dollars() -> Commodity {
+        Commodity::new(
+            Symbol::new("$", QuoteOption::Unquoted),
+            RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+                ThousandsSeparator::Comma, 2))
+    }
+
+    #[test]
+    fn commodity_format_symbol_left_with_space() {
+        let result = Commodity::new(
+            Symbol::new("US$", QuoteOption::Unquoted),
+            RenderOptions::new(SymbolPosition::Left, Spacing::Space,
+                ThousandsSeparator::NoSeparator, 2))
+            .format(d128!(13245.00));
+        assert_eq!(result, "US$ 13245.00");
+    }
+
+    #[test]
+    fn commodity_format_symbol_left_no_space() {
+        let result = Commodity::new(
+            Symbol::new("$", QuoteOption::Unquoted),
+            RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+                ThousandsSeparator::NoSeparator, 2))
+            .format(d128!(13245.00));
+        assert_eq!(result, "$13245.00");
+    }
+
+    #[test]
+    fn commodity_format_symbol_right_with_space() {
+        let result = Commodity::new(
+            Symbol::new("MUTF2351", QuoteOption::Quoted),
+            RenderOptions::new(SymbolPosition::Right, Spacing::Space,
+                ThousandsSeparator::NoSeparator, 3))
+            .format(d128!(13245.463));
+        assert_eq!(result, "13245.463 \"MUTF2351\"");
+    }
+
+    #[test]
+    fn commodity_format_symbol_right_no_space() {
+        let result = Commodity::new(
+            Symbol::new("RUST", QuoteOption::Unquoted),
+            RenderOptions::new(SymbolPosition::Right, Spacing::NoSpace,
+                ThousandsSeparator::NoSeparator, 3))
+            .format(d128!(13245.463));
+        assert_eq!(result, "13245.463RUST");
+    }
+
+    #[test]
+    fn commodity_format_thousands_separator() {
+        let result = dollars().format(d128!(-1234567.5));
+        assert_eq!(result, "$-1,234,567.50");
+    }
+
+    #[test]
+    fn commodity_format_rounds_to_precision() {
+        let result = dollars().format(d128!(71659.73483));
+        assert_eq!(result, "$71,659.73");
+    }
+
+    #[test]
+    fn commodity_format_zero_precision() {
+        let result = Commodity::new(
+            Symbol::new("AAPL", QuoteOption::Unquoted),
+            RenderOptions::new(SymbolPosition::Right, Spacing::Space,
+                ThousandsSeparator::Comma, 0))
+            .format(d128!(1000));
+        assert_eq!(result, "1,000 AAPL");
+    }
+
+    #[test]
+    fn registry_observe_keeps_first_use() {
+        let mut registry = CommodityRegistry::new();
+        registry.observe(dollars());
+        registry.observe(Commodity::new(
+            Symbol::new("$", QuoteOption::Unquoted),
+            RenderOptions::new(SymbolPosition::Left, Spacing::Space,
+                ThousandsSeparator::NoSeparator, 0)));
+        assert_eq!(registry.format(d128!(5), Symbol::new("$", QuoteOption::Unquoted)), "$5.00");
+    }
+
+    #[test]
+    fn registry_observe_tracks_max_precision() {
+        let mut registry = CommodityRegistry::new();
+        registry.observe(dollars());
+        registry.observe(Commodity::new(
+            Symbol::new("$", QuoteOption::Unquoted),
+            RenderOptions::new(SymbolPosition::Right, Spacing::Space,
+                ThousandsSeparator::NoSeparator, 4)));
+        assert_eq!(registry.precision(Symbol::new("$", QuoteOption::Unquoted)), Some(4));
+        assert_eq!(registry.format(d128!(1234.5), Symbol::new("$", QuoteOption::Unquoted)),
+            "$1,234.5000");
+    }
+
+    #[test]
+    fn registry_observe_does_not_change_declared_precision() {
+        let mut registry = CommodityRegistry::new();
+        registry.declare(dollars());
+        registry.observe(Commodity::new(
+            Symbol::new("$", QuoteOption::Unquoted),
+            RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+                ThousandsSeparator::Comma, 4)));
+        assert_eq!(registry.precision(Symbol::new("$", QuoteOption::Unquoted)), Some(2));
+    }
+
+    #[test]
+    fn registry_declare_replaces_observed() {
+        let mut registry = CommodityRegistry::new();
+        registry.observe(Commodity::new(
+            Symbol::new("$", QuoteOption::Unquoted),
+            RenderOptions::new(SymbolPosition::Left, Spacing::Space,
+                ThousandsSeparator::NoSeparator, 0)));
+        registry.declare(dollars());
+        registry.observe(Commodity::new(
+            Symbol::new("$", QuoteOption::Unquoted),
+            RenderOptions::new(SymbolPosition::Right, Spacing::Space,
+                ThousandsSeparator::NoSeparator, 0)));
+        assert!(registry.is_declared(Symbol::new("$", QuoteOption::Unquoted)));
+        assert_eq!(registry.format(d128!(5), Symbol::new("$", QuoteOption::Unquoted)), "$5.00");
+    }
+
+    #[test]
+    fn registry_format_unknown_symbol() {
+        let registry = CommodityRegistry::new();
+        let result = registry.format(d128!(13.508), Symbol::new("MUTF2351", QuoteOption::Quoted));
+        assert_eq!(result, "13.508 \"MUTF2351\"");
+    }
+
+    #[test]
+    fn registry_format_exact_keeps_extra_digits() {
+        let mut registry = CommodityRegistry::new();
+        registry.declare(dollars());
+        let dollars = Symbol::new("$", QuoteOption::Unquoted);
+        assert_eq!(registry.format_exact(d128!(0.00153), dollars), "$0.00153");
+        assert_eq!(registry.format_exact(d128!(1234.5), dollars), "$1,234.50");
+    }
+
+    #[test]
+    fn registry_find_ignores_quotes() {
+        let mut registry = CommodityRegistry::new();
+        let fund = Symbol::new("AAPL", QuoteOption::Quoted);
+        registry.observe(Commodity::new(fund, *dollars().render_options()));
+        assert_eq!(registry.find("AAPL"), Some(fund));
+        assert_eq!(registry.find("MUTF2351"), None);
+    }
+}
@@ -0,0 +1,155 @@
+use rust_core::str::FromStr;
+use chrono::{Date, Datelike};
+use chrono::offset::{Local, TimeZone};
+use std::collections::HashMap;
+use super::price::Price;
+use super::symbol::Symbol;
+
+
+/// How many old prices to keep when compacting: one per month or one per week.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Resolution {
+    MonthEnd,
+    WeekEnd,
+}
+
+impl FromStr for Resolution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Resolution, String> {
+        match s {
+            "month" => Ok(Resolution::MonthEnd),
+            "week" => Ok(Resolution::WeekEnd),
+            _ => Err(format!("unknown resolution: {}", s)),
+        }
+    }
+}
+
+/// The prices kept and removed by compacting.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Compaction {
+    kept: Vec<Price>,
+    removed: Vec<Price>,
+}
+
+impl Compaction {
+    /// The prices kept, in the order given.
+    pub fn kept(&self) -> &[Price] {
+        &self.kept
+    }
+
+    /// The prices removed, in the order given.
+    pub fn removed(&self) -> &[Price] {
+        &self.removed
+    }
+}
+
+/// Thins out old prices. Every price on or after `keep_since` is kept; before it, only the last
+/// price of each commodity (in each quote commodity) in each month or week is kept, which is the
+/// month-end or week-end price when prices are recorded daily.
+pub fn compact_prices(prices: &[Price], keep_since: Date<Local>, resolution: Resolution)
+-> Compaction {
+    let mut last_in_period: HashMap<(Symbol, Symbol, i32, u32), usize> = HashMap::new();
+    for (index, price) in prices.iter().enumerate() {
+        if price.date() >= keep_since {
+            continue;
+        }
+        let date = price.date();
+        let (year, period) = match resolution {
+            Resolution::MonthEnd => (date.year(), date.month()),
+            Resolution::WeekEnd => (date.iso_week().year(), date.iso_week().week()),
+        };
+        let last = last_in_period.entry((price.symbol(), price.amount().symbol(), year, period))
+            .or_insert(index);
+        if prices[*last].sort_key() <= price.sort_key() {
+            *last = index;
+        }
+    }
+
+    let mut keep: Vec<bool> = prices.iter().map(|price| price.date() >= keep_since).collect();
+    for index in last_in_period.values() {
+        keep[*index] = true;
+    }
+
+    let (kept, removed): (Vec<_>, Vec<_>) = prices.iter().cloned().zip(keep)
+        .partition(|&(_, keep)| keep);
+    Compaction {
+        kept: kept.into_iter().map(|(price, _)| price).collect(),
+        removed: removed.into_iter().map(|(price, _)| price).collect(),
+    }
+}
+
+/// The same day of the month, `months` months before `date`, or the last day of that month if
+/// it is shorter.
+pub fn months_before(date: Date<Local>, months: u32) -> Date<Local> {
+    let total = date.year() * 12 + date.month0() as i32 - months as i32;
+    let (year, month) = (total.div_euclid(12), total.rem_euclid(12) as u32 + 1);
+    (0..4).map(|back| date.day() - back)
+        .filter_map(|day| Local.ymd_opt(year, month, day).single())
+        .next()
+        .expect("a valid day of the month")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::amount::Amount;
+    use core::symbol::*;
+
+    fn price(month: u32, day: u32, symbol: &str) -> Price {
+        Price::new(Local.ymd(2016, month, day), Symbol::new(symbol, QuoteOption::Quoted),
+            Amount::new(d128!(5.41), Symbol::new("$", QuoteOption::Unquoted)))
+    }
+
+    fn prices() -> Vec<Price> {
+        vec![
+            price(1, 28, "MUTF2351"),
+            price(1, 29, "MUTF2351"),
+            price(1, 29, "AAPL"),
+            price(2, 1, "MUTF2351"),
+            price(2, 2, "MUTF2351"),
+            price(2, 3, "MUTF2351"),
+            price(2, 4, "MUTF2351"),
+        ]
+    }
+
+    #[test]
+    fn compact_prices_keeps_month_ends_before_cutoff() {
+        let compaction = compact_prices(&prices(), Local.ymd(2016, 2, 3), Resolution::MonthEnd);
+        assert_eq!(compaction.kept(), &[
+            price(1, 29, "MUTF2351"),
+            price(1, 29, "AAPL"),
+            price(2, 2, "MUTF2351"),
+            price(2, 3, "MUTF2351"),
+            price(2, 4, "MUTF2351"),
+        ]);
+        assert_eq!(compaction.removed(), &[price(1, 28, "MUTF2351"), price(2, 1, "MUTF2351")]);
+    }
+
+    #[test]
+    fn compact_prices_keeps_week_ends_before_cutoff() {
+        // 2016-01-28 and 2016-01-29 are in one ISO week, 2016-02-01 to 2016-02-04 in the next.
+        let compaction = compact_prices(&prices(), Local.ymd(2016, 2, 4), Resolution::WeekEnd);
+        assert_eq!(compaction.kept(), &[
+            price(1, 29, "MUTF2351"),
+            price(1, 29, "AAPL"),
+            price(2, 3, "MUTF2351"),
+            price(2, 4, "MUTF2351"),
+        ]);
+    }
+
+    #[test]
+    fn compact_prices_keeps_everything_after_cutoff() {
+        let compaction = compact_prices(&prices(), Local.ymd(2015, 12, 1), Resolution::MonthEnd);
+        assert_eq!(compaction.kept(), &prices()[..]);
+        assert!(compaction.removed().is_empty());
+    }
+
+    #[test]
+    fn months_before_clamps_day() {
+        assert_eq!(months_before(Local.ymd(2016, 3, 31), 1), Local.ymd(2016, 2, 29));
+        assert_eq!(months_before(Local.ymd(2016, 2, 7), 14), Local.ymd(2014, 12, 7));
+        assert_eq!(months_before(Local.ymd(2016, 2, 7), 0), Local.ymd(2016, 2, 7));
+    }
+}
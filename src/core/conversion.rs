@@ -0,0 +1,393 @@
+use chrono::Date;
+use chrono::offset::Local;
+use decimal::d128;
+use std::collections::BTreeMap;
+use std::fmt;
+use super::amount::Amount;
+use super::balance::Balance;
+use super::price_history::PriceHistory;
+use super::symbol::Symbol;
+
+
+/// One hop of a conversion: the rate from one commodity to another and the date of the price it
+/// came from. An inverted step uses a price of `to` quoted in `from`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ConversionStep {
+    from: Symbol,
+    to: Symbol,
+    rate: d128,
+    date: Date<Local>,
+    inverted: bool,
+}
+
+impl ConversionStep {
+    pub fn from(&self) -> Symbol {
+        self.from
+    }
+
+    pub fn to(&self) -> Symbol {
+        self.to
+    }
+
+    pub fn rate(&self) -> d128 {
+        self.rate
+    }
+
+    /// The date of the price the rate came from.
+    pub fn date(&self) -> Date<Local> {
+        self.date
+    }
+
+    pub fn inverted(&self) -> bool {
+        self.inverted
+    }
+}
+
+impl fmt::Display for ConversionStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} -> {} at {} ({}{})", self.from, self.to, self.rate.reduce(),
+            if self.inverted { "inverted price from " } else { "" },
+            self.date.format("%Y-%m-%d"))
+    }
+}
+
+/// An amount converted to another commodity, with the steps taken to get there.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Conversion {
+    original: Amount,
+    converted: Amount,
+    steps: Vec<ConversionStep>,
+}
+
+impl Conversion {
+    pub fn original(&self) -> &Amount {
+        &self.original
+    }
+
+    pub fn converted(&self) -> &Amount {
+        &self.converted
+    }
+
+    /// The steps taken, which is empty if the amount was already in the target commodity.
+    pub fn steps(&self) -> &[ConversionStep] {
+        &self.steps
+    }
+
+    /// The commodities passed through, from the original commodity to the target.
+    pub fn path(&self) -> Vec<Symbol> {
+        let mut path = vec![self.original.symbol()];
+        path.extend(self.steps.iter().map(|step| step.to));
+        path
+    }
+}
+
+/// A multi-commodity balance converted to a single commodity.
+#[derive(Clone, PartialEq, Debug)]
+pub struct BalanceConversion {
+    total: Amount,
+    conversions: Vec<Conversion>,
+}
+
+impl BalanceConversion {
+    pub fn total(&self) -> &Amount {
+        &self.total
+    }
+
+    /// The conversion of each amount in the balance, ordered by symbol.
+    pub fn conversions(&self) -> &[Conversion] {
+        &self.conversions
+    }
+}
+
+/// No chain of prices leads from one commodity to another on or before a date.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ConversionError {
+    from: Symbol,
+    to: Symbol,
+    date: Date<Local>,
+}
+
+impl ConversionError {
+    pub fn from(&self) -> Symbol {
+        self.from
+    }
+
+    pub fn to(&self) -> Symbol {
+        self.to
+    }
+
+    pub fn date(&self) -> Date<Local> {
+        self.date
+    }
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no prices to convert {} to {} as of {}", self.from, self.to,
+            self.date.format("%Y-%m-%d"))
+    }
+}
+
+/// Converts amounts between commodities by treating the price history as a graph. Each
+/// commodity is a node, and the latest price of a pair on or before the conversion date links
+/// them in both directions, inverting the price to go from the quote commodity back.
+///
+/// The path with the fewest hops is used. Among paths of the same length, the one whose oldest
+/// price is most recent wins.
+pub struct Converter<'a> {
+    history: &'a PriceHistory,
+}
+
+impl<'a> Converter<'a> {
+    pub fn new(history: &'a PriceHistory) -> Converter<'a> {
+        Converter {
+            history: history,
+        }
+    }
+
+    /// Finds the steps to convert `from` to `to` using prices on or before `date`.
+    pub fn path(&self, from: Symbol, to: Symbol, date: Date<Local>)
+    -> Result<Vec<ConversionStep>, ConversionError> {
+        let edges = self.edges(date);
+        let mut reached = BTreeMap::new();
+        reached.insert(from, Vec::new());
+        let mut frontier = vec![from];
+        while !frontier.is_empty() && !reached.contains_key(&to) {
+            let mut next: BTreeMap<Symbol, Vec<ConversionStep>> = BTreeMap::new();
+            for symbol in &frontier {
+                for edge in edges.get(symbol).map(|e| &e[..]).unwrap_or(&[]) {
+                    if reached.contains_key(&edge.to) {
+                        continue;
+                    }
+                    let mut candidate = reached[symbol].clone();
+                    candidate.push(edge.clone());
+                    let better = match next.get(&edge.to) {
+                        Some(existing) => oldest(&candidate) > oldest(existing),
+                        None => true,
+                    };
+                    if better {
+                        next.insert(edge.to, candidate);
+                    }
+                }
+            }
+            frontier = next.keys().cloned().collect();
+            reached.extend(next);
+        }
+        reached.remove(&to).ok_or(ConversionError {
+            from: from,
+            to: to,
+            date: date,
+        })
+    }
+
+    /// Converts an amount to the target commodity using prices on or before `date`.
+    pub fn convert(&self, amount: &Amount, target: Symbol, date: Date<Local>)
+    -> Result<Conversion, ConversionError> {
+        let steps = self.path(amount.symbol(), target, date)?;
+        let quantity = steps.iter().fold(amount.quantity(), |quantity, step| quantity * step.rate);
+        Ok(Conversion {
+            original: *amount,
+            converted: Amount::new(quantity, target),
+            steps: steps,
+        })
+    }
+
+    /// Converts every amount in a balance to the target commodity and sums them, reporting
+    /// every commodity that couldn't be converted.
+    pub fn convert_balance(&self, balance: &Balance, target: Symbol, date: Date<Local>)
+    -> Result<BalanceConversion, Vec<ConversionError>> {
+        let mut total = d128::zero();
+        let mut conversions = Vec::new();
+        let mut errors = Vec::new();
+        for amount in balance.amounts() {
+            match self.convert(&amount, target, date) {
+                Ok(conversion) => {
+                    total += conversion.converted.quantity();
+                    conversions.push(conversion);
+                },
+                Err(err) => errors.push(err),
+            }
+        }
+        if errors.is_empty() {
+            Ok(BalanceConversion {
+                total: Amount::new(total, target),
+                conversions: conversions,
+            })
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// The rates out of each commodity as of `date`.
+    fn edges(&self, date: Date<Local>) -> BTreeMap<Symbol, Vec<ConversionStep>> {
+        let mut edges: BTreeMap<Symbol, Vec<ConversionStep>> = BTreeMap::new();
+        for (symbol, quote) in self.history.pairs() {
+            let price = match self.history.latest(symbol, quote, date) {
+                Some(price) if !price.amount().quantity().is_zero() => price,
+                _ => continue,
+            };
+            let rate = price.amount().quantity();
+            edges.entry(symbol).or_default().push(ConversionStep {
+                from: symbol,
+                to: quote,
+                rate: rate,
+                date: price.date(),
+                inverted: false,
+            });
+            edges.entry(quote).or_default().push(ConversionStep {
+                from: quote,
+                to: symbol,
+                rate: d128!(1) / rate,
+                date: price.date(),
+                inverted: true,
+            });
+        }
+        edges
+    }
+}
+
+
+
+// HELPERS
+
+fn oldest(steps: &[ConversionStep]) -> Option<Date<Local>> {
+    steps.iter().map(|step| step.date).min()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::offset::TimeZone;
+    use core::price::Price;
+    use core::symbol::*;
+
+    fn fund() -> Symbol {
+        Symbol::new("MUTF2351", QuoteOption::Quoted)
+    }
+
+    fn usd() -> Symbol {
+        Symbol::new("$", QuoteOption::Unquoted)
+    }
+
+    fn cad() -> Symbol {
+        Symbol::new("CAD", QuoteOption::Unquoted)
+    }
+
+    fn eur() -> Symbol {
+        Symbol::new("EUR", QuoteOption::Unquoted)
+    }
+
+    fn price(day: u32, symbol: Symbol, quantity: d128, quote: Symbol) -> Price {
+        Price::new(Local.ymd(2016, 2, day), symbol, Amount::new(quantity, quote))
+    }
+
+    fn history() -> PriceHistory {
+        PriceHistory::from_prices(&[
+            price(7, fund(), d128!(5.41), usd()),
+            price(9, fund(), d128!(7.10), usd()),
+            price(8, usd(), d128!(1.25), cad()),
+            price(8, eur(), d128!(1.5), cad()),
+        ])
+    }
+
+    #[test]
+    fn convert_same_commodity_is_unchanged() {
+        let history = history();
+        let converter = Converter::new(&history);
+        let amount = Amount::new(d128!(10), usd());
+        let conversion = converter.convert(&amount, usd(), Local.ymd(2016, 2, 9)).unwrap();
+        assert_eq!(*conversion.converted(), amount);
+        assert!(conversion.steps().is_empty());
+    }
+
+    #[test]
+    fn convert_uses_latest_price_on_or_before_date() {
+        let history = history();
+        let converter = Converter::new(&history);
+        let amount = Amount::new(d128!(10), fund());
+        let conversion = converter.convert(&amount, usd(), Local.ymd(2016, 2, 8)).unwrap();
+        assert_eq!(*conversion.converted(), Amount::new(d128!(54.10), usd()));
+        assert_eq!(conversion.steps()[0].date(), Local.ymd(2016, 2, 7));
+    }
+
+    #[test]
+    fn convert_multi_hop() {
+        let history = history();
+        let converter = Converter::new(&history);
+        let amount = Amount::new(d128!(10), fund());
+        let conversion = converter.convert(&amount, cad(), Local.ymd(2016, 2, 9)).unwrap();
+        assert_eq!(conversion.path(), vec![fund(), usd(), cad()]);
+        assert_eq!(*conversion.converted(), Amount::new(d128!(88.75), cad()));
+        assert_eq!(format!("{}", conversion.steps()[1]), "$ -> CAD at 1.25 (2016-02-08)");
+    }
+
+    #[test]
+    fn convert_inverts_prices() {
+        let history = history();
+        let converter = Converter::new(&history);
+        let amount = Amount::new(d128!(15), cad());
+        let conversion = converter.convert(&amount, eur(), Local.ymd(2016, 2, 9)).unwrap();
+        assert_eq!(conversion.converted().quantity().reduce(), d128!(10));
+        assert!(conversion.steps()[0].inverted());
+        assert_eq!(format!("{}", conversion.steps()[0]),
+            "CAD -> EUR at 0.6666666666666666666666666666666667 \
+            (inverted price from 2016-02-08)");
+    }
+
+    #[test]
+    fn path_prefers_fewest_hops() {
+        let mut history = history();
+        history.add(price(1, fund(), d128!(7.00), cad()));
+        let converter = Converter::new(&history);
+        let steps = converter.path(fund(), cad(), Local.ymd(2016, 2, 9)).unwrap();
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].date(), Local.ymd(2016, 2, 1));
+    }
+
+    #[test]
+    fn path_among_equal_lengths_prefers_most_recent_oldest_price() {
+        let history = PriceHistory::from_prices(&[
+            price(1, fund(), d128!(5.00), usd()),
+            price(1, usd(), d128!(1.30), cad()),
+            price(8, fund(), d128!(4.00), eur()),
+            price(8, eur(), d128!(1.60), cad()),
+        ]);
+        let converter = Converter::new(&history);
+        let steps = converter.path(fund(), cad(), Local.ymd(2016, 2, 9)).unwrap();
+        assert_eq!(steps.iter().map(|s| s.to()).collect::<Vec<_>>(), vec![eur(), cad()]);
+    }
+
+    #[test]
+    fn convert_without_path_is_error() {
+        let history = history();
+        let converter = Converter::new(&history);
+        let amount = Amount::new(d128!(10), fund());
+        let err = converter.convert(&amount, cad(), Local.ymd(2016, 2, 6)).unwrap_err();
+        assert_eq!(format!("{}", err), "no prices to convert \"MUTF2351\" to CAD as of 2016-02-06");
+    }
+
+    #[test]
+    fn convert_balance_sums_conversions() {
+        let history = history();
+        let converter = Converter::new(&history);
+        let mut balance = Balance::new();
+        balance.add_amount(&Amount::new(d128!(10), fund()));
+        balance.add_amount(&Amount::new(d128!(20), usd()));
+        balance.add_amount(&Amount::new(d128!(5), cad()));
+        let result = converter.convert_balance(&balance, cad(), Local.ymd(2016, 2, 9)).unwrap();
+        assert_eq!(*result.total(), Amount::new(d128!(118.75), cad()));
+        assert_eq!(result.conversions().len(), 3);
+    }
+
+    #[test]
+    fn convert_balance_reports_every_missing_path() {
+        let history = history();
+        let converter = Converter::new(&history);
+        let mut balance = Balance::new();
+        balance.add_amount(&Amount::new(d128!(10), fund()));
+        balance.add_amount(&Amount::new(d128!(1), Symbol::new("AAPL", QuoteOption::Unquoted)));
+        let errors = converter.convert_balance(&balance, eur(), Local.ymd(2016, 2, 7)).unwrap_err();
+        assert_eq!(errors.len(), 2);
+    }
+}
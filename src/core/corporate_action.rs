@@ -0,0 +1,278 @@
+use chrono::Date;
+use chrono::offset::Local;
+use decimal::d128;
+use std::fmt;
+use super::amount::Amount;
+use super::price::Price;
+use super::price_history::PriceHistory;
+use super::symbol::Symbol;
+
+
+/// What happens to a commodity in a corporate action.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ActionKind {
+    /// Every `old` units held become `new` units, e.g. 2:1 for a 2-for-1 split or 1:10 for a
+    /// 1-for-10 reverse split. Prices scale the other way.
+    Split { new: d128, old: d128 },
+    /// The commodity is known by a new symbol, e.g. after a ticker change.
+    Rename(Symbol),
+}
+
+/// A split or rename of a commodity, taking effect at the start of its date. Amounts and prices
+/// dated before it are in the old units or symbol; those dated on or after it are in the new.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CorporateAction {
+    #[cfg_attr(feature = "serde", serde(with = "::core::serde_date"))]
+    date: Date<Local>,
+    symbol: Symbol,
+    kind: ActionKind,
+}
+
+impl CorporateAction {
+    /// A split of `symbol` where every `old` units become `new` units.
+    pub fn split(date: Date<Local>, symbol: Symbol, new: d128, old: d128) -> CorporateAction {
+        CorporateAction {
+            date: date,
+            symbol: symbol,
+            kind: ActionKind::Split { new: new, old: old },
+        }
+    }
+
+    /// A rename of the commodity `from` to `to`.
+    pub fn rename(date: Date<Local>, from: Symbol, to: Symbol) -> CorporateAction {
+        CorporateAction {
+            date: date,
+            symbol: from,
+            kind: ActionKind::Rename(to),
+        }
+    }
+
+    pub fn date(&self) -> Date<Local> {
+        self.date
+    }
+
+    /// The commodity affected, by its symbol before the action.
+    pub fn symbol(&self) -> Symbol {
+        self.symbol
+    }
+
+    pub fn kind(&self) -> ActionKind {
+        self.kind
+    }
+
+    /// Restates an amount held before this action in terms of after it.
+    fn apply_to_amount(&self, amount: &Amount) -> Amount {
+        if amount.symbol() != self.symbol {
+            return *amount;
+        }
+        match self.kind {
+            ActionKind::Split { new, old } =>
+                Amount::new(amount.quantity() * new / old, self.symbol),
+            ActionKind::Rename(to) => Amount::new(amount.quantity(), to),
+        }
+    }
+
+    /// Restates a price recorded before this action in terms of after it. Both the priced
+    /// commodity and the commodity it is quoted in are adjusted.
+    fn apply_to_price(&self, price: &Price) -> Price {
+        let mut symbol = price.symbol();
+        let mut amount = self.apply_to_amount(price.amount());
+        if symbol == self.symbol {
+            match self.kind {
+                ActionKind::Split { new, old } =>
+                    amount = Amount::new(amount.quantity() * old / new, amount.symbol()),
+                ActionKind::Rename(to) => symbol = to,
+            }
+        }
+        Price::new(price.date(), symbol, amount)
+            .with_time(price.time())
+            .with_origin(price.origin())
+    }
+}
+
+impl fmt::Display for CorporateAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ActionKind::Split { new, old } => write!(f, "split {} {} {}:{}",
+                self.date.format("%Y-%m-%d"), self.symbol, new, old),
+            ActionKind::Rename(to) => write!(f, "rename {} {} {}",
+                self.date.format("%Y-%m-%d"), self.symbol, to),
+        }
+    }
+}
+
+/// The corporate actions declared in a journal, used to adjust amounts and prices recorded at
+/// one date so they line up with those at a later date. The recorded transactions and prices
+/// are never changed.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct CorporateActions {
+    actions: Vec<CorporateAction>,
+}
+
+impl CorporateActions {
+    /// Collects actions in any order. Actions on the same date are applied in the order given.
+    pub fn new(mut actions: Vec<CorporateAction>) -> CorporateActions {
+        actions.sort_by_key(|action| action.date());
+        CorporateActions {
+            actions: actions,
+        }
+    }
+
+    /// Every action, sorted by date.
+    pub fn actions(&self) -> &[CorporateAction] {
+        &self.actions
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// Restates an amount recorded on `from` as of `to`, applying every split and rename after
+    /// `from` and up to `to`. e.g. 10 shares bought before a 2:1 split are 20 shares after it.
+    pub fn adjust_amount(&self, amount: &Amount, from: Date<Local>, to: Date<Local>) -> Amount {
+        self.between(from, to)
+            .fold(*amount, |amount, action| action.apply_to_amount(&amount))
+    }
+
+    /// Restates a price as of `to`, applying every split and rename after the price's date and
+    /// up to `to`. e.g. a $10 price before a 2:1 split is $5 after it.
+    pub fn adjust_price(&self, price: &Price, to: Date<Local>) -> Price {
+        self.between(price.date(), to)
+            .fold(price.clone(), |price, action| action.apply_to_price(&price))
+    }
+
+    /// Restates every price in a history as of `to`, so a commodity's prices form one series
+    /// across splits and renames. Prices dated after `to` are left as they are.
+    pub fn adjust_history(&self, history: &PriceHistory, to: Date<Local>) -> PriceHistory {
+        if self.actions.is_empty() {
+            return history.clone();
+        }
+        let prices: Vec<Price> = history.pairs().into_iter()
+            .flat_map(|(symbol, quote)| history.series(symbol, quote).iter())
+            .map(|price| self.adjust_price(price, to))
+            .collect();
+        PriceHistory::from_prices(&prices)
+    }
+
+    /// The actions taking effect after `from` and on or before `to`.
+    fn between<'a>(&'a self, from: Date<Local>, to: Date<Local>)
+    -> impl Iterator<Item=&'a CorporateAction> + 'a {
+        self.actions.iter().filter(move |action| action.date() > from && action.date() <= to)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveTime;
+    use chrono::offset::TimeZone;
+    use core::price::PriceOrigin;
+    use core::symbol::*;
+
+    fn fund() -> Symbol {
+        Symbol::new("MUTF2351", QuoteOption::Quoted)
+    }
+
+    fn renamed() -> Symbol {
+        Symbol::new("MUTF9999", QuoteOption::Quoted)
+    }
+
+    fn dollars() -> Symbol {
+        Symbol::new("$", QuoteOption::Unquoted)
+    }
+
+    fn price(day: u32, symbol: Symbol, quantity: d128) -> Price {
+        Price::new(Local.ymd(2016, 3, day), symbol, Amount::new(quantity, dollars()))
+    }
+
+    fn actions() -> CorporateActions {
+        CorporateActions::new(vec![
+            CorporateAction::rename(Local.ymd(2016, 3, 20), fund(), renamed()),
+            CorporateAction::split(Local.ymd(2016, 3, 10), fund(), d128!(2), d128!(1)),
+        ])
+    }
+
+    #[test]
+    fn corporate_actions_sorted_by_date() {
+        let dates: Vec<Date<Local>> = actions().actions().iter().map(|a| a.date()).collect();
+        assert_eq!(dates, vec![Local.ymd(2016, 3, 10), Local.ymd(2016, 3, 20)]);
+    }
+
+    #[test]
+    fn adjust_amount_applies_actions_in_range() {
+        let actions = actions();
+        let amount = Amount::new(d128!(10), fund());
+        assert_eq!(actions.adjust_amount(&amount, Local.ymd(2016, 3, 1), Local.ymd(2016, 3, 9)),
+            amount);
+        assert_eq!(actions.adjust_amount(&amount, Local.ymd(2016, 3, 1), Local.ymd(2016, 3, 10)),
+            Amount::new(d128!(20), fund()));
+        assert_eq!(actions.adjust_amount(&amount, Local.ymd(2016, 3, 1), Local.ymd(2016, 3, 31)),
+            Amount::new(d128!(20), renamed()));
+        assert_eq!(actions.adjust_amount(&amount, Local.ymd(2016, 3, 10), Local.ymd(2016, 3, 31)),
+            Amount::new(d128!(10), renamed()));
+    }
+
+    #[test]
+    fn adjust_amount_leaves_other_commodities() {
+        let amount = Amount::new(d128!(45.00), dollars());
+        assert_eq!(actions().adjust_amount(&amount, Local.ymd(2016, 3, 1),
+            Local.ymd(2016, 3, 31)), amount);
+    }
+
+    #[test]
+    fn adjust_price_scales_inversely() {
+        let price = price(1, fund(), d128!(10.84))
+            .with_time(Some(NaiveTime::from_hms(16, 0, 0)))
+            .with_origin(PriceOrigin::Ledger);
+        let result = actions().adjust_price(&price, Local.ymd(2016, 3, 31));
+        assert_eq!(result, Price::new(Local.ymd(2016, 3, 1), renamed(),
+                Amount::new(d128!(5.42), dollars()))
+            .with_time(Some(NaiveTime::from_hms(16, 0, 0)))
+            .with_origin(PriceOrigin::Ledger));
+    }
+
+    #[test]
+    fn adjust_price_of_reverse_split() {
+        let actions = CorporateActions::new(vec![
+            CorporateAction::split(Local.ymd(2016, 3, 10), fund(), d128!(1), d128!(10)),
+        ]);
+        let result = actions.adjust_price(&price(1, fund(), d128!(0.54)), Local.ymd(2016, 3, 10));
+        assert_eq!(result.amount(), &Amount::new(d128!(5.4), dollars()));
+    }
+
+    #[test]
+    fn adjust_price_in_split_quote_commodity() {
+        let actions = CorporateActions::new(vec![
+            CorporateAction::split(Local.ymd(2016, 3, 10), dollars(), d128!(100), d128!(1)),
+        ]);
+        let result = actions.adjust_price(&price(1, fund(), d128!(5.42)), Local.ymd(2016, 3, 10));
+        assert_eq!(result.amount(), &Amount::new(d128!(542), dollars()));
+    }
+
+    #[test]
+    fn adjust_history_joins_renamed_series() {
+        let history = PriceHistory::from_prices(&[
+            price(1, fund(), d128!(10.84)),
+            price(15, fund(), d128!(5.61)),
+            price(25, renamed(), d128!(5.70)),
+        ]);
+        let result = actions().adjust_history(&history, Local.ymd(2016, 3, 31));
+        assert_eq!(result.series(fund(), dollars()), &[][..]);
+        assert_eq!(result.series(renamed(), dollars()), &[
+            price(1, renamed(), d128!(5.42)),
+            price(15, renamed(), d128!(5.61)),
+            price(25, renamed(), d128!(5.70)),
+        ][..]);
+    }
+
+    #[test]
+    fn corporate_action_fmt() {
+        let actions = actions();
+        assert_eq!(format!("{}", actions.actions()[0]), "split 2016-03-10 \"MUTF2351\" 2:1");
+        assert_eq!(format!("{}", actions.actions()[1]),
+            "rename 2016-03-20 \"MUTF2351\" \"MUTF9999\"");
+    }
+}
@@ -0,0 +1,143 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{OnceLock, RwLock};
+
+
+/// An interned string. Names are cheap to copy, and equality and hashing only compare the
+/// handle. Ordering compares the underlying strings so names sort alphabetically.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Name(u32);
+
+impl Name {
+    pub fn new(value: &str) -> Name {
+        interner().intern(value)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        interner().lookup(*self)
+    }
+}
+
+impl PartialOrd for Name {
+    fn partial_cmp(&self, other: &Name) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Name {
+    fn cmp(&self, other: &Name) -> Ordering {
+        if self.0 == other.0 {
+            Ordering::Equal
+        } else {
+            self.as_str().cmp(other.as_str())
+        }
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl fmt::Debug for Name {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Name({:?})", self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl ::serde::Serialize for Name {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: ::serde::Serializer {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> ::serde::Deserialize<'de> for Name {
+    fn deserialize<D>(deserializer: D) -> Result<Name, D::Error>
+    where D: ::serde::Deserializer<'de> {
+        let value = String::deserialize(deserializer)?;
+        Ok(Name::new(&value))
+    }
+}
+
+
+
+// INTERNER
+
+/// Process-wide string table. Interned strings are leaked so lookups can hand out `'static`
+/// references without holding the lock.
+struct Interner {
+    table: RwLock<Table>,
+}
+
+struct Table {
+    ids: HashMap<&'static str, Name>,
+    names: Vec<&'static str>,
+}
+
+impl Interner {
+    fn intern(&self, value: &str) -> Name {
+        if let Some(name) = self.table.read().expect("interner lock").ids.get(value) {
+            return *name;
+        }
+
+        let mut table = self.table.write().expect("interner lock");
+        if let Some(name) = table.ids.get(value) {
+            return *name;
+        }
+
+        let value: &'static str = Box::leak(value.to_string().into_boxed_str());
+        let name = Name(table.names.len() as u32);
+        table.names.push(value);
+        table.ids.insert(value, name);
+        name
+    }
+
+    fn lookup(&self, name: Name) -> &'static str {
+        self.table.read().expect("interner lock").names[name.0 as usize]
+    }
+}
+
+fn interner() -> &'static Interner {
+    static INTERNER: OnceLock<Interner> = OnceLock::new();
+    INTERNER.get_or_init(|| {
+        Interner {
+            table: RwLock::new(Table {
+                ids: HashMap::new(),
+                names: Vec::new(),
+            }),
+        }
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_same_string_same_handle() {
+        assert_eq!(Name::new("Assets:Savings"), Name::new("Assets:Savings"));
+    }
+
+    #[test]
+    fn name_different_strings_different_handles() {
+        assert!(Name::new("Assets:Savings") != Name::new("Assets:Checking"));
+    }
+
+    #[test]
+    fn name_as_str() {
+        assert_eq!(Name::new("MUTF2351").as_str(), "MUTF2351");
+    }
+
+    #[test]
+    fn name_orders_alphabetically() {
+        let later = Name::new("Zebra");
+        let earlier = Name::new("Aardvark");
+        assert!(earlier < later);
+    }
+}
@@ -0,0 +1,77 @@
+use std::fmt;
+use super::intern::Name;
+
+
+/// Where an item was found in the ledger: the file, once it is known, and the line number.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SourceLocation {
+    file: Option<Name>,
+    line: u32,
+}
+
+impl SourceLocation {
+    pub fn new(line: usize) -> SourceLocation {
+        SourceLocation {
+            file: None,
+            line: line as u32,
+        }
+    }
+
+    /// Returns this location in the given file.
+    pub fn in_file(&self, file_path: &str) -> SourceLocation {
+        SourceLocation {
+            file: Some(Name::new(file_path)),
+            line: self.line,
+        }
+    }
+
+    /// Returns the given line in the same file as this location.
+    pub fn at_line(&self, line: usize) -> SourceLocation {
+        SourceLocation {
+            file: self.file,
+            line: line as u32,
+        }
+    }
+
+    pub fn file(&self) -> Option<&'static str> {
+        self.file.map(|file| file.as_str())
+    }
+
+    pub fn line(&self) -> usize {
+        self.line as usize
+    }
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.file {
+            Some(file) => write!(f, "{}:{}", file, self.line),
+            None => write!(f, "line {}", self.line),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_location_fmt_with_file() {
+        let result = format!("{}", SourceLocation::new(12).in_file("test/data/sample.ledger"));
+        assert_eq!(result, "test/data/sample.ledger:12");
+    }
+
+    #[test]
+    fn source_location_fmt_without_file() {
+        let result = format!("{}", SourceLocation::new(12));
+        assert_eq!(result, "line 12");
+    }
+
+    #[test]
+    fn source_location_at_line_keeps_file() {
+        let result = SourceLocation::new(12).in_file("test/data/sample.ledger").at_line(14);
+        assert_eq!(format!("{}", result), "test/data/sample.ledger:14");
+    }
+}
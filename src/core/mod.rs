@@ -1,4 +1,17 @@
+pub mod account;
 pub mod amount;
+pub mod balance;
+pub mod commodity;
+pub mod compact;
+pub mod conversion;
+pub mod corporate_action;
+pub mod intern;
+pub mod location;
 pub mod price;
+pub mod price_history;
+#[cfg(feature = "serde")]
+pub mod serde_date;
+#[cfg(feature = "serde")]
+pub mod serde_time;
 pub mod symbol;
-pub mod transaction;
\ No newline at end of file
+pub mod transaction;
@@ -1,29 +1,121 @@
-use chrono::Date;
+use chrono::{Date, NaiveTime};
 use chrono::offset::Local;
 use std::fmt;
 use super::amount::Amount;
+use super::commodity::CommodityRegistry;
 use super::symbol::Symbol;
 
-#[derive(PartialEq, Debug)]
+/// Where a price was recorded.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PriceOrigin {
+    /// An entry in the price DB file.
+    #[default]
+    PriceDb,
+    /// A `P` line in the ledger.
+    Ledger,
+    /// Implied by a transaction, from a posting's cost or an implicit conversion.
+    Transaction,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Price {
+    #[cfg_attr(feature = "serde", serde(with = "::core::serde_date"))]
     date: Date<Local>,
+    #[cfg_attr(feature = "serde", serde(default, with = "::core::serde_time",
+        skip_serializing_if = "Option::is_none"))]
+    time: Option<NaiveTime>,
     symbol: Symbol,
     amount: Amount,
+    #[cfg_attr(feature = "serde", serde(default))]
+    origin: PriceOrigin,
 }
 
 impl Price {
     pub fn new(date: Date<Local>, symbol: Symbol, amount: Amount) -> Price {
         Price {
             date: date,
+            time: None,
             symbol: symbol,
             amount: amount,
+            origin: PriceOrigin::PriceDb,
+        }
+    }
+
+    /// Sets where the price was recorded. Prices come from the price DB unless set otherwise.
+    pub fn with_origin(self, origin: PriceOrigin) -> Price {
+        Price {
+            origin: origin,
+            ..self
+        }
+    }
+
+    /// Sets the time of day the price was quoted at, for sources that give intraday quotes.
+    pub fn with_time(self, time: Option<NaiveTime>) -> Price {
+        Price {
+            time: time,
+            ..self
+        }
+    }
+
+    pub fn date(&self) -> Date<Local> {
+        self.date
+    }
+
+    pub fn time(&self) -> Option<NaiveTime> {
+        self.time
+    }
+
+    /// The key prices are ordered by: the date, then the time of day. A price without a time
+    /// is usually the day's closing price, so it sorts after every timed price on its date and
+    /// is the one taken as the latest price for that date.
+    pub fn sort_key(&self) -> (Date<Local>, bool, Option<NaiveTime>) {
+        (self.date, self.time.is_none(), self.time)
+    }
+
+    /// The date, followed by the time of day if there is one, as written in a price entry.
+    /// e.g. 2016-02-07 16:00:00
+    pub fn format_timestamp(&self) -> String {
+        match self.time {
+            Some(time) => format!("{} {}", self.date.format("%Y-%m-%d"), time.format("%H:%M:%S")),
+            None => self.date.format("%Y-%m-%d").to_string(),
         }
     }
+
+    pub fn symbol(&self) -> Symbol {
+        self.symbol
+    }
+
+    pub fn amount(&self) -> &Amount {
+        &self.amount
+    }
+
+    pub fn origin(&self) -> PriceOrigin {
+        self.origin
+    }
+
+    /// Returns a value that renders this price as a price entry, formatting the amount using
+    /// the commodity registry.
+    pub fn display<'a>(&'a self, commodities: &'a CommodityRegistry) -> PriceDisplay<'a> {
+        PriceDisplay {
+            price: self,
+            commodities: commodities,
+        }
+    }
+}
+
+pub struct PriceDisplay<'a> {
+    price: &'a Price,
+    commodities: &'a CommodityRegistry,
 }
 
-impl fmt::Display for Price {
+impl<'a> fmt::Display for PriceDisplay<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "P {} {} {}", self.date.format("%Y-%m-%d"), self.symbol, self.amount)
+        write!(f, "P {} {} {}",
+            self.price.format_timestamp(),
+            self.price.symbol,
+            self.price.amount.display(self.commodities))
     }
 }
 
@@ -32,19 +124,65 @@ impl fmt::Display for Price {
 mod tests {
     use super::*;
     use core::amount::*;
+    use core::commodity::*;
     use core::symbol::*;
     use chrono::offset::Local;
     use chrono::offset::TimeZone;
 
     #[test]
     fn price_fmt() {
-        let result = format!("{}", Price::new(
+        let mut commodities = CommodityRegistry::new();
+        commodities.observe(Commodity::new(
+            Symbol::new("$", QuoteOption::Unquoted),
+            RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+                ThousandsSeparator::NoSeparator, 2)));
+        let price = Price::new(
                 Local.ymd(2016, 2, 7),
                 Symbol::new("MUTF2351", QuoteOption::Quoted),
                 Amount::new(
                     d128!(5.42),
-                    Symbol::new("$", QuoteOption::Unquoted),
-                    RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace))));
+                    Symbol::new("$", QuoteOption::Unquoted)));
+        let result = format!("{}", price.display(&commodities));
         assert_eq!(result, "P 2016-02-07 \"MUTF2351\" $5.42");
+
+        let price = price.with_time(Some(NaiveTime::from_hms(16, 0, 0)));
+        let result = format!("{}", price.display(&commodities));
+        assert_eq!(result, "P 2016-02-07 16:00:00 \"MUTF2351\" $5.42");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn price_sort_key_orders_untimed_last() {
+        let price = Price::new(
+                Local.ymd(2016, 2, 7),
+                Symbol::new("MUTF2351", QuoteOption::Quoted),
+                Amount::new(
+                    d128!(5.42),
+                    Symbol::new("$", QuoteOption::Unquoted)));
+        let timed = price.clone().with_time(Some(NaiveTime::from_hms(9, 30, 0)));
+        let late = price.clone().with_time(Some(NaiveTime::from_hms(23, 59, 59)));
+        assert!(timed.sort_key() < late.sort_key());
+        assert!(late.sort_key() < price.sort_key());
+        assert_eq!(timed.time(), Some(NaiveTime::from_hms(9, 30, 0)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn price_json_round_trip() {
+        let price = Price::new(
+                Local.ymd(2016, 2, 7),
+                Symbol::new("MUTF2351", QuoteOption::Quoted),
+                Amount::new(
+                    d128!(5.42),
+                    Symbol::new("$", QuoteOption::Unquoted)));
+        let json = ::serde_json::to_string(&price).unwrap();
+        assert!(json.starts_with("{\"date\":\"2016-02-07\","));
+        let result: Price = ::serde_json::from_str(&json).unwrap();
+        assert_eq!(result, price);
+
+        let price = price.with_time(Some(NaiveTime::from_hms(16, 0, 0)));
+        let json = ::serde_json::to_string(&price).unwrap();
+        assert!(json.starts_with("{\"date\":\"2016-02-07\",\"time\":\"16:00:00\","));
+        let result: Price = ::serde_json::from_str(&json).unwrap();
+        assert_eq!(result, price);
+    }
+}
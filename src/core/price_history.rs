@@ -0,0 +1,255 @@
+use chrono::Date;
+use chrono::offset::Local;
+use std::collections::BTreeMap;
+use super::price::{Price, PriceOrigin};
+use super::symbol::Symbol;
+
+
+/// Prices indexed by the commodity priced and the commodity it is quoted in, each series sorted
+/// by date. Prices on the same date keep the order they were added in, and the last one added
+/// is taken as the price for that date.
+#[derive(Clone, Debug, Default)]
+pub struct PriceHistory {
+    series: BTreeMap<(Symbol, Symbol), Vec<Price>>,
+}
+
+impl PriceHistory {
+    pub fn new() -> PriceHistory {
+        PriceHistory {
+            series: BTreeMap::new(),
+        }
+    }
+
+    /// Builds a price history from prices in any order.
+    pub fn from_prices<'a, I>(prices: I) -> PriceHistory
+    where I: IntoIterator<Item=&'a Price> {
+        let mut history = PriceHistory::new();
+        history.extend(prices);
+        history
+    }
+
+    pub fn add(&mut self, price: Price) {
+        let series = self.series.entry(key(&price)).or_default();
+        let index = series.partition_point(|p| p.sort_key() <= price.sort_key());
+        series.insert(index, price);
+    }
+
+    /// Adds many prices at once, e.g. from a price DB, the ledger's `P` lines or the prices
+    /// implied by transactions.
+    pub fn extend<'a, I>(&mut self, prices: I)
+    where I: IntoIterator<Item=&'a Price> {
+        for price in prices {
+            self.series.entry(key(price)).or_default().push(price.clone());
+        }
+        for series in self.series.values_mut() {
+            series.sort_by_key(|p| p.sort_key());
+        }
+    }
+
+    /// Adds every price from another history.
+    pub fn merge(&mut self, other: &PriceHistory) {
+        self.extend(other.series.values().flat_map(|series| series.iter()));
+    }
+
+    /// A history holding only the prices from the given origins, e.g. to value holdings without
+    /// prices implied by transactions.
+    pub fn restricted_to(&self, origins: &[PriceOrigin]) -> PriceHistory {
+        let mut history = PriceHistory::new();
+        for (key, series) in &self.series {
+            let kept: Vec<Price> = series.iter()
+                .filter(|p| origins.contains(&p.origin()))
+                .cloned()
+                .collect();
+            if !kept.is_empty() {
+                history.series.insert(*key, kept);
+            }
+        }
+        history
+    }
+
+    /// Every price of `symbol` quoted in `quote`, sorted by date and time of day.
+    pub fn series(&self, symbol: Symbol, quote: Symbol) -> &[Price] {
+        self.series.get(&(symbol, quote)).map(|s| &s[..]).unwrap_or(&[])
+    }
+
+    /// The (symbol, quote commodity) pairs that have prices, in sorted order.
+    pub fn pairs(&self) -> Vec<(Symbol, Symbol)> {
+        self.series.keys().cloned().collect()
+    }
+
+    /// The latest price of `symbol` in `quote` on or before `date`. When there are several
+    /// prices on the day, this is the last of them.
+    pub fn latest(&self, symbol: Symbol, quote: Symbol, date: Date<Local>) -> Option<&Price> {
+        let series = self.series(symbol, quote);
+        let index = series.partition_point(|p| p.date() <= date);
+        if index == 0 { None } else { Some(&series[index - 1]) }
+    }
+
+    /// The price of `symbol` in `quote` closest to `date`, before or after. When prices before
+    /// and after are equally close, the earlier one is chosen.
+    pub fn nearest(&self, symbol: Symbol, quote: Symbol, date: Date<Local>) -> Option<&Price> {
+        let series = self.series(symbol, quote);
+        let index = series.partition_point(|p| p.date() <= date);
+        let before = if index == 0 { None } else { Some(&series[index - 1]) };
+        let after = series.get(index);
+        match (before, after) {
+            (Some(before), Some(after)) => {
+                let before_days = date.signed_duration_since(before.date());
+                let after_days = after.date().signed_duration_since(date);
+                if after_days < before_days { Some(after) } else { Some(before) }
+            },
+            (before, after) => before.or(after),
+        }
+    }
+
+    /// The prices of `symbol` in `quote` from `start` to `end`, inclusive.
+    pub fn range(&self, symbol: Symbol, quote: Symbol, start: Date<Local>, end: Date<Local>)
+    -> &[Price] {
+        let series = self.series(symbol, quote);
+        let from = series.partition_point(|p| p.date() < start);
+        let to = series.partition_point(|p| p.date() <= end);
+        if from < to { &series[from..to] } else { &[] }
+    }
+
+    /// The total number of prices.
+    pub fn len(&self) -> usize {
+        self.series.values().map(|s| s.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.series.is_empty()
+    }
+}
+
+
+
+// HELPERS
+
+fn key(price: &Price) -> (Symbol, Symbol) {
+    (price.symbol(), price.amount().symbol())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveTime;
+    use chrono::offset::TimeZone;
+    use decimal::d128;
+    use core::amount::Amount;
+    use core::symbol::*;
+
+    fn fund() -> Symbol {
+        Symbol::new("MUTF2351", QuoteOption::Quoted)
+    }
+
+    fn dollars() -> Symbol {
+        Symbol::new("$", QuoteOption::Unquoted)
+    }
+
+    fn price(day: u32, quantity: d128) -> Price {
+        Price::new(Local.ymd(2016, 2, day), fund(), Amount::new(quantity, dollars()))
+    }
+
+    fn history() -> PriceHistory {
+        PriceHistory::from_prices(&[
+            price(9, d128!(7.10)),
+            price(3, d128!(5.41)),
+            price(7, d128!(5.61)),
+        ])
+    }
+
+    #[test]
+    fn latest_on_or_before_date() {
+        let history = history();
+        assert_eq!(history.latest(fund(), dollars(), Local.ymd(2016, 2, 8)),
+            Some(&price(7, d128!(5.61))));
+        assert_eq!(history.latest(fund(), dollars(), Local.ymd(2016, 2, 7)),
+            Some(&price(7, d128!(5.61))));
+        assert_eq!(history.latest(fund(), dollars(), Local.ymd(2016, 2, 2)), None);
+    }
+
+    #[test]
+    fn latest_same_date_takes_last_added() {
+        let mut history = history();
+        history.add(price(7, d128!(5.62)));
+        assert_eq!(history.latest(fund(), dollars(), Local.ymd(2016, 2, 7)),
+            Some(&price(7, d128!(5.62))));
+    }
+
+    #[test]
+    fn latest_same_date_takes_last_time_of_day() {
+        let close = price(8, d128!(5.65)).with_time(Some(NaiveTime::from_hms(16, 0, 0)));
+        let open = price(8, d128!(5.58)).with_time(Some(NaiveTime::from_hms(9, 30, 0)));
+        let mut history = history();
+        history.add(close.clone());
+        history.add(open.clone());
+        assert_eq!(history.latest(fund(), dollars(), Local.ymd(2016, 2, 8)), Some(&close));
+        assert_eq!(history.range(fund(), dollars(), Local.ymd(2016, 2, 8), Local.ymd(2016, 2, 8)),
+            &[open, close][..]);
+    }
+
+    #[test]
+    fn latest_same_date_prefers_untimed_close_to_intraday_quotes() {
+        let afternoon = price(7, d128!(5.65)).with_time(Some(NaiveTime::from_hms(15, 30, 0)));
+        let open = price(7, d128!(5.58)).with_time(Some(NaiveTime::from_hms(9, 30, 0)));
+        let mut history = history();
+        history.add(afternoon.clone());
+        history.add(open.clone());
+        assert_eq!(history.latest(fund(), dollars(), Local.ymd(2016, 2, 7)),
+            Some(&price(7, d128!(5.61))));
+        assert_eq!(history.range(fund(), dollars(), Local.ymd(2016, 2, 7), Local.ymd(2016, 2, 7)),
+            &[open, afternoon, price(7, d128!(5.61))][..]);
+    }
+
+    #[test]
+    fn latest_is_per_quote_commodity() {
+        let history = history();
+        let cad = Symbol::new("CAD", QuoteOption::Unquoted);
+        assert_eq!(history.latest(fund(), cad, Local.ymd(2016, 2, 8)), None);
+    }
+
+    #[test]
+    fn nearest_before_or_after() {
+        let history = history();
+        assert_eq!(history.nearest(fund(), dollars(), Local.ymd(2016, 2, 1)),
+            Some(&price(3, d128!(5.41))));
+        assert_eq!(history.nearest(fund(), dollars(), Local.ymd(2016, 2, 8)),
+            Some(&price(7, d128!(5.61))));
+        assert_eq!(history.nearest(fund(), dollars(), Local.ymd(2016, 2, 6)),
+            Some(&price(7, d128!(5.61))));
+        assert_eq!(history.nearest(fund(), dollars(), Local.ymd(2016, 2, 20)),
+            Some(&price(9, d128!(7.10))));
+    }
+
+    #[test]
+    fn range_is_inclusive() {
+        let history = history();
+        assert_eq!(history.range(fund(), dollars(), Local.ymd(2016, 2, 3), Local.ymd(2016, 2, 7)),
+            &[price(3, d128!(5.41)), price(7, d128!(5.61))]);
+        assert_eq!(history.range(fund(), dollars(), Local.ymd(2016, 2, 4), Local.ymd(2016, 2, 6)),
+            &[]);
+    }
+
+    #[test]
+    fn merge_combines_histories() {
+        let mut history = history();
+        history.merge(&PriceHistory::from_prices(&[price(5, d128!(5.50))]));
+        assert_eq!(history.len(), 4);
+        assert_eq!(history.pairs(), vec![(fund(), dollars())]);
+        assert_eq!(history.series(fund(), dollars())[1], price(5, d128!(5.50)));
+    }
+
+    #[test]
+    fn restricted_to_keeps_chosen_origins() {
+        let mut history = history();
+        history.add(price(8, d128!(5.70)).with_origin(PriceOrigin::Transaction));
+        assert_eq!(history.latest(fund(), dollars(), Local.ymd(2016, 2, 8)).unwrap().origin(),
+            PriceOrigin::Transaction);
+        let explicit = history.restricted_to(&[PriceOrigin::PriceDb]);
+        assert_eq!(explicit.len(), 3);
+        assert_eq!(explicit.latest(fund(), dollars(), Local.ymd(2016, 2, 8)),
+            Some(&price(7, d128!(5.61))));
+        assert!(history.restricted_to(&[PriceOrigin::Ledger]).is_empty());
+    }
+}
@@ -0,0 +1,18 @@
+//! Serializes dates as ISO-8601 (`YYYY-MM-DD`) strings, for use with `#[serde(with = "...")]`.
+
+use chrono::{Datelike, Date, NaiveDate};
+use chrono::offset::{Local, TimeZone};
+use serde::{Deserialize, Deserializer, Serializer};
+use serde::de::Error;
+
+pub fn serialize<S>(date: &Date<Local>, serializer: S) -> Result<S::Ok, S::Error>
+where S: Serializer {
+    serializer.serialize_str(&date.format("%Y-%m-%d").to_string())
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Date<Local>, D::Error>
+where D: Deserializer<'de> {
+    let value = String::deserialize(deserializer)?;
+    let date = NaiveDate::parse_from_str(&value, "%Y-%m-%d").map_err(D::Error::custom)?;
+    Ok(Local.ymd(date.year(), date.month(), date.day()))
+}
@@ -0,0 +1,24 @@
+//! Serializes optional times of day as `HH:MM:SS` strings, for use with
+//! `#[serde(with = "...")]`.
+
+use chrono::NaiveTime;
+use serde::{Deserialize, Deserializer, Serializer};
+use serde::de::Error;
+
+pub fn serialize<S>(time: &Option<NaiveTime>, serializer: S) -> Result<S::Ok, S::Error>
+where S: Serializer {
+    match *time {
+        Some(time) => serializer.serialize_some(&time.format("%H:%M:%S").to_string()),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<NaiveTime>, D::Error>
+where D: Deserializer<'de> {
+    match Option::<String>::deserialize(deserializer)? {
+        Some(value) => NaiveTime::parse_from_str(&value, "%H:%M:%S")
+            .map(Some)
+            .map_err(D::Error::custom),
+        None => Ok(None),
+    }
+}
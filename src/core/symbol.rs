@@ -1,26 +1,45 @@
 use std::fmt;
+use super::intern::Name;
 
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum QuoteOption {
     Quoted,
     Unquoted,
 }
 
-#[derive(PartialEq, Debug)]
+/// A commodity symbol. Symbols are interned, so they are cheap to copy and compare.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Symbol {
-    value: String,
+    value: Name,
     quote_option: QuoteOption,
 }
 
 impl Symbol {
     pub fn new<S>(symbol: S, quote_option: QuoteOption) -> Symbol
-    where S: Into<String> {
+    where S: AsRef<str> {
         Symbol {
-            value: symbol.into(),
+            value: Name::new(symbol.as_ref()),
             quote_option: quote_option,
         }
     }
+
+    /// A symbol for a name, quoted only if the name can't be written unquoted in a ledger, e.g.
+    /// because it contains digits or spaces.
+    pub fn from_name(name: &str) -> Symbol {
+        if name.chars().any(|c| "-0123456789; \"\t\r\n=@".contains(c)) {
+            Symbol::new(name, QuoteOption::Quoted)
+        } else {
+            Symbol::new(name, QuoteOption::Unquoted)
+        }
+    }
+
+    /// The symbol's name, without quotes.
+    pub fn name(&self) -> &'static str {
+        self.value.as_str()
+    }
 }
 
 impl fmt::Display for Symbol {
@@ -48,4 +67,16 @@ mod tests {
         let result = format!("{}", Symbol::new("$", QuoteOption::Unquoted));
         assert_eq!(result, "$");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn symbol_from_name_quotes_when_needed() {
+        assert_eq!(Symbol::from_name("MUTF2351"), Symbol::new("MUTF2351", QuoteOption::Quoted));
+        assert_eq!(Symbol::from_name("$"), Symbol::new("$", QuoteOption::Unquoted));
+        assert_eq!(Symbol::from_name("CAD"), Symbol::new("CAD", QuoteOption::Unquoted));
+    }
+
+    #[test]
+    fn symbol_name() {
+        assert_eq!(Symbol::new("MUTF2351", QuoteOption::Quoted).name(), "MUTF2351");
+    }
+}
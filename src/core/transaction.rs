@@ -1,14 +1,22 @@
 use chrono::Date;
 use chrono::offset::Local;
+use decimal::d128;
+use super::account::AccountName;
+use super::amount::Amount;
+use super::location::SourceLocation;
+use super::price::Price;
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Status {
     Cleared,
     Uncleared,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Header {
+    #[cfg_attr(feature = "serde", serde(with = "::core::serde_date"))]
     date: Date<Local>,
     status: Status,
     code: Option<String>,
@@ -27,4 +35,250 @@ impl Header {
             comment: comment
         }
     }
-}
\ No newline at end of file
+
+    pub fn date(&self) -> Date<Local> {
+        self.date
+    }
+
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    pub fn code(&self) -> Option<&str> {
+        self.code.as_ref().map(|c| &c[..])
+    }
+
+    pub fn payee(&self) -> &str {
+        &self.payee
+    }
+
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_ref().map(|c| &c[..])
+    }
+}
+
+/// Whether a posting's amount was written in the ledger or inferred by balancing the
+/// transaction.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AmountSource {
+    Provided,
+    Inferred,
+}
+
+/// The cost of a posting, written after its amount as either a per-unit price (`@`) or a total
+/// price (`@@`).
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Cost {
+    PerUnit(Amount),
+    Total(Amount),
+}
+
+impl Cost {
+    /// The total cost of the given quantity. A total price takes its sign from the quantity, so
+    /// selling at `@@ $50` costs $-50.
+    pub fn total(&self, quantity: d128) -> Amount {
+        match *self {
+            Cost::PerUnit(price) => Amount::new(price.quantity() * quantity, price.symbol()),
+            Cost::Total(total) => {
+                let magnitude = total.quantity().abs();
+                let quantity = if quantity.is_negative() { -magnitude } else { magnitude };
+                Amount::new(quantity, total.symbol())
+            },
+        }
+    }
+}
+
+/// A posting whose amount is known, either as written or as inferred by balancing.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Posting {
+    account: AccountName,
+    amount: Amount,
+    amount_source: AmountSource,
+    cost: Option<Cost>,
+    assertion: Option<Amount>,
+    comment: Option<String>,
+    line: Option<usize>,
+}
+
+impl Posting {
+    pub fn new(account: AccountName, amount: Amount, amount_source: AmountSource,
+    comment: Option<String>) -> Posting {
+        Posting {
+            account: account,
+            amount: amount,
+            amount_source: amount_source,
+            cost: None,
+            assertion: None,
+            comment: comment,
+            line: None,
+        }
+    }
+
+    /// Returns this posting with the line it was written on, if known.
+    pub fn with_line(self, line: Option<usize>) -> Posting {
+        Posting {
+            line: line,
+            .. self
+        }
+    }
+
+    /// Returns this posting with the given cost.
+    pub fn with_cost(self, cost: Option<Cost>) -> Posting {
+        Posting {
+            cost: cost,
+            .. self
+        }
+    }
+
+    /// Returns this posting with the given balance assertion.
+    pub fn with_assertion(self, assertion: Option<Amount>) -> Posting {
+        Posting {
+            assertion: assertion,
+            .. self
+        }
+    }
+
+    pub fn account(&self) -> AccountName {
+        self.account
+    }
+
+    pub fn amount(&self) -> &Amount {
+        &self.amount
+    }
+
+    pub fn amount_source(&self) -> AmountSource {
+        self.amount_source
+    }
+
+    pub fn cost(&self) -> Option<&Cost> {
+        self.cost.as_ref()
+    }
+
+    /// The balance the account is asserted to have after this posting.
+    pub fn assertion(&self) -> Option<&Amount> {
+        self.assertion.as_ref()
+    }
+
+    /// The amount this posting contributes towards balancing its transaction: its total cost if
+    /// it has one, otherwise its amount.
+    pub fn weight(&self) -> Amount {
+        match self.cost {
+            Some(cost) => cost.total(self.amount.quantity()),
+            None => self.amount,
+        }
+    }
+
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_ref().map(|c| &c[..])
+    }
+
+    /// The line the posting was written on, if it was read from a ledger.
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+}
+
+/// A transaction whose postings have all been given amounts and which balances.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Transaction {
+    location: SourceLocation,
+    header: Header,
+    postings: Vec<Posting>,
+    implied_price: Option<Price>,
+}
+
+impl Transaction {
+    pub fn new(location: SourceLocation, header: Header, postings: Vec<Posting>) -> Transaction {
+        Transaction {
+            location: location,
+            header: header,
+            postings: postings,
+            implied_price: None,
+        }
+    }
+
+    /// Returns this transaction with the given price implied by its postings.
+    pub fn with_implied_price(self, implied_price: Option<Price>) -> Transaction {
+        Transaction {
+            implied_price: implied_price,
+            .. self
+        }
+    }
+
+    pub fn location(&self) -> SourceLocation {
+        self.location
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn postings(&self) -> &[Posting] {
+        &self.postings
+    }
+
+    /// Where one of this transaction's postings was written: its own line, or the transaction's
+    /// location if the line isn't known.
+    pub fn posting_location(&self, posting: &Posting) -> SourceLocation {
+        posting.line().map_or(self.location, |line| self.location.at_line(line))
+    }
+
+    /// The exchange rate inferred when balancing an implicit conversion between two
+    /// commodities.
+    pub fn implied_price(&self) -> Option<&Price> {
+        self.implied_price.as_ref()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::symbol::*;
+
+    fn dollars(quantity: d128) -> Amount {
+        Amount::new(quantity, Symbol::new("$", QuoteOption::Unquoted))
+    }
+
+    #[test]
+    fn cost_per_unit_total() {
+        let result = Cost::PerUnit(dollars(d128!(5.42))).total(d128!(10));
+        assert_eq!(result, dollars(d128!(54.20)));
+    }
+
+    #[test]
+    fn cost_total_takes_sign_of_quantity() {
+        let result = Cost::Total(dollars(d128!(54.20))).total(d128!(-10));
+        assert_eq!(result, dollars(d128!(-54.20)));
+    }
+
+    #[test]
+    fn posting_weight_uses_cost() {
+        let posting = Posting::new(
+            AccountName::new("Assets:Investments"),
+            Amount::new(d128!(10), Symbol::new("MUTF2351", QuoteOption::Quoted)),
+            AmountSource::Provided,
+            None)
+            .with_cost(Some(Cost::PerUnit(dollars(d128!(5.42)))));
+        assert_eq!(posting.weight(), dollars(d128!(54.20)));
+    }
+
+    #[test]
+    fn transaction_posting_location_uses_posting_line() {
+        use chrono::offset::TimeZone;
+        let header = Header::new(Local.ymd(2016, 6, 7), Status::Cleared, None,
+            "Grocery Store".to_string(), None);
+        let posting = |line| Posting::new(AccountName::new("Expenses:Groceries"),
+            dollars(d128!(45.00)), AmountSource::Provided, None).with_line(line);
+        let transaction = Transaction::new(SourceLocation::new(5).in_file("test.ledger"), header,
+            vec![posting(Some(6)), posting(None)]);
+        let locations: Vec<String> = transaction.postings().iter()
+            .map(|p| transaction.posting_location(p).to_string())
+            .collect();
+        assert_eq!(locations, vec!["test.ledger:6", "test.ledger:5"]);
+    }
+}
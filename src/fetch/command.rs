@@ -0,0 +1,93 @@
+use std::process::Command;
+use core::price::Price;
+use super::mapping::{parse_records, FieldMapping, Format};
+use super::provider::{FetchError, PriceProvider, QuoteRequest};
+
+
+/// Fetches prices by running an external command and reading its standard output.
+///
+/// The arguments are templates filled in for each request (see `QuoteRequest::expand`), e.g.
+/// `curl -s https://quotes.example.com/{symbol}.csv`.
+#[derive(Clone, Debug)]
+pub struct CommandProvider {
+    program: String,
+    args: Vec<String>,
+    format: Format,
+    mapping: FieldMapping,
+}
+
+impl CommandProvider {
+    pub fn new(program: &str, args: Vec<String>, format: Format, mapping: FieldMapping)
+    -> CommandProvider {
+        CommandProvider {
+            program: program.to_string(),
+            args: args,
+            format: format,
+            mapping: mapping,
+        }
+    }
+
+    /// Builds a provider from a command line, splitting the program and arguments on
+    /// whitespace.
+    pub fn from_command_line(command_line: &str, format: Format, mapping: FieldMapping)
+    -> Option<CommandProvider> {
+        let mut words = command_line.split_whitespace().map(|w| w.to_string());
+        words.next().map(|program| CommandProvider::new(&program, words.collect(), format, mapping))
+    }
+
+    pub fn program(&self) -> &str {
+        &self.program
+    }
+
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+}
+
+impl PriceProvider for CommandProvider {
+    fn fetch(&self, request: &QuoteRequest) -> Result<Vec<Price>, FetchError> {
+        let args: Vec<String> = self.args.iter()
+            .map(|arg| request.expand(arg, |s| s.to_string()))
+            .collect();
+        let output = Command::new(&self.program).args(&args).output()
+            .map_err(|err| FetchError::Io(format!("{}: {}", self.program, err)))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            return Err(FetchError::Command(self.program.clone(), stderr));
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let records = parse_records(&stdout, self.format, &self.mapping)
+            .map_err(|e| FetchError::Format(format!("{}: {}", self.program, e)))?;
+        Ok(request.prices(&records))
+    }
+}
+
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use chrono::offset::{Local, TimeZone};
+    use fetch::provider::tests::request;
+
+    #[test]
+    fn fetch_reads_command_output() {
+        let provider = CommandProvider::from_command_line(
+            "printf date,close,fund\\n{start},5.61,{symbol}\\n",
+            Format::Csv,
+            FieldMapping::default().with_symbol(Some("fund".to_string()))).unwrap();
+        let prices = provider.fetch(&request()).unwrap();
+        assert_eq!(prices.len(), 1);
+        assert_eq!(prices[0].date(), Local.ymd(2016, 2, 8));
+        assert_eq!(prices[0].amount().quantity(), d128!(5.61));
+    }
+
+    #[test]
+    fn fetch_failing_command_is_error() {
+        let provider = CommandProvider::from_command_line("false", Format::Csv,
+            FieldMapping::default()).unwrap();
+        assert!(provider.fetch(&request()).is_err());
+        let provider = CommandProvider::from_command_line("wealth-pulse-missing-command",
+            Format::Csv, FieldMapping::default()).unwrap();
+        assert!(provider.fetch(&request()).is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+use core::price::Price;
+use super::mapping::{parse_records, FieldMapping, Format};
+use super::provider::{FetchError, PriceProvider, QuoteRequest};
+
+
+/// Fetches prices from a web service over plain HTTP.
+///
+/// The URL is a template filled in for each request (see `QuoteRequest::expand`), e.g.
+/// `http://quotes.example.com/{symbol}.csv?from={start}`. This is a minimal HTTP/1.0 client:
+/// it doesn't speak HTTPS or follow redirects, and reports both as errors. Services that need
+/// either are reached through a `CommandProvider` running a tool like `curl -sfL`, which is the
+/// supported way to fetch from most public quote services.
+#[derive(Clone, Debug)]
+pub struct HttpProvider {
+    url: String,
+    format: Format,
+    mapping: FieldMapping,
+}
+
+impl HttpProvider {
+    pub fn new(url: &str, format: Format, mapping: FieldMapping) -> HttpProvider {
+        HttpProvider {
+            url: url.to_string(),
+            format: format,
+            mapping: mapping,
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl PriceProvider for HttpProvider {
+    fn fetch(&self, request: &QuoteRequest) -> Result<Vec<Price>, FetchError> {
+        let url = request.expand(&self.url, percent_encode);
+        let body = get(&url)?;
+        let records = parse_records(&body, self.format, &self.mapping)
+            .map_err(|e| FetchError::Format(format!("{}: {}", url, e)))?;
+        Ok(request.prices(&records))
+    }
+}
+
+
+
+// HELPERS
+
+/// Sends a GET request and returns the body of a successful response.
+fn get(url: &str) -> Result<String, FetchError> {
+    let io_error = |err: ::std::io::Error| FetchError::Io(format!("{}: {}", url, err));
+    let rest = if let Some(rest) = url.strip_prefix("http://") { rest } else {
+        return Err(FetchError::Io(format!("{}: only http:// URLs are supported; fetch with a \
+            command such as curl instead", url)));
+    };
+    let (host, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    let address = if host.contains(':') { host.to_string() } else { format!("{}:80", host) };
+
+    let mut stream = TcpStream::connect(&address[..]).map_err(io_error)?;
+    stream.set_read_timeout(Some(Duration::from_secs(30))).map_err(io_error)?;
+    write!(stream, "GET {} HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n", path, host)
+        .map_err(io_error)?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).map_err(io_error)?;
+    let response = String::from_utf8_lossy(&response);
+
+    let (head, body) = match response.find("\r\n\r\n") {
+        Some(index) => (&response[..index], &response[index + 4..]),
+        None => return Err(FetchError::Io(format!("{}: incomplete response", url))),
+    };
+    let status = head.split_whitespace().nth(1).and_then(|s| s.parse::<u16>().ok())
+        .ok_or_else(|| FetchError::Io(format!("{}: invalid response", url)))?;
+    if status / 100 == 3 {
+        let location = head.lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|&(name, _)| name.trim().eq_ignore_ascii_case("location"))
+            .map_or("another URL", |(_, value)| value.trim());
+        return Err(FetchError::Io(format!("{}: redirected to {}, which isn't followed; fetch with \
+            a command such as curl -L instead", url, location)));
+    }
+    if status != 200 {
+        return Err(FetchError::Status(url.to_string(), status));
+    }
+    Ok(body.to_string())
+}
+
+/// Escapes everything but unreserved characters for use in a URL.
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| {
+            match b {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' =>
+                    (b as char).to_string(),
+                _ => format!("%{:02X}", b),
+            }
+        })
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::offset::{Local, TimeZone};
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::thread;
+    use fetch::provider::tests::request;
+
+    /// Serves a single response on a local port, returning the server's base URL and a handle
+    /// that yields the request line it received.
+    fn serve(status: &str, body: &str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let response = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\n\r\n{}",
+            status, body.len(), body);
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request_line = String::new();
+            {
+                let mut reader = BufReader::new(&stream);
+                reader.read_line(&mut request_line).unwrap();
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 0 && line.trim() != "" {
+                    line.clear();
+                }
+            }
+            stream.write_all(response.as_bytes()).unwrap();
+            request_line.trim_end().to_string()
+        });
+        (base_url, handle)
+    }
+
+    #[test]
+    fn fetch_csv() {
+        let (base_url, server) = serve("200 OK",
+            "Date,Close\n2016-02-07,5.41\n2016-02-08,5.61\n2016-02-09,7.10\n");
+        let url = format!("{}/quotes/{{symbol}}.csv?from={{start}}&in={{quote}}", base_url);
+        let provider = HttpProvider::new(&url, Format::Csv, FieldMapping::new("Date", "Close"));
+        let prices = provider.fetch(&request()).unwrap();
+        assert_eq!(server.join().unwrap(),
+            "GET /quotes/MUTF2351.csv?from=2016-02-08&in=%24 HTTP/1.0");
+        assert_eq!(prices.len(), 2);
+        assert_eq!(prices[1].amount().quantity(), d128!(7.10));
+    }
+
+    #[test]
+    fn fetch_json() {
+        let (base_url, server) = serve("200 OK",
+            r#"{"prices": [{"date": "2016-02-08", "nav": 5.61}]}"#);
+        let mapping = FieldMapping::new("date", "nav").with_records(Some("prices".to_string()));
+        let provider = HttpProvider::new(&base_url, Format::Json, mapping);
+        let prices = provider.fetch(&request()).unwrap();
+        assert_eq!(server.join().unwrap(), "GET / HTTP/1.0");
+        assert_eq!(prices[0].date(), Local.ymd(2016, 2, 8));
+    }
+
+    #[test]
+    fn fetch_unsuccessful_status_is_error() {
+        let (base_url, server) = serve("404 Not Found", "no such fund");
+        let provider = HttpProvider::new(&base_url, Format::Csv, FieldMapping::default());
+        let err = provider.fetch(&request()).unwrap_err();
+        server.join().unwrap();
+        assert_eq!(format!("{}", err), format!("{}: HTTP status 404", base_url));
+    }
+
+    #[test]
+    fn fetch_malformed_body_is_error() {
+        let (base_url, server) = serve("200 OK", "<html>busy</html>");
+        let provider = HttpProvider::new(&base_url, Format::Csv, FieldMapping::default());
+        let err = provider.fetch(&request()).unwrap_err();
+        server.join().unwrap();
+        assert_eq!(format!("{}", err),
+            format!("could not read prices: {}: no \"date\" column", base_url));
+    }
+
+    #[test]
+    fn fetch_https_is_unsupported() {
+        let provider = HttpProvider::new("https://example.com/{symbol}", Format::Csv,
+            FieldMapping::default());
+        let err = provider.fetch(&request()).unwrap_err();
+        assert_eq!(format!("{}", err),
+            "https://example.com/MUTF2351: only http:// URLs are supported; fetch with a \
+            command such as curl instead");
+    }
+
+    #[test]
+    fn fetch_redirect_is_error() {
+        let (base_url, server) = serve("301 Moved Permanently\r\nLocation: https://example.com/",
+            "");
+        let provider = HttpProvider::new(&base_url, Format::Csv, FieldMapping::default());
+        let err = provider.fetch(&request()).unwrap_err();
+        server.join().unwrap();
+        assert_eq!(format!("{}", err), format!("{}: redirected to https://example.com/, which \
+            isn't followed; fetch with a command such as curl -L instead", base_url));
+    }
+}
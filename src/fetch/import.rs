@@ -0,0 +1,226 @@
+use chrono::Date;
+use chrono::offset::Local;
+use std::collections::HashMap;
+use std::fmt;
+use core::amount::{Amount, RenderedAmount};
+use core::commodity::CommodityRegistry;
+use core::price::Price;
+use core::symbol::Symbol;
+use super::mapping::{PriceRecord, RecordPosition};
+
+
+/// An imported price that differs from the price already recorded for that commodity and date.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ImportConflict {
+    position: RecordPosition,
+    existing: Price,
+    imported: Price,
+    existing_amount: RenderedAmount,
+    imported_amount: RenderedAmount,
+}
+
+impl ImportConflict {
+    /// Where in the import the price came from.
+    pub fn position(&self) -> RecordPosition {
+        self.position
+    }
+
+    pub fn existing(&self) -> &Price {
+        &self.existing
+    }
+
+    pub fn imported(&self) -> &Price {
+        &self.imported
+    }
+}
+
+impl fmt::Display for ImportConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} {} is {} in the price DB but {} in the import", self.position,
+            self.imported.date().format("%Y-%m-%d"), self.imported.symbol(),
+            self.existing_amount, self.imported_amount)
+    }
+}
+
+/// The outcome of merging imported prices into existing ones.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ImportReport {
+    added: Vec<Price>,
+    skipped: usize,
+    conflicts: Vec<ImportConflict>,
+}
+
+impl ImportReport {
+    /// The imported prices that weren't already recorded, in the order they were imported.
+    pub fn added(&self) -> &[Price] {
+        &self.added
+    }
+
+    /// How many imported prices were already recorded, or imported twice.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// Imported prices that differ from one already recorded. These are not added.
+    pub fn conflicts(&self) -> &[ImportConflict] {
+        &self.conflicts
+    }
+}
+
+/// Merges imported price records into existing prices, quoted in `quote`.
+///
+/// Each record's commodity is the one it names or, if it doesn't name one, `symbol`. Named
+/// commodities are matched by name to those in the existing prices or the commodity registry,
+/// so a record naming `AAPL` matches prices recorded for `"AAPL"`. A record
+/// matching a recorded price for the same commodity, quote commodity and date is skipped; one
+/// with a different price is reported as a conflict and left out. Conflicting prices are
+/// rendered using the commodity registry.
+pub fn import_prices(existing: &[Price], records: &[PriceRecord], symbol: Option<Symbol>,
+    quote: Symbol, commodities: &CommodityRegistry) -> Result<ImportReport, String> {
+    let mut recorded: HashMap<(Date<Local>, Symbol, Symbol), Vec<Price>> = HashMap::new();
+    let mut named: HashMap<&str, Symbol> = HashMap::new();
+    for price in existing {
+        recorded.entry(key(price)).or_default().push(price.clone());
+        named.entry(price.symbol().name()).or_insert_with(|| price.symbol());
+    }
+
+    let mut added = Vec::new();
+    let mut skipped = 0;
+    let mut conflicts = Vec::new();
+    for record in records {
+        let symbol = record.symbol()
+            .map(|name| named.get(name).cloned()
+                .or_else(|| commodities.find(name))
+                .unwrap_or_else(|| Symbol::from_name(name)))
+            .or(symbol)
+            .ok_or_else(|| format!("{}: no commodity given", record.position()))?;
+        let price = Price::new(record.date(), symbol, Amount::new(record.price(), quote));
+        let matching = recorded.entry(key(&price)).or_default();
+        if matching.iter().any(|p| p.amount().quantity() == price.amount().quantity()) {
+            skipped += 1;
+        } else if let Some(existing) = matching.first() {
+            conflicts.push(ImportConflict {
+                position: record.position(),
+                existing: existing.clone(),
+                existing_amount: RenderedAmount::new(*existing.amount(), commodities),
+                imported_amount: RenderedAmount::new(*price.amount(), commodities),
+                imported: price,
+            });
+        } else {
+            matching.push(price.clone());
+            added.push(price);
+        }
+    }
+    Ok(ImportReport {
+        added: added,
+        skipped: skipped,
+        conflicts: conflicts,
+    })
+}
+
+
+
+// HELPERS
+
+fn key(price: &Price) -> (Date<Local>, Symbol, Symbol) {
+    (price.date(), price.symbol(), price.amount().symbol())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::offset::TimeZone;
+    use decimal::d128;
+    use core::commodity::*;
+    use core::symbol::*;
+    use fetch::mapping::*;
+
+    fn fund() -> Symbol {
+        Symbol::new("MUTF2351", QuoteOption::Quoted)
+    }
+
+    fn dollars() -> Symbol {
+        Symbol::new("$", QuoteOption::Unquoted)
+    }
+
+    fn existing() -> Vec<Price> {
+        vec![
+            Price::new(Local.ymd(2016, 2, 7), fund(), Amount::new(d128!(5.41), dollars())),
+            Price::new(Local.ymd(2016, 2, 8), fund(), Amount::new(d128!(5.61), dollars())),
+        ]
+    }
+
+    fn registry() -> CommodityRegistry {
+        let mut commodities = CommodityRegistry::new();
+        commodities.declare(Commodity::new(dollars(), RenderOptions::new(SymbolPosition::Left,
+            Spacing::NoSpace, ThousandsSeparator::Comma, 2)));
+        commodities
+    }
+
+    fn records(contents: &str, mapping: &FieldMapping) -> Vec<PriceRecord> {
+        parse_records(contents, Format::Csv, mapping).unwrap()
+    }
+
+    #[test]
+    fn import_prices_adds_skips_and_reports_conflicts() {
+        let records = records("date,close\n\
+            2016-02-07,5.410\n\
+            2016-02-08,5.62\n\
+            2016-02-09,7.10\n\
+            2016-02-09,7.1\n",
+            &FieldMapping::default());
+        let report = import_prices(&existing(), &records, Some(fund()), dollars(), &registry())
+            .unwrap();
+        assert_eq!(report.added(), &[
+            Price::new(Local.ymd(2016, 2, 9), fund(), Amount::new(d128!(7.10), dollars())),
+        ]);
+        assert_eq!(report.skipped(), 2);
+        assert_eq!(report.conflicts().len(), 1);
+        assert_eq!(format!("{}", report.conflicts()[0]),
+            "line 3: 2016-02-08 \"MUTF2351\" is $5.61 in the price DB but $5.62 in the import");
+    }
+
+    #[test]
+    fn import_prices_uses_symbol_column() {
+        let records = records("fund,date,close\nAAPL,2016-02-07,94.99\n",
+            &FieldMapping::default().with_symbol(Some("fund".to_string())));
+        let report = import_prices(&existing(), &records, Some(fund()), dollars(), &registry())
+            .unwrap();
+        assert_eq!(report.added()[0].symbol(), Symbol::new("AAPL", QuoteOption::Unquoted));
+    }
+
+    #[test]
+    fn import_prices_matches_symbol_column_by_name() {
+        let apple = Symbol::new("AAPL", QuoteOption::Quoted);
+        let existing = vec![
+            Price::new(Local.ymd(2016, 2, 7), apple, Amount::new(d128!(94.99), dollars())),
+        ];
+        let records = records("fund,date,close\nAAPL,2016-02-07,94.99\nAAPL,2016-02-08,95.01\n",
+            &FieldMapping::default().with_symbol(Some("fund".to_string())));
+        let report = import_prices(&existing, &records, None, dollars(), &registry()).unwrap();
+        assert_eq!(report.skipped(), 1);
+        assert_eq!(report.added(), &[
+            Price::new(Local.ymd(2016, 2, 8), apple, Amount::new(d128!(95.01), dollars())),
+        ]);
+    }
+
+    #[test]
+    fn import_prices_reports_json_conflicts_by_record() {
+        let records = parse_records(r#"[
+            {"date": "2016-02-07", "close": 5.41},
+            {"date": "2016-02-08", "close": 5.62}
+        ]"#, Format::Json, &FieldMapping::default()).unwrap();
+        let report = import_prices(&existing(), &records, Some(fund()), dollars(), &registry())
+            .unwrap();
+        assert_eq!(format!("{}", report.conflicts()[0]),
+            "record 2: 2016-02-08 \"MUTF2351\" is $5.61 in the price DB but $5.62 in the import");
+    }
+
+    #[test]
+    fn import_prices_without_commodity_is_error() {
+        let records = records("date,close\n2016-02-07,5.41\n", &FieldMapping::default());
+        assert_eq!(import_prices(&existing(), &records, None, dollars(), &registry()).unwrap_err(),
+            "line 2: no commodity given");
+    }
+}
@@ -0,0 +1,182 @@
+use combine::{any, between, count, many, many1, parser, satisfy, sep_by, Parser, ParseResult,
+    State};
+use combine::char::{char, hex_digit, spaces, string};
+use combine::primitives::Stream;
+
+
+/// A parsed JSON document. Numbers are kept as written so they can be read as decimals without
+/// going through floating point.
+#[derive(Clone, PartialEq, Debug)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Looks up a value by a dot-separated path of object keys and array indexes, e.g.
+    /// `data.prices.0.close`. An empty path is the value itself.
+    pub fn get(&self, path: &str) -> Option<&JsonValue> {
+        let mut value = self;
+        for key in path.split('.').filter(|key| !key.is_empty()) {
+            value = match *value {
+                JsonValue::Object(ref members) => &members.iter().find(|m| m.0 == key)?.1,
+                JsonValue::Array(ref items) => items.get(key.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match *self {
+            JsonValue::Array(ref items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// The value as text, if it is a string or a number.
+    pub fn as_text(&self) -> Option<&str> {
+        match *self {
+            JsonValue::String(ref text) | JsonValue::Number(ref text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+
+
+// PARSERS
+
+/// Parses a JSON string, handling escapes. Characters outside the Basic Multilingual Plane are
+/// escaped as a pair of UTF-16 surrogates, e.g. `\ud83d\ude00`, which are joined back together.
+fn json_string<I>(input: I) -> ParseResult<String, I>
+where I: Stream<Item=char> {
+    let unicode = char('u').with(count::<String, _>(4, hex_digit()))
+        .map(|digits| u32::from_str_radix(&digits, 16).unwrap_or(0xfffd));
+    let escaped = any().map(|c| {
+        match c {
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            c => c,
+        }
+    });
+    let character = satisfy(|c| c != '"' && c != '\\').map(|c| c as u32)
+        .or(char('\\').with(unicode.or(escaped.map(|c| c as u32))));
+    between(char('"'), char('"'), many(character))
+        .map(|code_points: Vec<u32>| join_surrogates(&code_points))
+        .parse_stream(input)
+}
+
+/// Parses a JSON value and any whitespace following it.
+fn json_value<I>(input: I) -> ParseResult<JsonValue, I>
+where I: Stream<Item=char> {
+    let separator = || char(',').skip(spaces());
+    let member = (parser(json_string).skip(spaces()), char(':').skip(spaces()), parser(json_value))
+        .map(|(key, _, value)| (key, value));
+    let array = between(char('[').skip(spaces()), char(']'),
+        sep_by(parser(json_value), separator()));
+    let object = between(char('{').skip(spaces()), char('}'), sep_by(member, separator()));
+    let number = many1(satisfy(|c: char| c.is_ascii_digit() || "+-.eE".contains(c)));
+    parser(json_string).map(JsonValue::String)
+        .or(number.map(JsonValue::Number))
+        .or(array.map(JsonValue::Array))
+        .or(object.map(JsonValue::Object))
+        .or(string("true").map(|_| JsonValue::Bool(true)))
+        .or(string("false").map(|_| JsonValue::Bool(false)))
+        .or(string("null").map(|_| JsonValue::Null))
+        .skip(spaces())
+        .parse_stream(input)
+}
+
+
+
+/// Parses a JSON document.
+pub fn parse_json(contents: &str) -> Result<JsonValue, String> {
+    match spaces().with(parser(json_value)).parse(State::new(contents)) {
+        Ok((value, ref rest)) if rest.input.is_empty() => Ok(value),
+        Ok((_, rest)) => Err(format!("unexpected {:?} after JSON value",
+            rest.input.chars().take(20).collect::<String>())),
+        Err(err) => Err(format!("{}", err).trim_end().replace('\n', "; ")),
+    }
+}
+
+
+
+
+// HELPERS
+
+/// Turns code points into a string, joining surrogate pairs into the character they encode.
+/// Unpaired surrogates become U+FFFD.
+fn join_surrogates(code_points: &[u32]) -> String {
+    let mut text = String::new();
+    let mut index = 0;
+    while index < code_points.len() {
+        let high = code_points[index];
+        let low = code_points.get(index + 1).cloned().unwrap_or(0);
+        let (code_point, width) =
+            if (0xd800..0xdc00).contains(&high) && (0xdc00..0xe000).contains(&low) {
+                (0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00), 2)
+            } else {
+                (high, 1)
+            };
+        text.push(::std::char::from_u32(code_point).unwrap_or('\u{fffd}'));
+        index += width;
+    }
+    text
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_json_values() {
+        let value = parse_json(r#" {"a": [1, -2.50, "x\"é"], "b": {"c": true}, "d": null} "#)
+            .unwrap();
+        assert_eq!(value, JsonValue::Object(vec![
+            ("a".to_string(), JsonValue::Array(vec![
+                JsonValue::Number("1".to_string()),
+                JsonValue::Number("-2.50".to_string()),
+                JsonValue::String("x\"\u{e9}".to_string()),
+            ])),
+            ("b".to_string(), JsonValue::Object(vec![
+                ("c".to_string(), JsonValue::Bool(true)),
+            ])),
+            ("d".to_string(), JsonValue::Null),
+        ]));
+    }
+
+    #[test]
+    fn parse_json_trailing_input_is_error() {
+        assert!(parse_json("[1] 2").is_err());
+        assert!(parse_json("[1,").is_err());
+    }
+
+    #[test]
+    fn parse_json_surrogate_pairs() {
+        let value = parse_json(r#"["\ud83d\ude00", "\ud83d!", "\u00e9"]"#).unwrap();
+        assert_eq!(value, JsonValue::Array(vec![
+            JsonValue::String("\u{1f600}".to_string()),
+            JsonValue::String("\u{fffd}!".to_string()),
+            JsonValue::String("\u{e9}".to_string()),
+        ]));
+    }
+
+    #[test]
+    fn get_follows_path() {
+        let value = parse_json(r#"{"data": {"prices": [{"close": 5.41}, {"close": "5.61"}]}}"#)
+            .unwrap();
+        assert_eq!(value.get("data.prices.1.close").and_then(|v| v.as_text()), Some("5.61"));
+        assert_eq!(value.get("data.prices.0.close").and_then(|v| v.as_text()), Some("5.41"));
+        assert_eq!(value.get("data.missing"), None);
+        assert_eq!(value.get(""), Some(&value));
+    }
+}
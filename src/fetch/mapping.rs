@@ -0,0 +1,329 @@
+use rust_core::str::FromStr;
+use std::fmt;
+use chrono::{Date, Datelike, NaiveDate};
+use chrono::offset::{Local, TimeZone};
+use decimal::d128;
+use super::json::parse_json;
+
+
+/// The shape of a price source's output.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    /// Comma-separated values with a header row naming the columns.
+    Csv,
+    /// A JSON document holding an array of records.
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown format: {}", s)),
+        }
+    }
+}
+
+/// Which fields of a price source's records hold the date, price and (optionally) symbol.
+///
+/// For CSV, fields are column names from the header row. For JSON, fields are dot-separated
+/// paths within each record, and `records` is the path to the array of records.
+#[derive(Clone, PartialEq, Debug)]
+pub struct FieldMapping {
+    date: String,
+    price: String,
+    symbol: Option<String>,
+    records: String,
+    date_format: String,
+}
+
+impl FieldMapping {
+    pub fn new(date: &str, price: &str) -> FieldMapping {
+        FieldMapping {
+            date: date.to_string(),
+            price: price.to_string(),
+            symbol: None,
+            records: String::new(),
+            date_format: "%Y-%m-%d".to_string(),
+        }
+    }
+
+    /// Sets the field naming the commodity of each record, for sources that mix commodities.
+    pub fn with_symbol(self, symbol: Option<String>) -> FieldMapping {
+        FieldMapping {
+            symbol: symbol,
+            ..self
+        }
+    }
+
+    /// Sets the path to the array of records in a JSON document. The default is the whole
+    /// document.
+    pub fn with_records(self, records: Option<String>) -> FieldMapping {
+        FieldMapping {
+            records: records.unwrap_or_default(),
+            ..self
+        }
+    }
+
+    /// Sets the `strftime`-style format of dates. The default is `%Y-%m-%d`.
+    pub fn with_date_format(self, date_format: Option<String>) -> FieldMapping {
+        FieldMapping {
+            date_format: date_format.unwrap_or_else(|| "%Y-%m-%d".to_string()),
+            ..self
+        }
+    }
+
+    pub fn date(&self) -> &str {
+        &self.date
+    }
+
+    pub fn price(&self) -> &str {
+        &self.price
+    }
+
+    pub fn symbol(&self) -> Option<&str> {
+        self.symbol.as_ref().map(|s| &s[..])
+    }
+
+    pub fn records(&self) -> &str {
+        &self.records
+    }
+
+    pub fn date_format(&self) -> &str {
+        &self.date_format
+    }
+}
+
+impl Default for FieldMapping {
+    fn default() -> FieldMapping {
+        FieldMapping::new("date", "close")
+    }
+}
+
+/// Where a record was found in a price source's output, counting from 1.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RecordPosition {
+    /// The line of a CSV file, including the header line.
+    Line(usize),
+    /// The position in a JSON document's array of records.
+    Record(usize),
+}
+
+impl fmt::Display for RecordPosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RecordPosition::Line(line) => write!(f, "line {}", line),
+            RecordPosition::Record(record) => write!(f, "record {}", record),
+        }
+    }
+}
+
+/// One record read from a price source: a dated price, possibly naming its commodity.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PriceRecord {
+    position: RecordPosition,
+    date: Date<Local>,
+    symbol: Option<String>,
+    price: d128,
+}
+
+impl PriceRecord {
+    /// Where the record was read from, for reporting problems with it.
+    pub fn position(&self) -> RecordPosition {
+        self.position
+    }
+
+    pub fn date(&self) -> Date<Local> {
+        self.date
+    }
+
+    pub fn symbol(&self) -> Option<&str> {
+        self.symbol.as_ref().map(|s| &s[..])
+    }
+
+    pub fn price(&self) -> d128 {
+        self.price
+    }
+}
+
+/// Reads the price records in a price source's output.
+pub fn parse_records(contents: &str, format: Format, mapping: &FieldMapping)
+-> Result<Vec<PriceRecord>, String> {
+    match format {
+        Format::Csv => parse_csv_records(contents, mapping),
+        Format::Json => parse_json_records(contents, mapping),
+    }
+}
+
+
+
+// HELPERS
+
+fn parse_csv_records(contents: &str, mapping: &FieldMapping) -> Result<Vec<PriceRecord>, String> {
+    let mut lines = contents.lines().enumerate().filter(|&(_, line)| !line.trim().is_empty());
+    let header = match lines.next() {
+        Some((_, header)) => split_csv_line(header),
+        None => return Ok(Vec::new()),
+    };
+    let column = |name: &str| {
+        header.iter().position(|h| h.trim() == name)
+            .ok_or_else(|| format!("no {:?} column", name))
+    };
+    let date_column = column(&mapping.date)?;
+    let price_column = column(&mapping.price)?;
+    let symbol_column = match mapping.symbol {
+        Some(ref symbol) => Some(column(symbol)?),
+        None => None,
+    };
+
+    let mut records = Vec::new();
+    for (index, line) in lines {
+        let line_number = index + 1;
+        let fields = split_csv_line(line);
+        let field = |column: usize| {
+            fields.get(column).map(|f| f.trim())
+                .ok_or_else(|| format!("line {}: missing column {}", line_number, column + 1))
+        };
+        records.push(PriceRecord {
+            position: RecordPosition::Line(line_number),
+            date: parse_date(field(date_column)?, &mapping.date_format)
+                .map_err(|e| format!("line {}: {}", line_number, e))?,
+            symbol: match symbol_column {
+                Some(column) => Some(field(column)?.to_string()),
+                None => None,
+            },
+            price: parse_price(field(price_column)?)
+                .map_err(|e| format!("line {}: {}", line_number, e))?,
+        });
+    }
+    Ok(records)
+}
+
+fn parse_json_records(contents: &str, mapping: &FieldMapping)
+-> Result<Vec<PriceRecord>, String> {
+    let document = parse_json(contents)?;
+    let items = document.get(&mapping.records).and_then(|value| value.as_array())
+        .ok_or_else(|| format!("no array of records at {:?}", mapping.records))?;
+    let mut records = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        let position = RecordPosition::Record(index + 1);
+        let field = |path: &str| {
+            item.get(path).and_then(|value| value.as_text())
+                .ok_or_else(|| format!("{}: no {:?} field", position, path))
+        };
+        records.push(PriceRecord {
+            position: position,
+            date: parse_date(field(&mapping.date)?, &mapping.date_format)
+                .map_err(|e| format!("{}: {}", position, e))?,
+            symbol: match mapping.symbol {
+                Some(ref symbol) => Some(field(symbol)?.to_string()),
+                None => None,
+            },
+            price: parse_price(field(&mapping.price)?)
+                .map_err(|e| format!("{}: {}", position, e))?,
+        });
+    }
+    Ok(records)
+}
+
+/// Splits a CSV line into fields, handling quoted fields and doubled quotes within them.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            },
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(::std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+fn parse_date(text: &str, format: &str) -> Result<Date<Local>, String> {
+    NaiveDate::parse_from_str(text, format)
+        .map(|date| Local.ymd(date.year(), date.month(), date.day()))
+        .map_err(|_| format!("date {:?} doesn't match format {:?}", text, format))
+}
+
+fn parse_price(text: &str) -> Result<d128, String> {
+    match d128::from_str(&text.replace(',', "")) {
+        Ok(price) if !price.is_nan() && !price.is_infinite() => Ok(price),
+        _ => Err(format!("invalid price {:?}", text)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_csv_records_by_column_name() {
+        let contents = "Date,Open,Close\n2016-02-07,5.40,5.41\n\n2016-02-08,5.50,\"5.61\"\n";
+        let mapping = FieldMapping::new("Date", "Close");
+        let records = parse_records(contents, Format::Csv, &mapping).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].position(), RecordPosition::Line(4));
+        assert_eq!(records[1].date(), Local.ymd(2016, 2, 8));
+        assert_eq!(records[1].price(), d128!(5.61));
+        assert_eq!(records[1].symbol(), None);
+    }
+
+    #[test]
+    fn parse_csv_records_with_symbol_and_date_format() {
+        let contents = "fund,nav date,nav\n\"MUTF2351\",02/07/2016,\"1,005.41\"\n";
+        let mapping = FieldMapping::new("nav date", "nav")
+            .with_symbol(Some("fund".to_string()))
+            .with_date_format(Some("%m/%d/%Y".to_string()));
+        let records = parse_records(contents, Format::Csv, &mapping).unwrap();
+        assert_eq!(records[0].symbol(), Some("MUTF2351"));
+        assert_eq!(records[0].date(), Local.ymd(2016, 2, 7));
+        assert_eq!(records[0].price(), d128!(1005.41));
+    }
+
+    #[test]
+    fn parse_csv_records_errors() {
+        let mapping = FieldMapping::default();
+        assert_eq!(parse_records("date,open\n", Format::Csv, &mapping).unwrap_err(),
+            "no \"close\" column");
+        assert_eq!(parse_records("date,close\n2016-02-07,abc\n", Format::Csv, &mapping)
+            .unwrap_err(), "line 2: invalid price \"abc\"");
+        assert_eq!(parse_records("date,close\n7 Feb,5.41\n", Format::Csv, &mapping)
+            .unwrap_err(), "line 2: date \"7 Feb\" doesn't match format \"%Y-%m-%d\"");
+    }
+
+    #[test]
+    fn parse_json_records_at_path() {
+        let contents = r#"{"data": {"quotes": [
+            {"day": "2016-02-07", "nav": {"close": 5.41}},
+            {"day": "2016-02-08", "nav": {"close": "5.61"}}
+        ]}}"#;
+        let mapping = FieldMapping::new("day", "nav.close")
+            .with_records(Some("data.quotes".to_string()));
+        let records = parse_records(contents, Format::Json, &mapping).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].position(), RecordPosition::Record(1));
+        assert_eq!(records[0].price(), d128!(5.41));
+        assert_eq!(records[1].date(), Local.ymd(2016, 2, 8));
+    }
+
+    #[test]
+    fn parse_json_records_missing_field_is_error() {
+        let mapping = FieldMapping::default();
+        assert_eq!(parse_records(r#"[{"date": "2016-02-07"}]"#, Format::Json, &mapping)
+            .unwrap_err(), "record 1: no \"close\" field");
+        assert_eq!(parse_records(r#"{"a": 1}"#, Format::Json, &mapping).unwrap_err(),
+            "no array of records at \"\"");
+    }
+}
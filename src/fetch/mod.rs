@@ -0,0 +1,7 @@
+pub mod command;
+pub mod http;
+pub mod import;
+pub mod json;
+pub mod mapping;
+pub mod provider;
+pub mod update;
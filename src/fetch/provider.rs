@@ -0,0 +1,139 @@
+use chrono::Date;
+use chrono::offset::Local;
+use std::fmt;
+use core::amount::Amount;
+use core::price::Price;
+use core::symbol::Symbol;
+use super::mapping::PriceRecord;
+
+
+/// A request for the prices of a commodity, quoted in another, over a range of dates.
+#[derive(Clone, PartialEq, Debug)]
+pub struct QuoteRequest {
+    symbol: Symbol,
+    quote: Symbol,
+    start: Date<Local>,
+    end: Date<Local>,
+}
+
+impl QuoteRequest {
+    pub fn new(symbol: Symbol, quote: Symbol, start: Date<Local>, end: Date<Local>)
+    -> QuoteRequest {
+        QuoteRequest {
+            symbol: symbol,
+            quote: quote,
+            start: start,
+            end: end,
+        }
+    }
+
+    pub fn symbol(&self) -> Symbol {
+        self.symbol
+    }
+
+    pub fn quote(&self) -> Symbol {
+        self.quote
+    }
+
+    pub fn start(&self) -> Date<Local> {
+        self.start
+    }
+
+    pub fn end(&self) -> Date<Local> {
+        self.end
+    }
+
+    /// Fills in a template's `{symbol}`, `{quote}`, `{start}` and `{end}` placeholders, passing
+    /// each value through `encode`. Symbols are filled in without quotes and dates as
+    /// `YYYY-MM-DD`.
+    pub fn expand<F>(&self, template: &str, encode: F) -> String
+    where F: Fn(&str) -> String {
+        template
+            .replace("{symbol}", &encode(self.symbol.name()))
+            .replace("{quote}", &encode(self.quote.name()))
+            .replace("{start}", &encode(&self.start.format("%Y-%m-%d").to_string()))
+            .replace("{end}", &encode(&self.end.format("%Y-%m-%d").to_string()))
+    }
+
+    /// Turns records from a price source into prices, keeping those within the requested dates
+    /// and, if the records name their commodity, those for the requested commodity.
+    pub fn prices(&self, records: &[PriceRecord]) -> Vec<Price> {
+        records.iter()
+            .filter(|r| r.date() >= self.start && r.date() <= self.end)
+            .filter(|r| r.symbol().is_none() || r.symbol() == Some(self.symbol.name()))
+            .map(|r| Price::new(r.date(), self.symbol, Amount::new(r.price(), self.quote)))
+            .collect()
+    }
+}
+
+/// Reasons prices could not be fetched.
+#[derive(Debug)]
+pub enum FetchError {
+    /// The source could not be reached or read.
+    Io(String),
+    /// The source answered with an unsuccessful HTTP status.
+    Status(String, u16),
+    /// An external command failed, with what it wrote to stderr.
+    Command(String, String),
+    /// The source's output could not be read as prices.
+    Format(String),
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FetchError::Io(ref message) => write!(f, "{}", message),
+            FetchError::Status(ref url, status) => write!(f, "{}: HTTP status {}", url, status),
+            FetchError::Command(ref command, ref stderr) =>
+                write!(f, "{} failed: {}", command, stderr),
+            FetchError::Format(ref message) => write!(f, "could not read prices: {}", message),
+        }
+    }
+}
+
+/// A source of commodity prices.
+pub trait PriceProvider {
+    /// Fetches the prices for a request. Prices outside the requested dates are left out.
+    fn fetch(&self, request: &QuoteRequest) -> Result<Vec<Price>, FetchError>;
+}
+
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use chrono::offset::TimeZone;
+    use core::symbol::*;
+    use fetch::mapping::*;
+
+    /// A request for a fund's prices in dollars over two days, shared by the providers' tests.
+    pub fn request() -> QuoteRequest {
+        QuoteRequest::new(
+            Symbol::new("MUTF2351", QuoteOption::Quoted),
+            Symbol::new("$", QuoteOption::Unquoted),
+            Local.ymd(2016, 2, 8),
+            Local.ymd(2016, 2, 9))
+    }
+
+    #[test]
+    fn expand_fills_placeholders() {
+        let url = request().expand("http://quotes/{symbol}?in={quote}&from={start}&to={end}",
+            |s| s.to_string());
+        assert_eq!(url, "http://quotes/MUTF2351?in=$&from=2016-02-08&to=2016-02-09");
+    }
+
+    #[test]
+    fn prices_keeps_requested_dates_and_symbol() {
+        let contents = "symbol,date,close\n\
+            MUTF2351,2016-02-07,5.41\n\
+            MUTF2351,2016-02-08,5.61\n\
+            AAPL,2016-02-08,94.99\n\
+            MUTF2351,2016-02-09,7.10\n";
+        let mapping = FieldMapping::default().with_symbol(Some("symbol".to_string()));
+        let records = parse_records(contents, Format::Csv, &mapping).unwrap();
+        let prices = request().prices(&records);
+        let dates: Vec<Date<Local>> = prices.iter().map(|p| p.date()).collect();
+        assert_eq!(dates, vec![Local.ymd(2016, 2, 8), Local.ymd(2016, 2, 9)]);
+        assert_eq!(prices[0].symbol(), Symbol::new("MUTF2351", QuoteOption::Quoted));
+        assert_eq!(prices[0].amount().symbol(), Symbol::new("$", QuoteOption::Unquoted));
+    }
+}
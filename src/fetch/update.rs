@@ -0,0 +1,158 @@
+use chrono::Date;
+use chrono::offset::Local;
+use core::price::Price;
+use core::symbol::Symbol;
+use validate::journal::Journal;
+use super::provider::{FetchError, PriceProvider, QuoteRequest};
+
+
+/// The prices fetched for a journal's holdings, and the commodities whose prices could not be
+/// fetched.
+#[derive(Debug)]
+pub struct FetchReport {
+    prices: Vec<Price>,
+    failures: Vec<(Symbol, FetchError)>,
+}
+
+impl FetchReport {
+    /// The new prices, grouped by commodity and sorted by date within each.
+    pub fn prices(&self) -> &[Price] {
+        &self.prices
+    }
+
+    pub fn failures(&self) -> &[(Symbol, FetchError)] {
+        &self.failures
+    }
+}
+
+/// Asks a provider for the prices of every commodity held today, quoted in `quote`.
+///
+/// Each commodity is fetched from the day after its latest stored price, or from the day it
+/// was first posted if it has no prices yet, up to today. Prices implied by transactions don't
+/// count as stored.
+pub fn fetch_new_prices<P>(journal: &Journal, provider: &P, quote: Symbol, today: Date<Local>)
+-> FetchReport
+where P: PriceProvider + ?Sized {
+    let mut prices = Vec::new();
+    let mut failures = Vec::new();
+    for amount in journal.holdings(today).amounts() {
+        let symbol = amount.symbol();
+        if symbol == quote {
+            continue;
+        }
+        let start = match last_stored_date(journal, symbol, quote) {
+            Some(date) => date.succ(),
+            None => first_posted_date(journal, symbol).unwrap_or(today),
+        };
+        if start > today {
+            continue;
+        }
+        match provider.fetch(&QuoteRequest::new(symbol, quote, start, today)) {
+            Ok(mut fetched) => {
+                fetched.sort_by_key(|p| p.sort_key());
+                prices.extend(fetched);
+            },
+            Err(err) => failures.push((symbol, err)),
+        }
+    }
+    FetchReport {
+        prices: prices,
+        failures: failures,
+    }
+}
+
+
+
+// HELPERS
+
+fn last_stored_date(journal: &Journal, symbol: Symbol, quote: Symbol) -> Option<Date<Local>> {
+    journal.prices().iter()
+        .filter(|p| p.symbol() == symbol && p.amount().symbol() == quote)
+        .map(|p| p.date())
+        .max()
+}
+
+fn first_posted_date(journal: &Journal, symbol: Symbol) -> Option<Date<Local>> {
+    journal.postings().iter()
+        .find(|p| p.posting().amount().symbol() == symbol)
+        .map(|p| p.date())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::offset::TimeZone;
+    use std::cell::RefCell;
+    use core::amount::Amount;
+    use core::symbol::*;
+    use parser::parse::parse_ledger_str;
+
+    /// Answers every request with a price on each day requested, recording the requests.
+    struct DailyProvider {
+        requests: RefCell<Vec<QuoteRequest>>,
+    }
+
+    impl PriceProvider for DailyProvider {
+        fn fetch(&self, request: &QuoteRequest) -> Result<Vec<Price>, FetchError> {
+            self.requests.borrow_mut().push(request.clone());
+            if request.symbol().name() == "AAPL" {
+                return Err(FetchError::Io("no quotes for AAPL".to_string()));
+            }
+            let mut prices = Vec::new();
+            let mut date = request.start();
+            while date <= request.end() {
+                prices.push(Price::new(date, request.symbol(),
+                    Amount::new(d128!(5.61), request.quote())));
+                date = date.succ();
+            }
+            Ok(prices)
+        }
+    }
+
+    fn journal() -> Journal {
+        let tree = parse_ledger_str("\
+            2016-02-01 * Buy fund\n\
+            \tAssets:Investments    10 \"MUTF2351\" @ $5.00\n\
+            \tAssets:Checking\n\
+            2016-02-03 * Buy shares\n\
+            \tAssets:Investments    1 AAPL @ $95.00\n\
+            \tAssets:Checking\n\
+            2016-02-04 * Buy and sell\n\
+            \tAssets:Investments    1 GOOG @ $700.00\n\
+            \tAssets:Investments    -1 GOOG @ $700.00\n\
+            P 2016-02-07 \"MUTF2351\" $5.41\n").unwrap();
+        Journal::from_parse_tree(tree).unwrap()
+    }
+
+    #[test]
+    fn fetch_new_prices_since_last_stored_date() {
+        let journal = journal();
+        let provider = DailyProvider { requests: RefCell::new(Vec::new()) };
+        let dollars = Symbol::new("$", QuoteOption::Unquoted);
+        let report = fetch_new_prices(&journal, &provider, dollars, Local.ymd(2016, 2, 9));
+
+        let requests = provider.requests.borrow();
+        let requested: Vec<(&str, Date<Local>)> = requests.iter()
+            .map(|r| (r.symbol().name(), r.start()))
+            .collect();
+        assert_eq!(requested, vec![
+            ("AAPL", Local.ymd(2016, 2, 3)),
+            ("MUTF2351", Local.ymd(2016, 2, 8)),
+        ]);
+        assert_eq!(report.prices().len(), 2);
+        assert_eq!(report.prices()[0].date(), Local.ymd(2016, 2, 8));
+        assert_eq!(report.failures().len(), 1);
+        assert_eq!(format!("{}", report.failures()[0].1), "no quotes for AAPL");
+    }
+
+    #[test]
+    fn fetch_new_prices_skips_up_to_date_commodities() {
+        let journal = journal();
+        let provider = DailyProvider { requests: RefCell::new(Vec::new()) };
+        let dollars = Symbol::new("$", QuoteOption::Unquoted);
+        let report = fetch_new_prices(&journal, &provider, dollars, Local.ymd(2016, 2, 7));
+        assert_eq!(provider.requests.borrow().len(), 1);
+        assert!(report.prices().is_empty());
+    }
+}
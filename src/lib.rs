@@ -3,6 +3,16 @@ extern crate chrono;
 extern crate combine;
 #[macro_use]
 extern crate decimal;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
 
 pub mod core;
-pub mod parser;
\ No newline at end of file
+pub mod fetch;
+pub mod parser;
+pub mod report;
+pub mod validate;
\ No newline at end of file
@@ -1,24 +1,517 @@
+extern crate chrono;
 extern crate wealth_pulse;
 
-use wealth_pulse::parser::parse::{parse_ledger, parse_pricedb};
+use chrono::offset::Local;
+use wealth_pulse::core::commodity::{Commodity, CommodityRegistry};
+use wealth_pulse::core::compact::{compact_prices, months_before, Resolution};
+use wealth_pulse::core::symbol::Symbol;
+use wealth_pulse::fetch::command::CommandProvider;
+use wealth_pulse::fetch::http::HttpProvider;
+use wealth_pulse::fetch::import::import_prices;
+use wealth_pulse::fetch::mapping::{parse_records, FieldMapping, Format};
+use wealth_pulse::fetch::provider::PriceProvider;
+use wealth_pulse::fetch::update::fetch_new_prices;
+use wealth_pulse::parser::parse::{read_located_pricedb, read_pricedb};
+use wealth_pulse::parser::write::{format_price, write_pricedb};
+use wealth_pulse::report::staleness::StalenessReport;
+use wealth_pulse::report::stats::JournalStats;
+use wealth_pulse::validate::duplicate::{find_duplicates, DuplicateOptions};
+use wealth_pulse::validate::journal::Journal;
+use wealth_pulse::validate::lint::{lint, LintConfig, Severity};
+use wealth_pulse::validate::price_check::{check_prices, PriceCheckOptions};
+use wealth_pulse::validate::strict::Strictness;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::env;
+use std::fs;
+use std::process;
+
+const USAGE: &str = "\
+usage: wealth_pulse [--strict | --strict-payees] [command]
+
+commands:
+    stats       summarise the journal (default)
+    lint        check the journal for likely mistakes
+        --disable <rule>            don't run a rule
+        --severity <rule>=<level>   report a rule's findings as warning or error
+        --format human|machine      output one finding per line, or tab-separated records
+    duplicates  list transactions that look like they were entered twice
+        --window <days>             largest gap between duplicates' dates (default 3)
+        --min-confidence <0-1>      lowest confidence worth reporting (default 0.6)
+    prices fetch  add quotes for held commodities since their last stored price to the price DB
+        --url <template>            fetch over HTTP from a URL with {symbol}, {quote}, {start}
+                                    and {end} placeholders
+        --command <command line>    fetch by running a command, with the same placeholders
+        --quote <commodity>         commodity to fetch prices in (default $)
+    prices import <file>  add prices from a CSV (or JSON) file to the price DB, skipping ones
+                          already there
+        --symbol <commodity>        commodity the prices are for, if the file doesn't say
+        --quote <commodity>         commodity the prices are in (default $)
+    prices stale  show how old the latest price of each held commodity is, and gaps in its prices
+        --quote <commodity>         commodity the prices should be in (default $)
+        --gap-days <days>           shortest gap between prices worth reporting (default 7)
+    prices check  look for suspicious prices in the price DB, such as jumps and typos
+        --max-change <percent>      largest move between prices not reported (default 20)
+    prices compact  thin out old prices in the price DB, keeping one per month before a cutoff
+        --keep-months <months>      months of prices to keep in full (default 12)
+        --resolution month|week     keep month-end or week-end prices before that
+        --dry-run                   list the prices that would be removed instead
+    prices implied  list the prices implied by transaction costs and conversions
+        --write                     add those on dates without a stored price to the price DB
+
+field mapping options, for commands reading prices from CSV or JSON:
+        --format csv|json           shape of the price source (default csv)
+        --date-field <field>        column or JSON path holding the date (default date)
+        --price-field <field>       column or JSON path holding the price (default close)
+        --symbol-field <field>      column or JSON path holding the commodity, if any
+        --records <path>            JSON path to the array of records (default the document)
+        --date-format <format>      strftime-style date format (default %Y-%m-%d)";
+
+const MAPPING_OPTIONS: &[&str] =
+    &["--format", "--date-field", "--price-field", "--symbol-field", "--records", "--date-format"];
 
 // MAIN
 
 fn main() {
-    let pricedb_filepath = env::var("WEALTH_PULSE_PRICES_FILE")
-        .expect("Could not read WEALTH_PULSE_PRICES_FILE environment variable");
-    let ledger_filepath = env::var("LEDGER_FILE")
-        .expect("Could not read LEDGER_FILE environment variable");
+    let mut strictness = Strictness::Permissive;
+    let mut args = Vec::new();
+    for arg in env::args().skip(1) {
+        match &arg[..] {
+            "--strict" => strictness = Strictness::Strict,
+            "--strict-payees" => strictness = Strictness::StrictWithPayees,
+            _ => args.push(arg),
+        }
+    }
+
+    let (command, args) =
+        match args.split_first() {
+            Some((command, args)) => (&command[..], args),
+            None => ("stats", &args[..]),
+        };
+    match command {
+        "stats" => stats(strictness),
+        "lint" => lint_journal(strictness, args),
+        "duplicates" => duplicates(strictness, args),
+        "prices" => prices(strictness, args),
+        _ => usage_error(&format!("unknown command: {}", command)),
+    }
+}
+
+
+
+// COMMANDS
+
+fn stats(strictness: Strictness) {
+    let journal = load_journal(strictness);
+    println!("{}", JournalStats::new(&journal));
+}
+
+/// Prints lint findings, exiting with a failure status if any of them are errors.
+fn lint_journal(strictness: Strictness, args: &[String]) {
+    let mut config = LintConfig::new();
+    let mut machine = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| usage_error(&format!("{} needs a value", arg)));
+        match &arg[..] {
+            "--disable" => {
+                let rule = value.parse().unwrap_or_else(|e: String| usage_error(&e));
+                config.disable(rule);
+            },
+            "--severity" => {
+                let mut parts = value.splitn(2, '=');
+                let rule = parts.next().unwrap_or("").parse()
+                    .unwrap_or_else(|e: String| usage_error(&e));
+                let severity = parts.next().unwrap_or("").parse()
+                    .unwrap_or_else(|e: String| usage_error(&e));
+                config.enable(rule, severity);
+            },
+            "--format" => {
+                machine = match &value[..] {
+                    "human" => false,
+                    "machine" => true,
+                    _ => usage_error(&format!("unknown format: {}", value)),
+                };
+            },
+            _ => usage_error(&format!("unknown option: {}", arg)),
+        }
+    }
+
+    let journal = load_journal(strictness);
+    let diagnostics = lint(&journal, &config, Local::today());
+    for diagnostic in &diagnostics {
+        if machine {
+            println!("{}", diagnostic.to_record());
+        } else {
+            println!("{}", diagnostic);
+        }
+    }
+    if diagnostics.iter().any(|d| d.severity() == Severity::Error) {
+        process::exit(1);
+    }
+}
+
+
+/// Prints likely duplicate transactions, most confident first, with both locations.
+fn duplicates(strictness: Strictness, args: &[String]) {
+    let defaults = DuplicateOptions::default();
+    let mut window_days = defaults.window_days();
+    let mut min_confidence = defaults.min_confidence();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| usage_error(&format!("{} needs a value", arg)));
+        match &arg[..] {
+            "--window" => {
+                window_days = value.parse()
+                    .unwrap_or_else(|_| usage_error(&format!("invalid window: {}", value)));
+            },
+            "--min-confidence" => {
+                min_confidence = value.parse()
+                    .unwrap_or_else(|_| usage_error(&format!("invalid confidence: {}", value)));
+            },
+            _ => usage_error(&format!("unknown option: {}", arg)),
+        }
+    }
+
+    let journal = load_journal(strictness);
+    let options = DuplicateOptions::new(window_days, min_confidence);
+    for duplicate in find_duplicates(&journal, &options) {
+        let describe = |index: usize| {
+            let header = journal.transactions()[index].header();
+            format!("{} {}", header.date().format("%Y-%m-%d"), header.payee().trim())
+        };
+        println!("{:.0}% likely duplicates:", duplicate.confidence() * 100.0);
+        println!("    {}: {}", duplicate.first_location(), describe(duplicate.first()));
+        println!("    {}: {}", duplicate.second_location(), describe(duplicate.second()));
+    }
+}
+
+
+fn prices(strictness: Strictness, args: &[String]) {
+    match args.split_first() {
+        Some((command, args)) if command == "fetch" => fetch_prices(strictness, args),
+        Some((command, args)) if command == "import" => import_price_file(strictness, args),
+        Some((command, args)) if command == "implied" => implied_prices(strictness, args),
+        Some((command, args)) if command == "stale" => stale_prices(strictness, args),
+        Some((command, args)) if command == "check" => check_pricedb(args),
+        Some((command, args)) if command == "compact" => compact_pricedb(args),
+        Some((command, _)) => usage_error(&format!("unknown prices command: {}", command)),
+        None => usage_error("prices needs a command"),
+    }
+}
+
+/// Fetches new prices for held commodities and adds them to the price DB, exiting with a
+/// failure status if any commodity's prices couldn't be fetched.
+fn fetch_prices(strictness: Strictness, args: &[String]) {
+    let mut provider_option = None;
+    let mut quote_name = "$";
+    let mut mapping_options = HashMap::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| usage_error(&format!("{} needs a value", arg)));
+        match &arg[..] {
+            "--url" | "--command" => provider_option = Some((arg, value)),
+            "--quote" => quote_name = value,
+            _ if MAPPING_OPTIONS.contains(&&arg[..]) => {
+                mapping_options.insert(&arg[..], &value[..]);
+            },
+            _ => usage_error(&format!("unknown option: {}", arg)),
+        }
+    }
+    let (format, mapping) = field_mapping(&mapping_options);
+    let provider: Box<dyn PriceProvider> = match provider_option {
+        Some((option, url)) if option == "--url" =>
+            Box::new(HttpProvider::new(url, format, mapping)),
+        Some((_, command_line)) => Box::new(
+            CommandProvider::from_command_line(command_line, format, mapping)
+                .unwrap_or_else(|| usage_error("--command needs a command line"))),
+        None => usage_error("prices fetch needs --url or --command"),
+    };
+
+    let journal = load_journal(strictness);
+    let quote = symbol_named(&journal, quote_name);
+    let report = fetch_new_prices(&journal, &*provider, quote, Local::today());
+    for &(symbol, ref err) in report.failures() {
+        eprintln!("{}: {}", symbol, err);
+    }
+    let pricedb_filepath = pricedb_filepath();
+    if !report.prices().is_empty() {
+        let mut prices: Vec<_> = read_pricedb(&pricedb_filepath)
+            .unwrap_or_else(|err| exit_with_error(&err))
+            .into_iter()
+            .map(|(price, _)| price)
+            .collect();
+        prices.extend(report.prices().iter().cloned());
+        let conflicts = write_pricedb(&pricedb_filepath, &prices, journal.commodities())
+            .unwrap_or_else(|err| exit_with_error(&format!("{}: {}", pricedb_filepath, err)));
+        for conflict in conflicts {
+            eprintln!("warning: {}", conflict);
+        }
+    }
+    println!("added {} prices to {}", report.prices().len(), pricedb_filepath);
+    if !report.failures().is_empty() {
+        process::exit(1);
+    }
+}
+
+
+/// Adds prices from a file to the price DB, reporting how many were added, already there, or
+/// conflicting with a recorded price.
+fn import_price_file(strictness: Strictness, args: &[String]) {
+    let (file_path, args) = args.split_first()
+        .unwrap_or_else(|| usage_error("prices import needs a file"));
+    let mut symbol_name = None;
+    let mut quote_name = "$";
+    let mut mapping_options = HashMap::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| usage_error(&format!("{} needs a value", arg)));
+        match &arg[..] {
+            "--symbol" => symbol_name = Some(value),
+            "--quote" => quote_name = value,
+            _ if MAPPING_OPTIONS.contains(&&arg[..]) => {
+                mapping_options.insert(&arg[..], &value[..]);
+            },
+            _ => usage_error(&format!("unknown option: {}", arg)),
+        }
+    }
+    let (format, mapping) = field_mapping(&mapping_options);
+
+    let journal = load_journal(strictness);
+    let symbol = symbol_name.map(|name| symbol_named(&journal, name));
+    let quote = symbol_named(&journal, quote_name);
+    let contents = fs::read_to_string(file_path)
+        .unwrap_or_else(|err| exit_with_error(&format!("{}: {}", file_path, err)));
+    let records = parse_records(&contents, format, &mapping)
+        .unwrap_or_else(|err| exit_with_error(&format!("{}: {}", file_path, err)));
+    let pricedb_filepath = pricedb_filepath();
+    let mut prices: Vec<_> = read_pricedb(&pricedb_filepath)
+        .unwrap_or_else(|err| exit_with_error(&err))
+        .into_iter()
+        .map(|(price, _)| price)
+        .collect();
+    let report = import_prices(&prices, &records, symbol, quote, journal.commodities())
+        .unwrap_or_else(|err| exit_with_error(&format!("{}: {}", file_path, err)));
+    for conflict in report.conflicts() {
+        eprintln!("{}: {}", file_path, conflict);
+    }
+    if !report.added().is_empty() {
+        prices.extend(report.added().iter().cloned());
+        write_pricedb(&pricedb_filepath, &prices, journal.commodities())
+            .unwrap_or_else(|err| exit_with_error(&format!("{}: {}", pricedb_filepath, err)));
+    }
+    println!("{} added, {} skipped, {} conflicting", report.added().len(), report.skipped(),
+        report.conflicts().len());
+}
 
-    let prices = parse_pricedb(&pricedb_filepath);
-    println!("Parsed {} prices", prices.len());
 
-    let tree = parse_ledger(&ledger_filepath);
-    println!("Parsed {} tree items", tree.len());
+/// Prints the prices implied by transactions, optionally recording those on dates the price DB
+/// has no price for. Transactions that imply different prices for a commodity on the same date
+/// are reported and not recorded. Recorded prices reload as price DB prices, so they are no
+/// longer told apart from quotes by `PriceOrigin`.
+fn implied_prices(strictness: Strictness, args: &[String]) {
+    let mut write = false;
+    for arg in args {
+        match &arg[..] {
+            "--write" => write = true,
+            _ => usage_error(&format!("unknown option: {}", arg)),
+        }
+    }
 
-    // for price in &prices {
-    //     println!("{}", price);
-    // }
+    let journal = load_journal(strictness);
+    for price in journal.implied_prices() {
+        println!("{}", format_price(price, journal.commodities()));
+    }
+    if write {
+        let pricedb_filepath = pricedb_filepath();
+        let mut prices: Vec<_> = read_pricedb(&pricedb_filepath)
+            .unwrap_or_else(|err| exit_with_error(&err))
+            .into_iter()
+            .map(|(price, _)| price)
+            .collect();
+        let stored: HashSet<_> = prices.iter()
+            .map(|p| (p.date(), p.symbol(), p.amount().symbol()))
+            .collect();
+        let mut implied = BTreeMap::new();
+        for price in journal.implied_prices() {
+            let key = (price.date(), price.symbol(), price.amount().symbol());
+            if !stored.contains(&key) {
+                implied.entry(key).or_insert_with(Vec::new).push(price);
+            }
+        }
+
+        let mut new_prices = Vec::new();
+        let mut conflicts = 0;
+        for ((date, symbol, _), same_day) in implied {
+            if same_day.iter().all(|p| p.amount() == same_day[0].amount()) {
+                new_prices.push(same_day[0].clone());
+                continue;
+            }
+            let amounts: Vec<String> = same_day.iter()
+                .map(|p| journal.commodities().format_exact(p.amount().quantity(),
+                    p.amount().symbol()))
+                .collect();
+            eprintln!("warning: {} {}: transactions imply conflicting prices {}, not written",
+                date.format("%Y-%m-%d"), symbol, amounts.join(", "));
+            conflicts += 1;
+        }
+        prices.extend(new_prices.iter().cloned());
+        write_pricedb(&pricedb_filepath, &prices, journal.commodities())
+            .unwrap_or_else(|err| exit_with_error(&format!("{}: {}", pricedb_filepath, err)));
+        eprintln!("added {} prices to {}", new_prices.len(), pricedb_filepath);
+        if conflicts > 0 {
+            process::exit(1);
+        }
+    }
+}
+
+
+/// Prints how current the prices of held commodities are.
+fn stale_prices(strictness: Strictness, args: &[String]) {
+    let mut quote_name = "$";
+    let mut gap_days = 7;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| usage_error(&format!("{} needs a value", arg)));
+        match &arg[..] {
+            "--quote" => quote_name = value,
+            "--gap-days" => {
+                gap_days = value.parse()
+                    .unwrap_or_else(|_| usage_error(&format!("invalid gap: {}", value)));
+            },
+            _ => usage_error(&format!("unknown option: {}", arg)),
+        }
+    }
+
+    let journal = load_journal(strictness);
+    let quote = symbol_named(&journal, quote_name);
+    println!("{}", StalenessReport::new(&journal, quote, Local::today(), gap_days));
+}
+
+
+/// Prints suspicious entries in the price DB with their likely explanations.
+fn check_pricedb(args: &[String]) {
+    let mut options = PriceCheckOptions::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| usage_error(&format!("{} needs a value", arg)));
+        match &arg[..] {
+            "--max-change" => {
+                let percent: f64 = value.parse()
+                    .unwrap_or_else(|_| usage_error(&format!("invalid change: {}", value)));
+                options = PriceCheckOptions::new(percent / 100.0);
+            },
+            _ => usage_error(&format!("unknown option: {}", arg)),
+        }
+    }
+
+    let entries = read_located_pricedb(&pricedb_filepath())
+        .unwrap_or_else(|err| exit_with_error(&err));
+    for finding in check_prices(&entries, &options) {
+        println!("{}", finding);
+    }
+}
+
+
+/// Removes old prices from the price DB, keeping the last price of each month or week before
+/// the cutoff.
+fn compact_pricedb(args: &[String]) {
+    let mut keep_months = 12;
+    let mut resolution = Resolution::MonthEnd;
+    let mut dry_run = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--dry-run" {
+            dry_run = true;
+            continue;
+        }
+        let value = args.next().unwrap_or_else(|| usage_error(&format!("{} needs a value", arg)));
+        match &arg[..] {
+            "--keep-months" => {
+                keep_months = value.parse()
+                    .unwrap_or_else(|_| usage_error(&format!("invalid months: {}", value)));
+            },
+            "--resolution" => {
+                resolution = value.parse().unwrap_or_else(|e: String| usage_error(&e));
+            },
+            _ => usage_error(&format!("unknown option: {}", arg)),
+        }
+    }
+
+    let pricedb_filepath = pricedb_filepath();
+    let mut commodities = CommodityRegistry::new();
+    let prices: Vec<_> = read_pricedb(&pricedb_filepath)
+        .unwrap_or_else(|err| exit_with_error(&err))
+        .into_iter()
+        .map(|(price, render_opts)| {
+            commodities.observe(Commodity::new(price.amount().symbol(), render_opts));
+            price
+        })
+        .collect();
+    let keep_since = months_before(Local::today(), keep_months);
+    let compaction = compact_prices(&prices, keep_since, resolution);
+    if dry_run {
+        for price in compaction.removed() {
+            println!("{}", format_price(price, &commodities));
+        }
+        eprintln!("would remove {} of {} prices", compaction.removed().len(), prices.len());
+    } else {
+        write_pricedb(&pricedb_filepath, compaction.kept(), &commodities)
+            .unwrap_or_else(|err| exit_with_error(&format!("{}: {}", pricedb_filepath, err)));
+        println!("removed {} of {} prices", compaction.removed().len(), prices.len());
+    }
+}
+
+
+
+// HELPERS
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    process::exit(2);
+}
+
+fn exit_with_error<E: ::std::fmt::Display>(err: &E) -> ! {
+    eprintln!("{}", err);
+    process::exit(1);
+}
+
+/// Builds the format and field mapping for a price source from the field mapping options.
+fn field_mapping(options: &HashMap<&str, &str>) -> (Format, FieldMapping) {
+    let option = |name: &str| options.get(name).map(|value| value.to_string());
+    let format = option("--format").map_or(Format::Csv, |format| {
+        format.parse().unwrap_or_else(|e: String| usage_error(&e))
+    });
+    let defaults = FieldMapping::default();
+    let mapping = FieldMapping::new(
+            options.get("--date-field").cloned().unwrap_or(defaults.date()),
+            options.get("--price-field").cloned().unwrap_or(defaults.price()))
+        .with_symbol(option("--symbol-field"))
+        .with_records(option("--records"))
+        .with_date_format(option("--date-format"));
+    (format, mapping)
+}
+
+fn pricedb_filepath() -> String {
+    env::var("WEALTH_PULSE_PRICES_FILE")
+        .expect("Could not read WEALTH_PULSE_PRICES_FILE environment variable")
+}
+
+/// Loads the journal named by LEDGER_FILE along with the prices in WEALTH_PULSE_PRICES_FILE,
+/// exiting with the error if either can't be loaded.
+fn load_journal(strictness: Strictness) -> Journal {
+    let pricedb_filepath = pricedb_filepath();
+    let ledger_filepath = env::var("LEDGER_FILE")
+        .expect("Could not read LEDGER_FILE environment variable");
+
+    let journal = Journal::load_with(&ledger_filepath, strictness)
+        .and_then(|mut journal| journal.add_pricedb(&pricedb_filepath).map(|_| journal));
+    journal.unwrap_or_else(|err| exit_with_error(&err))
+}
 
+/// The symbol a commodity named on the command line is known by in the journal, however it is
+/// quoted there, e.g. `AAPL` for prices recorded for `"AAPL"`.
+fn symbol_named(journal: &Journal, name: &str) -> Symbol {
+    journal.commodities().find(name)
+        .or_else(|| journal.prices().iter().map(|p| p.symbol()).find(|s| s.name() == name))
+        .unwrap_or_else(|| Symbol::from_name(name))
 }
@@ -1,37 +1,178 @@
+use core::account::{AccountName, AccountType};
 use core::amount::*;
+use core::commodity::*;
+use core::corporate_action::CorporateAction;
+use core::location::SourceLocation;
 use core::price::Price;
 use core::transaction::*;
 
 #[derive(PartialEq, Debug)]
-pub enum AmountSource {
-    Provided,
-    Inferred,
-}
-
-#[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RawPosting {
-    full_account: String,
-    sub_accounts: Vec<String>,
+    account: AccountName,
     amount: Option<Amount>,
+    render_options: Option<RenderOptions>,
     amount_source: AmountSource,
+    cost: Option<Cost>,
+    cost_render_options: Option<RenderOptions>,
+    assertion: Option<Amount>,
     comment: Option<String>,
+    line: Option<usize>,
 }
 
 impl RawPosting {
-    pub fn new(sub_accounts: Vec<String>, amount: Option<Amount>,
+    pub fn new(account: AccountName, amount: Option<(Amount, RenderOptions)>,
     amount_source: AmountSource, comment: Option<String>) -> RawPosting {
+        let (amount, render_options) =
+            match amount {
+                Some((amount, render_options)) => (Some(amount), Some(render_options)),
+                None => (None, None),
+            };
         RawPosting {
-            full_account: sub_accounts.join(":"),
-            sub_accounts: sub_accounts,
+            account: account,
             amount: amount,
+            render_options: render_options,
             amount_source: amount_source,
-            comment: comment
+            cost: None,
+            cost_render_options: None,
+            assertion: None,
+            comment: comment,
+            line: None,
+        }
+    }
+
+    /// Returns this posting with the given cost, along with the render options the cost was
+    /// written with.
+    pub fn with_cost(self, cost: Option<(Cost, RenderOptions)>) -> RawPosting {
+        let (cost, cost_render_options) =
+            match cost {
+                Some((cost, render_options)) => (Some(cost), Some(render_options)),
+                None => (None, None),
+            };
+        RawPosting {
+            cost: cost,
+            cost_render_options: cost_render_options,
+            .. self
+        }
+    }
+
+    /// Returns this posting with the given balance assertion.
+    pub fn with_assertion(self, assertion: Option<Amount>) -> RawPosting {
+        RawPosting {
+            assertion: assertion,
+            .. self
+        }
+    }
+
+    /// Returns this posting with the line it was written on.
+    pub fn with_line(self, line: usize) -> RawPosting {
+        RawPosting {
+            line: Some(line),
+            .. self
         }
     }
+
+    pub fn account(&self) -> AccountName {
+        self.account
+    }
+
+    pub fn amount(&self) -> Option<&Amount> {
+        self.amount.as_ref()
+    }
+
+    pub fn amount_source(&self) -> AmountSource {
+        self.amount_source
+    }
+
+    pub fn cost(&self) -> Option<&Cost> {
+        self.cost.as_ref()
+    }
+
+    /// The balance the account is asserted to have after this posting.
+    pub fn assertion(&self) -> Option<&Amount> {
+        self.assertion.as_ref()
+    }
+
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_ref().map(|c| &c[..])
+    }
+
+    /// The line the posting was written on, if it was parsed as part of a transaction.
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    /// The render options the cost was written with, if a cost was provided.
+    pub fn cost_render_options(&self) -> Option<&RenderOptions> {
+        self.cost_render_options.as_ref()
+    }
+
+    /// The render options the amount was written with, if an amount was provided.
+    pub fn render_options(&self) -> Option<&RenderOptions> {
+        self.render_options.as_ref()
+    }
 }
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ParseTree {
-    Price(Price),
-    Transaction(Header, Vec<RawPosting>),
-}
\ No newline at end of file
+    Account(SourceLocation, AccountName, Option<AccountType>),
+    Commodity(Commodity),
+    Price(SourceLocation, Price, RenderOptions),
+    Transaction(SourceLocation, Header, Vec<RawPosting>),
+    Include(String),
+    Payee(String),
+    CorporateAction(SourceLocation, CorporateAction),
+}
+
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+    use chrono::offset::Local;
+    use chrono::offset::TimeZone;
+    use core::account::AccountName;
+    use core::symbol::*;
+
+    #[test]
+    fn parse_tree_json_round_trip() {
+        let dollars = RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+            ThousandsSeparator::Comma, 2);
+        let tree = vec![
+            ParseTree::Commodity(Commodity::new(Symbol::new("$", QuoteOption::Unquoted), dollars)),
+            ParseTree::Price(
+                SourceLocation::new(2),
+                Price::new(
+                    Local.ymd(2016, 6, 7),
+                    Symbol::new("MUTF2351", QuoteOption::Quoted),
+                    Amount::new(d128!(4.56), Symbol::new("$", QuoteOption::Unquoted))),
+                dollars),
+            ParseTree::Transaction(
+                SourceLocation::new(3),
+                Header::new(
+                    Local.ymd(2016, 6, 7),
+                    Status::Cleared,
+                    Some("conf# abc-123".to_string()),
+                    "Basic transaction".to_string(),
+                    None),
+                vec![
+                    RawPosting::new(
+                        AccountName::new("Expenses:Groceries"),
+                        Some((Amount::new(d128!(1045.00), Symbol::new("$", QuoteOption::Unquoted)),
+                            dollars)),
+                        AmountSource::Provided,
+                        Some("comment".to_string())),
+                    RawPosting::new(
+                        AccountName::new("Liabilities:Credit"),
+                        None,
+                        AmountSource::Inferred,
+                        None),
+                ]),
+        ];
+        let json = ::serde_json::to_string(&tree).unwrap();
+        assert!(json.contains("\"account\":\"Expenses:Groceries\""));
+        assert!(json.contains("\"quantity\":\"1045.00\""));
+        let result: Vec<ParseTree> = ::serde_json::from_str(&json).unwrap();
+        assert_eq!(result, tree);
+    }
+}
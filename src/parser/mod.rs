@@ -1,2 +1,3 @@
 pub mod parse;
-pub mod ast;
\ No newline at end of file
+pub mod ast;
+pub mod write;
@@ -1,16 +1,22 @@
 use rust_core::str::FromStr;
-use chrono::Date;
+use chrono::{Date, NaiveTime};
 use chrono::offset::Local;
 use chrono::offset::TimeZone;
-use combine::{between, many, many1, optional, parser, satisfy, sep_by1, sep_end_by, skip_many, try,
-    Parser, ParseResult};
-use combine::char::{alpha_num, char, crlf, digit, newline};
+use combine::{between, many, many1, optional, parser, position, satisfy, sep_by1, sep_end_by,
+    skip_many, try, Parser, ParseResult, State};
+use combine::char::{alpha_num, char, crlf, digit, newline, string};
 use combine::combinator::FnParser;
-use combine::primitives::{Stream};
+use combine::primitives::{Error, SourcePosition, Stream};
 use decimal::d128;
+use std::fmt;
 use std::fs::File;
+use std::io;
 use std::io::Read;
+use core::account::{AccountName, AccountType, AccountTypes};
 use core::amount::*;
+use core::commodity::*;
+use core::corporate_action::{ActionKind, CorporateAction};
+use core::location::SourceLocation;
 use core::price::*;
 use core::symbol::*;
 use core::transaction::*;
@@ -67,8 +73,20 @@ where I: Stream<Item=char> {
         .parse_stream(input)
 }
 
-/// Parses a quantity
-fn quantity<I>(input: I) -> ParseResult<d128,I>
+/// Parses a time of day, with or without seconds. e.g. 16:00 or 16:00:00
+fn time<I>(input: I) -> ParseResult<NaiveTime, I>
+where I: Stream<Item=char> {
+    (two_digits(), char(':'), two_digits(), optional(char(':').with(two_digits())))
+        .and_then(|(hour, _, minute, second)| {
+            NaiveTime::from_hms_opt(hour, minute, second.unwrap_or(0))
+                .ok_or_else(|| Error::Expected("a valid time of day".into()))
+        })
+        .parse_stream(input)
+}
+
+/// Parses a quantity, along with the thousands separator and number of decimal places it was
+/// written with.
+fn quantity<I>(input: I) -> ParseResult<(d128, ThousandsSeparator, u32), I>
 where I: Stream<Item=char> {
     (
         optional(char('-')).map(|x| {
@@ -82,9 +100,20 @@ where I: Stream<Item=char> {
         }))
     )
         .map(|(sign, numbers)| {
+            let separator =
+                if numbers.contains(',') {
+                    ThousandsSeparator::Comma
+                } else {
+                    ThousandsSeparator::NoSeparator
+                };
+            let precision =
+                match numbers.find('.') {
+                    Some(index) => (numbers.len() - index - 1) as u32,
+                    None => 0,
+                };
             let mut qty = format!("{}{}", sign, numbers);
             qty = qty.replace(",", "");
-            d128::from_str(&qty[..]).unwrap()
+            (d128::from_str(&qty[..]).unwrap(), separator, precision)
         })
         .parse_stream(input)
 }
@@ -100,7 +129,7 @@ where I: Stream<Item=char> {
 /// Parses an unquoted symbol
 fn unquoted_symbol<I>(input: I) -> ParseResult<Symbol, I>
 where I: Stream<Item=char> {
-    many1::<String, _>(satisfy(|c| "-0123456789; \"\t\r\n".chars().all(|s| s != c)))
+    many1::<String, _>(satisfy(|c| "-0123456789; \"\t\r\n=@".chars().all(|s| s != c)))
         .map(|symbol| Symbol::new(symbol, QuoteOption::Unquoted))
         .parse_stream(input)
 }
@@ -114,37 +143,39 @@ where I: Stream<Item=char> {
 }
 
 /// Parses an amount in the format of symbol then quantity.
-fn amount_symbol_then_quantity<I>(input: I) -> ParseResult<Amount, I>
+fn amount_symbol_then_quantity<I>(input: I) -> ParseResult<(Amount, RenderOptions), I>
 where I: Stream<Item=char> {
     (parser(symbol), optional(parser(whitespace)), parser(quantity))
-        .map(|(symbol, opt_whitespace, quantity)| {
+        .map(|(symbol, opt_whitespace, (quantity, separator, precision))| {
             let spacing = match opt_whitespace {
                 Some(_) => Spacing::Space,
                 None => Spacing::NoSpace,
             };
-            let render_opts = RenderOptions::new(SymbolPosition::Left, spacing);
-            Amount::new(quantity, symbol, render_opts)
+            let render_opts =
+                RenderOptions::new(SymbolPosition::Left, spacing, separator, precision);
+            (Amount::new(quantity, symbol), render_opts)
         })
         .parse_stream(input)
 }
 
 /// Parses an amount in the format of quantity then symbol.
-fn amount_quantity_then_symbol<I>(input: I) -> ParseResult<Amount, I>
+fn amount_quantity_then_symbol<I>(input: I) -> ParseResult<(Amount, RenderOptions), I>
 where I: Stream<Item=char> {
     (parser(quantity), optional(parser(whitespace)), parser(symbol))
-        .map(|(quantity, opt_whitespace, symbol)| {
+        .map(|((quantity, separator, precision), opt_whitespace, symbol)| {
             let spacing = match opt_whitespace {
                 Some(_) => Spacing::Space,
                 None => Spacing::NoSpace,
             };
-            let render_opts = RenderOptions::new(SymbolPosition::Right, spacing);
-            Amount::new(quantity, symbol, render_opts)
+            let render_opts =
+                RenderOptions::new(SymbolPosition::Right, spacing, separator, precision);
+            (Amount::new(quantity, symbol), render_opts)
         })
         .parse_stream(input)
 }
 
-/// Parses an amount
-fn amount<I>(input: I) -> ParseResult<Amount, I>
+/// Parses an amount, along with the render options it was written with.
+fn amount<I>(input: I) -> ParseResult<(Amount, RenderOptions), I>
 where I: Stream<Item=char> {
     parser(amount_symbol_then_quantity)
         .or(parser(amount_quantity_then_symbol))
@@ -152,7 +183,7 @@ where I: Stream<Item=char> {
 }
 
 /// Parses an amount or an inferred amount
-fn amount_or_inferred<I>(input: I) -> ParseResult<(AmountSource, Option<Amount>), I>
+fn amount_or_inferred<I>(input: I) -> ParseResult<(AmountSource, Option<(Amount, RenderOptions)>), I>
 where I: Stream<Item=char> {
     optional(parser(amount))
         .map(|opt_amount| {
@@ -166,25 +197,135 @@ where I: Stream<Item=char> {
 }
 
 /// Parses a price entry
-fn price<I>(input: I) -> ParseResult<Price, I>
+fn price<I>(input: I) -> ParseResult<(Price, RenderOptions), I>
 where I: Stream<Item=char> {
     (
         char('P').skip(parser(whitespace)),
         parser(date).skip(parser(whitespace)),
+        optional(parser(time).skip(parser(whitespace))),
         parser(symbol).skip(parser(whitespace)),
         parser(amount)
     )
-        .map(|(_, date, symbol, amount)| Price::new(date, symbol, amount))
+        .map(|(_, date, time, symbol, (amount, render_opts))| {
+            (Price::new(date, symbol, amount).with_time(time), render_opts)
+        })
+        .parse_stream(input)
+}
+
+/// Parses a price entry, recording the line it is on.
+fn located_price<I>(input: I) -> ParseResult<(SourceLocation, Price, RenderOptions), I>
+where I: Stream<Item=char, Position=SourcePosition> {
+    (position(), parser(price))
+        .map(|(position, (price, render_opts)): (SourcePosition, _)| {
+            (SourceLocation::new(position.line as usize), price, render_opts)
+        })
         .parse_stream(input)
 }
 
 /// Parses a price DB file, which contains only price entries.
-fn price_db<I>(input: I) -> ParseResult<Vec<Price>, I>
+fn price_db<I>(input: I) -> ParseResult<Vec<(Price, RenderOptions)>, I>
 where I: Stream<Item=char> {
     sep_end_by(parser(price), parser(line_ending))
         .parse_stream(input)
 }
 
+/// Parses a price DB file, recording the line each price entry is on.
+fn located_price_db<I>(input: I) -> ParseResult<Vec<(SourceLocation, Price, RenderOptions)>, I>
+where I: Stream<Item=char, Position=SourcePosition> {
+    sep_end_by(parser(located_price), parser(line_ending))
+        .parse_stream(input)
+}
+
+/// Parses a commodity directive, which declares how a commodity should be rendered using a
+/// sample amount. e.g. commodity $1,000.00
+fn commodity_directive<I>(input: I) -> ParseResult<Commodity, I>
+where I: Stream<Item=char> {
+    (
+        string("commodity").skip(parser(whitespace)),
+        parser(amount).skip(optional(parser(whitespace))),
+        optional(parser(comment))
+    )
+        .map(|(_, (amount, render_opts), _)| Commodity::new(amount.symbol(), render_opts))
+        .parse_stream(input)
+}
+
+/// Parses an include directive, which pulls in another ledger file. Relative paths are resolved
+/// against the directory of the including file. e.g. include 2016/january.ledger
+fn include_directive<I>(input: I) -> ParseResult<String, I>
+where I: Stream<Item=char> {
+    (
+        string("include").skip(parser(whitespace)),
+        many1::<String, _>(satisfy(|c| c != ';' && c != '\r' && c != '\n')),
+        optional(parser(comment))
+    )
+        .map(|(_, path, _)| path.trim().to_string())
+        .parse_stream(input)
+}
+
+/// Parses a payee directive, which declares a payee for strict mode. e.g. payee Grocery Store
+fn payee_directive<I>(input: I) -> ParseResult<String, I>
+where I: Stream<Item=char> {
+    (
+        string("payee").skip(parser(whitespace)),
+        parser(payee),
+        optional(parser(comment))
+    )
+        .map(|(_, payee, _)| payee.trim().to_string())
+        .parse_stream(input)
+}
+
+/// Parses the ratio of a split, as new units to old units. e.g. 2:1
+fn split_ratio<I>(input: I) -> ParseResult<(d128, d128), I>
+where I: Stream<Item=char> {
+    (parser(quantity), char(':'), parser(quantity))
+        .and_then(|((new, _, _), _, (old, _, _))| {
+            if new > d128::zero() && old > d128::zero() {
+                Ok((new, old))
+            } else {
+                Err(Error::Expected("a positive split ratio".into()))
+            }
+        })
+        .parse_stream(input)
+}
+
+/// Parses a split directive, recording the line it is on. Every `old` units held before the
+/// date become `new` units. e.g. split 2016-03-01 "MUTF2351" 2:1
+fn split_directive<I>(input: I) -> ParseResult<ParseTree, I>
+where I: Stream<Item=char, Position=SourcePosition> {
+    (
+        position(),
+        string("split").skip(parser(whitespace)),
+        parser(date).skip(parser(whitespace)),
+        parser(symbol).skip(parser(whitespace)),
+        parser(split_ratio).skip(optional(parser(whitespace))),
+        optional(parser(comment))
+    )
+        .map(|(position, _, date, symbol, (new, old), _): (SourcePosition, _, _, _, _, _)| {
+            ParseTree::CorporateAction(SourceLocation::new(position.line as usize),
+                CorporateAction::split(date, symbol, new, old))
+        })
+        .parse_stream(input)
+}
+
+/// Parses a rename directive, recording the line it is on. The commodity is known by the new
+/// symbol from the date on. e.g. rename 2016-04-01 "MUTF2351" "MUTF9999"
+fn rename_directive<I>(input: I) -> ParseResult<ParseTree, I>
+where I: Stream<Item=char, Position=SourcePosition> {
+    (
+        position(),
+        string("rename").skip(parser(whitespace)),
+        parser(date).skip(parser(whitespace)),
+        parser(symbol).skip(parser(whitespace)),
+        parser(symbol).skip(optional(parser(whitespace))),
+        optional(parser(comment))
+    )
+        .map(|(position, _, date, from, to, _): (SourcePosition, _, _, _, _, _)| {
+            ParseTree::CorporateAction(SourceLocation::new(position.line as usize),
+                CorporateAction::rename(date, from, to))
+        })
+        .parse_stream(input)
+}
+
 /// Parses transaction status token. e.g. * (cleared) or ! (uncleared)
 fn status<I>(input: I) -> ParseResult<Status, I>
 where I: Stream<Item=char> {
@@ -244,9 +385,67 @@ where I: Stream<Item=char> {
 }
 
 /// Parses an account, made up of sub-accounts separated by colons.
-fn account<I>(input: I) -> ParseResult<Vec<String>,I>
+fn account<I>(input: I) -> ParseResult<AccountName,I>
 where I: Stream<Item=char> {
     sep_by1(parser(sub_account), char(':'))
+        .map(|sub_accounts: Vec<String>| AccountName::from_sub_accounts(&sub_accounts))
+        .parse_stream(input)
+}
+
+/// Reads the account type from an account directive's comment, given as a `type:` tag.
+/// e.g. ; type: Asset
+fn account_type_tag(comment: &str) -> Option<AccountType> {
+    comment.find("type:").and_then(|index| {
+        comment[index + "type:".len()..]
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .find(|word| !word.is_empty())
+            .and_then(|word| word.parse().ok())
+    })
+}
+
+/// Parses an account directive, which declares an account and optionally its type, recording
+/// the line it is on. e.g. account Actifs ; type: Asset
+fn account_directive<I>(input: I) -> ParseResult<ParseTree, I>
+where I: Stream<Item=char, Position=SourcePosition> {
+    (
+        position(),
+        string("account").skip(parser(whitespace)),
+        parser(account).skip(optional(parser(whitespace))),
+        optional(parser(comment))
+    )
+        .map(|(position, _, account, comment): (SourcePosition, _, _, _)| {
+            let account_type = comment.and_then(|c| account_type_tag(&c));
+            ParseTree::Account(SourceLocation::new(position.line as usize), account,
+                account_type)
+        })
+        .parse_stream(input)
+}
+
+/// Parses a posting cost, either per unit or in total. e.g. @ $5.42 or @@ $54.20
+fn cost<I>(input: I) -> ParseResult<(Cost, RenderOptions), I>
+where I: Stream<Item=char> {
+    (
+        char('@'),
+        optional(char('@')).skip(optional(parser(whitespace))),
+        parser(amount)
+    )
+        .map(|(_, total, (amount, render_opts))| {
+            let cost = match total {
+                Some(_) => Cost::Total(amount),
+                None => Cost::PerUnit(amount),
+            };
+            (cost, render_opts)
+        })
+        .parse_stream(input)
+}
+
+/// Parses a balance assertion, which gives the account's balance after the posting.
+/// e.g. = $1,000.00
+fn balance_assertion<I>(input: I) -> ParseResult<Amount, I>
+where I: Stream<Item=char> {
+    char('=').skip(optional(parser(whitespace)))
+        .with(parser(amount))
+        .map(|(amount, _)| amount)
         .parse_stream(input)
 }
 
@@ -256,10 +455,14 @@ where I: Stream<Item=char> {
     (
         parser(account).skip(optional(parser(whitespace))),
         parser(amount_or_inferred).skip(optional(parser(whitespace))),
+        optional(parser(cost).skip(optional(parser(whitespace)))),
+        optional(parser(balance_assertion).skip(optional(parser(whitespace)))),
         optional(parser(comment))
     )
-        .map(|(sub_accounts, (amount_source, opt_amount), opt_comment)|
-            RawPosting::new(sub_accounts, opt_amount, amount_source, opt_comment))
+        .map(|(account, (amount_source, opt_amount), opt_cost, opt_assertion, opt_comment)|
+            RawPosting::new(account, opt_amount, amount_source, opt_comment)
+                .with_cost(opt_cost)
+                .with_assertion(opt_assertion))
         .parse_stream(input)
 }
 
@@ -270,17 +473,34 @@ where I: Stream<Item=char> {
         .parse_stream(input)
 }
 
-/// Parses a whole transaction.
+/// Parses a whole transaction, recording the line its header starts on and the line of each
+/// posting.
 fn transaction<I>(input: I) -> ParseResult<ParseTree, I>
-where I: Stream<Item=char> {
+where I: Stream<Item=char, Position=SourcePosition> {
     (
+        position(),
         parser(header).skip(parser(line_ending)),
         many1(try(parser(comment_line).map(|_| None))
-                .or(try(parser(posting_line).map(|p| Some(p)))))
+                .or(try((position(), parser(posting_line)))
+                    .map(|(position, p): (SourcePosition, RawPosting)| {
+                        Some(p.with_line(position.line as usize))
+                    })))
     )
-        .map(|(header, postings) : (Header, Vec<Option<RawPosting>>)| {
+        .map(|(position, header, postings): (SourcePosition, Header, Vec<Option<RawPosting>>)| {
+            let location = SourceLocation::new(position.line as usize);
             let raw_postings = postings.into_iter().filter_map(|p| p).collect();
-            ParseTree::Transaction(header, raw_postings)
+            ParseTree::Transaction(location, header, raw_postings)
+        })
+        .parse_stream(input)
+}
+
+/// Parses a price entry in a ledger, recording the line it is on.
+fn ledger_price<I>(input: I) -> ParseResult<ParseTree, I>
+where I: Stream<Item=char, Position=SourcePosition> {
+    (position(), parser(price))
+        .map(|(position, (price, render_opts)): (SourcePosition, _)| {
+            ParseTree::Price(SourceLocation::new(position.line as usize),
+                price.with_origin(PriceOrigin::Ledger), render_opts)
         })
         .parse_stream(input)
 }
@@ -296,13 +516,19 @@ where I: Stream<Item=char> {
 
 /// Parses a complete ledger, extracting transactions and prices.
 fn ledger<I>(input: I) -> ParseResult<Vec<ParseTree>, I>
-where I: Stream<Item=char> {
+where I: Stream<Item=char, Position=SourcePosition> {
     // skip one or more comment or empty lines
     // parse transactions or prices separated, which may be separated bycomment or empty lines
     parser(skip_comment_or_empty_lines)
         .with(many(
             parser(transaction)
-                .or(parser(price).map(|p| ParseTree::Price(p)))
+                .or(parser(ledger_price))
+                .or(parser(commodity_directive).map(ParseTree::Commodity))
+                .or(parser(account_directive))
+                .or(parser(include_directive).map(ParseTree::Include))
+                .or(parser(payee_directive).map(ParseTree::Payee))
+                .or(parser(split_directive))
+                .or(parser(rename_directive))
                 .skip(parser(skip_comment_or_empty_lines))))
         .parse_stream(input)
 }
@@ -311,49 +537,211 @@ where I: Stream<Item=char> {
 
 // FILES
 
-pub fn parse_pricedb(file_path: &str) -> Vec<Price> {
-    let mut file = File::open(file_path).ok().expect("Failed to open file");
+/// Reasons a ledger or price DB file could not be read.
+#[derive(Debug)]
+pub enum ParseError {
+    /// The file could not be opened or read.
+    Io(String, io::Error),
+    /// The file's contents could not be parsed.
+    Syntax(String, String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::Io(ref file_path, ref err) => write!(f, "{}: {}", file_path, err),
+            ParseError::Syntax(ref file_path, ref message) =>
+                write!(f, "{}: {}", file_path, message),
+        }
+    }
+}
+
+/// Reads a file's contents into a string.
+fn read_file(file_path: &str) -> Result<String, ParseError> {
     let mut contents = String::new();
+    File::open(file_path)
+        .and_then(|mut file| file.read_to_string(&mut contents))
+        .map_err(|err| ParseError::Io(file_path.to_string(), err))?;
+    Ok(contents)
+}
 
-    file.read_to_string(&mut contents).ok().expect("Failed to read from file");
+/// Describes an error from the parser on a single line.
+fn syntax_error(err: ::combine::ParseError<State<&str>>) -> String {
+    format!("{}", err).trim_end().replace('\n', "; ")
+}
 
-    let result = parser(price_db).parse(&contents[..]);
+/// Describes input the parser stopped at before reaching the end of a file.
+fn unparsed_input(rest: State<&str>) -> String {
+    let line = rest.input.lines().next().unwrap_or("");
+    format!("line {}: could not parse {:?}", rest.position.line, line)
+}
 
-    match result {
-        Ok((prices, _)) => prices,
-        Err(err) => panic!("{}", err),
+/// Reads a price DB file, returning each price along with the render options it was written
+/// with.
+pub fn read_pricedb(file_path: &str) -> Result<Vec<(Price, RenderOptions)>, ParseError> {
+    let contents = read_file(file_path)?;
+    match parser(price_db).parse(State::new(&contents[..])) {
+        Ok((prices, ref rest)) if rest.input.trim().is_empty() => Ok(prices),
+        Ok((_, rest)) => Err(ParseError::Syntax(file_path.to_string(), unparsed_input(rest))),
+        Err(err) => Err(ParseError::Syntax(file_path.to_string(), syntax_error(err))),
     }
 }
 
-pub fn parse_ledger(file_path: &str) -> Vec<ParseTree> {
-    let mut file = File::open(file_path).ok().expect("Failed to open file");
-    let mut contents = String::new();
+/// Reads a price DB file, returning each price along with its location and the render options
+/// it was written with.
+pub fn read_located_pricedb(file_path: &str)
+-> Result<Vec<(SourceLocation, Price, RenderOptions)>, ParseError> {
+    let contents = read_file(file_path)?;
+    match parser(located_price_db).parse(State::new(&contents[..])) {
+        Ok((entries, ref rest)) if rest.input.trim().is_empty() => {
+            Ok(entries.into_iter()
+                .map(|(location, price, render_opts)| {
+                    (location.in_file(file_path), price, render_opts)
+                })
+                .collect())
+        },
+        Ok((_, rest)) => Err(ParseError::Syntax(file_path.to_string(), unparsed_input(rest))),
+        Err(err) => Err(ParseError::Syntax(file_path.to_string(), syntax_error(err))),
+    }
+}
+
+/// Parses the contents of a ledger. Transactions and prices are tagged with their line number.
+pub fn parse_ledger_str(contents: &str) -> Result<Vec<ParseTree>, String> {
+    match parser(ledger).parse(State::new(contents)) {
+        Ok((tree, ref rest)) if rest.input.trim().is_empty() => Ok(tree),
+        Ok((_, rest)) => Err(unparsed_input(rest)),
+        Err(err) => Err(syntax_error(err)),
+    }
+}
 
-    file.read_to_string(&mut contents).ok().expect("Failed to read from file");
+/// Reads a ledger file, without following its includes. Transactions and prices are tagged
+/// with their location in the file.
+pub fn read_ledger(file_path: &str) -> Result<Vec<ParseTree>, ParseError> {
+    let contents = read_file(file_path)?;
+    let tree = parse_ledger_str(&contents)
+        .map_err(|message| ParseError::Syntax(file_path.to_string(), message))?;
+
+    Ok(tree.into_iter()
+        .map(|item| {
+            match item {
+                ParseTree::Transaction(location, header, postings) =>
+                    ParseTree::Transaction(location.in_file(file_path), header, postings),
+                ParseTree::Price(location, price, render_opts) =>
+                    ParseTree::Price(location.in_file(file_path), price, render_opts),
+                ParseTree::Account(location, account, account_type) =>
+                    ParseTree::Account(location.in_file(file_path), account, account_type),
+                ParseTree::CorporateAction(location, action) =>
+                    ParseTree::CorporateAction(location.in_file(file_path), action),
+                _ => item,
+            }
+        })
+        .collect())
+}
 
-    let result = parser(ledger).parse(&contents[..]);
+pub fn parse_pricedb(file_path: &str, commodities: &mut CommodityRegistry) -> Vec<Price> {
+    match read_pricedb(file_path) {
+        Ok(prices) => {
+            prices.into_iter()
+                .map(|(price, render_opts)| {
+                    commodities.observe(Commodity::new(price.amount().symbol(), render_opts));
+                    price
+                })
+                .collect()
+        },
+        Err(err) => panic!("{}", err),
+    }
+}
 
+pub fn parse_ledger(file_path: &str, commodities: &mut CommodityRegistry) -> Vec<ParseTree> {
     // TODO: Should return result value rather than panic here
-    match result {
-        Ok((tree, _)) => tree,
+    match read_ledger(file_path) {
+        Ok(tree) => {
+            register_commodities(&tree, commodities);
+            tree
+        },
         Err(err) => panic!("{}", err),
     }
 }
 
+/// Populates the commodity registry from a parsed ledger. Commodity directives take precedence
+/// over the first use of a symbol, wherever the directive appears in the file.
+pub fn register_commodities(tree: &[ParseTree], commodities: &mut CommodityRegistry) {
+    for item in tree {
+        if let ParseTree::Commodity(ref commodity) = *item {
+            commodities.declare(commodity.clone());
+        }
+    }
+
+    for item in tree {
+        match *item {
+            ParseTree::Account(_, _, _) | ParseTree::Commodity(_) | ParseTree::Include(_)
+            | ParseTree::Payee(_) | ParseTree::CorporateAction(_, _) => {},
+            ParseTree::Price(_, ref price, render_opts) => {
+                commodities.observe(Commodity::new(price.amount().symbol(), render_opts));
+            },
+            ParseTree::Transaction(_, _, ref postings) => {
+                for posting in postings {
+                    if let (Some(amount), Some(render_opts)) =
+                        (posting.amount(), posting.render_options()) {
+                        commodities.observe(Commodity::new(amount.symbol(), *render_opts));
+                    }
+                    if let (Some(cost), Some(render_opts)) =
+                        (posting.cost(), posting.cost_render_options()) {
+                        let symbol =
+                            match *cost {
+                                Cost::PerUnit(amount) | Cost::Total(amount) => amount.symbol(),
+                            };
+                        commodities.observe(Commodity::new(symbol, *render_opts));
+                    }
+                }
+            },
+        }
+    }
+
+    // a renamed commodity renders like the one it replaces unless it's used or declared itself
+    for item in tree {
+        if let ParseTree::CorporateAction(_, ref action) = *item {
+            if let ActionKind::Rename(to) = action.kind() {
+                let render_opts = commodities.get(action.symbol()).map(|c| *c.render_options());
+                if let Some(render_opts) = render_opts {
+                    commodities.observe(Commodity::new(to, render_opts));
+                }
+            }
+        }
+    }
+}
+
+/// Records the account types declared by account directives in a parsed ledger.
+pub fn register_account_types(tree: &[ParseTree], account_types: &mut AccountTypes) {
+    for item in tree {
+        if let ParseTree::Account(_, account, Some(account_type)) = *item {
+            account_types.declare(account, account_type);
+        }
+    }
+}
+
 
 
 #[cfg(test)]
 mod tests {
-    use super::{account, amount, amount_quantity_then_symbol, amount_or_inferred,
-        amount_symbol_then_quantity, code, comment, comment_line, skip_comment_or_empty_lines,
-        date, header, ledger, line_ending, payee, posting, posting_line, price, price_db, quantity,
-        quoted_symbol, status, sub_account, symbol, transaction, two_digits, two_digits_to_u32,
-        unquoted_symbol, whitespace};
+    use super::{account, account_directive, account_type_tag, amount, amount_quantity_then_symbol,
+        amount_or_inferred, amount_symbol_then_quantity, balance_assertion, code, comment,
+        comment_line, cost, include_directive, payee_directive, read_ledger,
+        commodity_directive, skip_comment_or_empty_lines, date, header, ledger, line_ending, payee,
+        posting, posting_line, price, price_db, quantity, quoted_symbol, read_located_pricedb,
+        register_account_types, register_commodities, rename_directive, split_directive,
+        split_ratio, status, sub_account, symbol, time, transaction, two_digits,
+        two_digits_to_u32, unquoted_symbol, whitespace};
+    use chrono::NaiveTime;
     use chrono::offset::Local;
     use chrono::offset::TimeZone;
     use combine::{parser};
-    use combine::{Parser};
+    use combine::{Parser, State};
+    use core::account::{AccountName, AccountType, AccountTypes};
     use core::amount::*;
+    use core::commodity::*;
+    use core::corporate_action::CorporateAction;
+    use core::location::SourceLocation;
     use core::price::*;
     use core::symbol::*;
     use core::transaction::*;
@@ -422,12 +810,33 @@ mod tests {
         assert_eq!(result, Ok(Local.ymd(2015, 10, 17)));
     }
 
+    #[test]
+    fn time_with_seconds() {
+        let result = parser(time)
+            .parse("16:00:30").map(|x| x.0);
+        assert_eq!(result, Ok(NaiveTime::from_hms(16, 0, 30)));
+    }
+
+    #[test]
+    fn time_without_seconds() {
+        let result = parser(time)
+            .parse("09:30").map(|x| x.0);
+        assert_eq!(result, Ok(NaiveTime::from_hms(9, 30, 0)));
+    }
+
+    #[test]
+    fn time_out_of_range_is_error() {
+        let result = parser(time)
+            .parse("25:00").map(|x| x.0);
+        assert!(result.is_err());
+    }
+
     #[test]
     fn quantity_negative_no_fractional_part()
     {
         let result = parser(quantity)
             .parse("-1110").map(|x| x.0);
-        assert_eq!(result, Ok(d128!(-1110)));
+        assert_eq!(result, Ok((d128!(-1110), ThousandsSeparator::NoSeparator, 0)));
     }
 
     #[test]
@@ -435,7 +844,7 @@ mod tests {
     {
         let result = parser(quantity)
             .parse("2,314").map(|x| x.0);
-        assert_eq!(result, Ok(d128!(2314)));
+        assert_eq!(result, Ok((d128!(2314), ThousandsSeparator::Comma, 0)));
     }
 
     #[test]
@@ -443,7 +852,7 @@ mod tests {
     {
         let result = parser(quantity)
             .parse("-1,110.38").map(|x| x.0);
-        assert_eq!(result, Ok(d128!(-1110.38)));
+        assert_eq!(result, Ok((d128!(-1110.38), ThousandsSeparator::Comma, 2)));
     }
 
     #[test]
@@ -451,7 +860,7 @@ mod tests {
     {
         let result = parser(quantity)
             .parse("24521.793").map(|x| x.0);
-        assert_eq!(result, Ok(d128!(24521.793)));
+        assert_eq!(result, Ok((d128!(24521.793), ThousandsSeparator::NoSeparator, 3)));
     }
 
     #[test]
@@ -500,70 +909,77 @@ mod tests {
     fn amount_symbol_then_quantity_no_whitespace() {
         let result = parser(amount_symbol_then_quantity)
             .parse("$13,245.00").map(|x| x.0);
-        assert_eq!(result, Ok(Amount::new(
+        assert_eq!(result, Ok((Amount::new(
             d128!(13245.00),
-            Symbol::new("$", QuoteOption::Unquoted),
-            RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace))));
+            Symbol::new("$", QuoteOption::Unquoted)),
+            RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+                ThousandsSeparator::Comma, 2))));
     }
 
     #[test]
     fn amount_symbol_then_quantity_with_whitespace() {
         let result = parser(amount_symbol_then_quantity)
             .parse("$ 13,245.00").map(|x| x.0);
-        assert_eq!(result, Ok(Amount::new(
+        assert_eq!(result, Ok((Amount::new(
             d128!(13245.00),
-            Symbol::new("$", QuoteOption::Unquoted),
-            RenderOptions::new(SymbolPosition::Left, Spacing::Space))));
+            Symbol::new("$", QuoteOption::Unquoted)),
+            RenderOptions::new(SymbolPosition::Left, Spacing::Space,
+                ThousandsSeparator::Comma, 2))));
     }
 
     #[test]
     fn amount_quantity_then_symbol_no_whitespace() {
         let result = parser(amount_quantity_then_symbol)
             .parse("13,245.463AAPL").map(|x| x.0);
-        assert_eq!(result, Ok(Amount::new(
+        assert_eq!(result, Ok((Amount::new(
             d128!(13245.463),
-            Symbol::new("AAPL", QuoteOption::Unquoted),
-            RenderOptions::new(SymbolPosition::Right, Spacing::NoSpace))));
+            Symbol::new("AAPL", QuoteOption::Unquoted)),
+            RenderOptions::new(SymbolPosition::Right, Spacing::NoSpace,
+                ThousandsSeparator::Comma, 3))));
     }
 
     #[test]
     fn amount_quantity_then_symbol_with_whitespace() {
         let result = parser(amount_quantity_then_symbol)
             .parse("13,245.463 \"MUTF2351\"").map(|x| x.0);
-        assert_eq!(result, Ok(Amount::new(
+        assert_eq!(result, Ok((Amount::new(
             d128!(13245.463),
-            Symbol::new("MUTF2351", QuoteOption::Quoted),
-            RenderOptions::new(SymbolPosition::Right, Spacing::Space))));
+            Symbol::new("MUTF2351", QuoteOption::Quoted)),
+            RenderOptions::new(SymbolPosition::Right, Spacing::Space,
+                ThousandsSeparator::Comma, 3))));
     }
 
     #[test]
     fn amount_test_symbol_then_quantity() {
         let result = parser(amount)
             .parse("$13,245.46").map(|x| x.0);
-        assert_eq!(result, Ok(Amount::new(
+        assert_eq!(result, Ok((Amount::new(
             d128!(13245.46),
-            Symbol::new("$", QuoteOption::Unquoted),
-            RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace))));
+            Symbol::new("$", QuoteOption::Unquoted)),
+            RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+                ThousandsSeparator::Comma, 2))));
     }
 
     #[test]
     fn amount_test_quantity_then_symbol() {
         let result = parser(amount)
             .parse("13,245.463 \"MUTF2351\"").map(|x| x.0);
-        assert_eq!(result, Ok(Amount::new(
+        assert_eq!(result, Ok((Amount::new(
             d128!(13245.463),
-            Symbol::new("MUTF2351", QuoteOption::Quoted),
-            RenderOptions::new(SymbolPosition::Right, Spacing::Space))));
+            Symbol::new("MUTF2351", QuoteOption::Quoted)),
+            RenderOptions::new(SymbolPosition::Right, Spacing::Space,
+                ThousandsSeparator::Comma, 3))));
     }
 
     #[test]
     fn amount_or_inferred_amount_provided() {
         let result = parser(amount_or_inferred)
             .parse("$13,245.46").map(|x| x.0);
-        assert_eq!(result, Ok((AmountSource::Provided, Some(Amount::new(
+        assert_eq!(result, Ok((AmountSource::Provided, Some((Amount::new(
             d128!(13245.46),
-            Symbol::new("$", QuoteOption::Unquoted),
-            RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace))))));
+            Symbol::new("$", QuoteOption::Unquoted)),
+            RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+                ThousandsSeparator::Comma, 2))))));
     }
 
     #[test]
@@ -577,13 +993,27 @@ mod tests {
     fn price_test() {
         let result = parser(price)
             .parse("P 2015-10-25 \"MUTF2351\" $5.42").map(|x| x.0);
-        assert_eq!(result, Ok(Price::new(
+        assert_eq!(result, Ok((Price::new(
             Local.ymd(2015, 10, 25),
             Symbol::new("MUTF2351", QuoteOption::Quoted),
             Amount::new(
                 d128!(5.42),
-                Symbol::new("$", QuoteOption::Unquoted),
-                RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace)))));
+                Symbol::new("$", QuoteOption::Unquoted))),
+            RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+                ThousandsSeparator::NoSeparator, 2))));
+    }
+
+    #[test]
+    fn price_with_time() {
+        let result = parser(price)
+            .parse("P 2016-02-07 16:00:00 \"MUTF2351\" $5.41").map(|x| x.0 .0);
+        assert_eq!(result, Ok(Price::new(
+            Local.ymd(2016, 2, 7),
+            Symbol::new("MUTF2351", QuoteOption::Quoted),
+            Amount::new(
+                d128!(5.41),
+                Symbol::new("$", QuoteOption::Unquoted)))
+            .with_time(Some(NaiveTime::from_hms(16, 0, 0)))));
     }
 
     #[test]
@@ -598,13 +1028,14 @@ mod tests {
         let result = parser(price_db)
             .parse("P 2015-10-25 \"MUTF2351\" $5.42").map(|x| x.0);
         assert_eq!(result, Ok(vec![
-            Price::new(
+            (Price::new(
                 Local.ymd(2015, 10, 25),
                 Symbol::new("MUTF2351", QuoteOption::Quoted),
                 Amount::new(
                     d128!(5.42),
-                    Symbol::new("$", QuoteOption::Unquoted),
-                    RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace)))
+                    Symbol::new("$", QuoteOption::Unquoted))),
+                RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+                    ThousandsSeparator::NoSeparator, 2))
         ]));
     }
 
@@ -617,30 +1048,100 @@ mod tests {
                 P 2015-10-25 AAPL $313.38\n\
             ").map(|x| x.0);
         assert_eq!(result, Ok(vec![
-            Price::new(
+            (Price::new(
                 Local.ymd(2015, 10, 23),
                 Symbol::new("MUTF2351", QuoteOption::Quoted),
                 Amount::new(
                     d128!(5.42),
-                    Symbol::new("$", QuoteOption::Unquoted),
-                    RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace))),
-            Price::new(
+                    Symbol::new("$", QuoteOption::Unquoted))),
+                RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+                    ThousandsSeparator::NoSeparator, 2)),
+            (Price::new(
                 Local.ymd(2015, 10, 25),
                 Symbol::new("MUTF2351", QuoteOption::Quoted),
                 Amount::new(
                     d128!(5.98),
-                    Symbol::new("$", QuoteOption::Unquoted),
-                    RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace))),
-            Price::new(
+                    Symbol::new("$", QuoteOption::Unquoted))),
+                RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+                    ThousandsSeparator::NoSeparator, 2)),
+            (Price::new(
                 Local.ymd(2015, 10, 25),
                 Symbol::new("AAPL", QuoteOption::Unquoted),
                 Amount::new(
                     d128!(313.38),
-                    Symbol::new("$", QuoteOption::Unquoted),
-                    RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace)))
+                    Symbol::new("$", QuoteOption::Unquoted))),
+                RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+                    ThousandsSeparator::NoSeparator, 2))
         ]));
     }
 
+    #[test]
+    fn commodity_directive_test() {
+        let result = parser(commodity_directive)
+            .parse("commodity $1,000.00").map(|x| x.0);
+        assert_eq!(result, Ok(Commodity::new(
+            Symbol::new("$", QuoteOption::Unquoted),
+            RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+                ThousandsSeparator::Comma, 2))));
+    }
+
+    #[test]
+    fn commodity_directive_with_comment() {
+        let result = parser(commodity_directive)
+            .parse("commodity 1.000 \"MUTF2351\" ; mutual fund").map(|x| x.0);
+        assert_eq!(result, Ok(Commodity::new(
+            Symbol::new("MUTF2351", QuoteOption::Quoted),
+            RenderOptions::new(SymbolPosition::Right, Spacing::Space,
+                ThousandsSeparator::NoSeparator, 3))));
+    }
+
+    #[test]
+    fn include_directive_test() {
+        let result = parser(include_directive)
+            .parse("include 2016/june.ledger ; monthly file").map(|x| x.0);
+        assert_eq!(result, Ok("2016/june.ledger".to_string()));
+    }
+
+    #[test]
+    fn payee_directive_test() {
+        let result = parser(payee_directive)
+            .parse("payee Grocery Store ; weekly shop").map(|x| x.0);
+        assert_eq!(result, Ok("Grocery Store".to_string()));
+    }
+
+    #[test]
+    fn split_ratio_test() {
+        let result = parser(split_ratio)
+            .parse("1:10").map(|x| x.0);
+        assert_eq!(result, Ok((d128!(1), d128!(10))));
+    }
+
+    #[test]
+    fn split_ratio_zero_is_error() {
+        let result = parser(split_ratio)
+            .parse("2:0").map(|x| x.0);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn split_directive_test() {
+        let result = parser(split_directive)
+            .parse(State::new("split 2016-03-01 \"MUTF2351\" 2:1 ; fund split")).map(|x| x.0);
+        assert_eq!(result, Ok(ParseTree::CorporateAction(SourceLocation::new(1),
+            CorporateAction::split(Local.ymd(2016, 3, 1),
+                Symbol::new("MUTF2351", QuoteOption::Quoted), d128!(2), d128!(1)))));
+    }
+
+    #[test]
+    fn rename_directive_test() {
+        let result = parser(rename_directive)
+            .parse(State::new("rename 2016-04-01 \"MUTF2351\" AAPL")).map(|x| x.0);
+        assert_eq!(result, Ok(ParseTree::CorporateAction(SourceLocation::new(1),
+            CorporateAction::rename(Local.ymd(2016, 4, 1),
+                Symbol::new("MUTF2351", QuoteOption::Quoted),
+                Symbol::new("AAPL", QuoteOption::Unquoted)))));
+    }
+
     #[test]
     fn status_cleared() {
         let result = parser(status)
@@ -806,18 +1307,94 @@ mod tests {
     fn account_single_level() {
         let result = parser(account)
             .parse("Expenses").map(|x| x.0);
-        assert_eq!(result, Ok(vec!["Expenses".to_string()]));
+        assert_eq!(result, Ok(AccountName::new("Expenses")));
     }
 
     #[test]
     fn account_multiple_level() {
         let result = parser(account)
             .parse("Expenses:Food:Groceries").map(|x| x.0);
-        assert_eq!(result, Ok(vec![
-            "Expenses".to_string(),
-            "Food".to_string(),
-            "Groceries".to_string()
-        ]));
+        assert_eq!(result, Ok(AccountName::new("Expenses:Food:Groceries")));
+    }
+
+    #[test]
+    fn account_type_tag_test() {
+        assert_eq!(account_type_tag(" type: Asset"), Some(AccountType::Asset));
+        assert_eq!(account_type_tag(" type:liabilities, closed"), Some(AccountType::Liability));
+        assert_eq!(account_type_tag(" checking account"), None);
+    }
+
+    #[test]
+    fn account_directive_with_type() {
+        let result = parser(account_directive)
+            .parse(State::new("account Actifs ; type: Asset")).map(|x| x.0);
+        assert_eq!(result, Ok(ParseTree::Account(
+            SourceLocation::new(1),
+            AccountName::new("Actifs"),
+            Some(AccountType::Asset))));
+    }
+
+    #[test]
+    fn account_directive_without_type() {
+        let result = parser(account_directive)
+            .parse(State::new("account Expenses:Food")).map(|x| x.0);
+        assert_eq!(result, Ok(ParseTree::Account(SourceLocation::new(1),
+            AccountName::new("Expenses:Food"), None)));
+    }
+
+    #[test]
+    fn cost_per_unit() {
+        let result = parser(cost)
+            .parse("@ $5.42").map(|x| x.0);
+        assert_eq!(result, Ok((
+            Cost::PerUnit(Amount::new(d128!(5.42), Symbol::new("$", QuoteOption::Unquoted))),
+            RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+                ThousandsSeparator::NoSeparator, 2))));
+    }
+
+    #[test]
+    fn cost_total() {
+        let result = parser(cost)
+            .parse("@@ $54.20").map(|x| x.0 .0);
+        assert_eq!(result, Ok(
+            Cost::Total(Amount::new(d128!(54.20), Symbol::new("$", QuoteOption::Unquoted)))));
+    }
+
+    #[test]
+    fn posting_with_cost() {
+        let result = parser(posting)
+            .parse("Assets:Investments\t10 \"MUTF2351\" @ $5.42 ;comment").map(|x| x.0).unwrap();
+        assert_eq!(result.cost(), Some(&Cost::PerUnit(
+            Amount::new(d128!(5.42), Symbol::new("$", QuoteOption::Unquoted)))));
+        assert_eq!(result.comment(), Some("comment"));
+    }
+
+    #[test]
+    fn balance_assertion_test() {
+        let result = parser(balance_assertion)
+            .parse("= $1,000.00").map(|x| x.0);
+        assert_eq!(result,
+            Ok(Amount::new(d128!(1000.00), Symbol::new("$", QuoteOption::Unquoted))));
+    }
+
+    #[test]
+    fn posting_with_cost_and_assertion() {
+        let result = parser(posting)
+            .parse("Assets:Investments\t10 AAPL @ $98.83 = 20 AAPL").map(|x| x.0).unwrap();
+        assert_eq!(result.cost(), Some(&Cost::PerUnit(
+            Amount::new(d128!(98.83), Symbol::new("$", QuoteOption::Unquoted)))));
+        assert_eq!(result.assertion(),
+            Some(&Amount::new(d128!(20), Symbol::new("AAPL", QuoteOption::Unquoted))));
+    }
+
+    #[test]
+    fn posting_with_only_assertion() {
+        let result = parser(posting)
+            .parse("Assets:Savings    = $500.00").map(|x| x.0).unwrap();
+        assert_eq!(result.amount(), None);
+        assert_eq!(result.amount_source(), AmountSource::Inferred);
+        assert_eq!(result.assertion(),
+            Some(&Amount::new(d128!(500.00), Symbol::new("$", QuoteOption::Unquoted))));
     }
 
     #[test]
@@ -825,14 +1402,12 @@ mod tests {
         let result = parser(posting)
             .parse("Assets:Savings\t$45.00\t;comment").map(|x| x.0);
         assert_eq!(result, Ok(RawPosting::new(
-            vec![
-                "Assets".to_string(),
-                "Savings".to_string()
-            ],
-            Some(Amount::new(
+            AccountName::new("Assets:Savings"),
+            Some((Amount::new(
                 d128!(45.00),
-                Symbol::new("$".to_string(), QuoteOption::Unquoted),
-                RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace))),
+                Symbol::new("$".to_string(), QuoteOption::Unquoted)),
+                RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+                    ThousandsSeparator::NoSeparator, 2))),
             AmountSource::Provided,
             Some("comment".to_string()))));
     }
@@ -842,14 +1417,12 @@ mod tests {
         let result = parser(posting)
             .parse("Assets:Investments\t13.508 \"MUTF2351\"\t;comment").map(|x| x.0);
         assert_eq!(result, Ok(RawPosting::new(
-            vec![
-                "Assets".to_string(),
-                "Investments".to_string()
-            ],
-            Some(Amount::new(
+            AccountName::new("Assets:Investments"),
+            Some((Amount::new(
                 d128!(13.508),
-                Symbol::new("MUTF2351".to_string(), QuoteOption::Quoted),
-                RenderOptions::new(SymbolPosition::Right, Spacing::Space))),
+                Symbol::new("MUTF2351".to_string(), QuoteOption::Quoted)),
+                RenderOptions::new(SymbolPosition::Right, Spacing::Space,
+                    ThousandsSeparator::NoSeparator, 3))),
             AmountSource::Provided,
             Some("comment".to_string()))));
     }
@@ -859,14 +1432,12 @@ mod tests {
         let result = parser(posting)
             .parse("Assets:Savings\t$45.00").map(|x| x.0);
         assert_eq!(result, Ok(RawPosting::new(
-            vec![
-                "Assets".to_string(),
-                "Savings".to_string()
-            ],
-            Some(Amount::new(
+            AccountName::new("Assets:Savings"),
+            Some((Amount::new(
                 d128!(45.00),
-                Symbol::new("$".to_string(), QuoteOption::Unquoted),
-                RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace))),
+                Symbol::new("$".to_string(), QuoteOption::Unquoted)),
+                RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+                    ThousandsSeparator::NoSeparator, 2))),
             AmountSource::Provided,
             None)));
     }
@@ -876,10 +1447,7 @@ mod tests {
         let result = parser(posting)
             .parse("Assets:Savings\t;comment").map(|x| x.0);
         assert_eq!(result, Ok(RawPosting::new(
-            vec![
-                "Assets".to_string(),
-                "Savings".to_string()
-            ],
+            AccountName::new("Assets:Savings"),
             None,
             AmountSource::Inferred,
             Some("comment".to_string()))));
@@ -890,10 +1458,7 @@ mod tests {
         let result = parser(posting)
             .parse("Assets:Savings").map(|x| x.0);
         assert_eq!(result, Ok(RawPosting::new(
-            vec![
-                "Assets".to_string(),
-                "Savings".to_string()
-            ],
+            AccountName::new("Assets:Savings"),
             None,
             AmountSource::Inferred,
             None)));
@@ -904,10 +1469,7 @@ mod tests {
         let result = parser(posting_line)
             .parse("  Assets:Savings\r\n").map(|x| x.0);
         assert_eq!(result, Ok(RawPosting::new(
-            vec![
-                "Assets".to_string(),
-                "Savings".to_string()
-            ],
+            AccountName::new("Assets:Savings"),
             None,
             AmountSource::Inferred,
             None)));
@@ -918,10 +1480,7 @@ mod tests {
         let result = parser(posting_line)
             .parse("\tAssets:Savings\r\n").map(|x| x.0);
         assert_eq!(result, Ok(RawPosting::new(
-            vec![
-                "Assets".to_string(),
-                "Savings".to_string()
-            ],
+            AccountName::new("Assets:Savings"),
             None,
             AmountSource::Inferred,
             None)));
@@ -930,12 +1489,13 @@ mod tests {
     #[test]
     fn transaction_basic() {
         let result = parser(transaction)
-            .parse("\
+            .parse(State::new("\
                 2016-06-07 * Basic transaction ;comment\n\
                 \tExpenses:Groceries    $45.00\n\
                 \tLiabilities:Credit\n\
-            ").map(|x| x.0);
+            ")).map(|x| x.0);
         assert_eq!(result, Ok(ParseTree::Transaction(
+            SourceLocation::new(1),
             Header::new(
                 Local.ymd(2016, 6, 7),
                 Status::Cleared,
@@ -944,24 +1504,21 @@ mod tests {
                 Some("comment".to_string())),
             vec![
                 RawPosting::new(
-                    vec![
-                        "Expenses".to_string(),
-                        "Groceries".to_string(),
-                    ],
-                    Some(Amount::new(
+                    AccountName::new("Expenses:Groceries"),
+                    Some((Amount::new(
                         d128!(45.00),
-                        Symbol::new("$".to_string(), QuoteOption::Unquoted),
-                        RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace))),
+                        Symbol::new("$".to_string(), QuoteOption::Unquoted)),
+                        RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+                            ThousandsSeparator::NoSeparator, 2))),
                     AmountSource::Provided,
-                    None),
+                    None)
+                    .with_line(2),
                 RawPosting::new(
-                    vec![
-                        "Liabilities".to_string(),
-                        "Credit".to_string(),
-                    ],
+                    AccountName::new("Liabilities:Credit"),
                     None,
                     AmountSource::Inferred,
                     None)
+                    .with_line(3)
             ]
         )));
     }
@@ -969,13 +1526,14 @@ mod tests {
     #[test]
     fn transaction_with_comment() {
         let result = parser(transaction)
-            .parse("\
+            .parse(State::new("\
                 2016-06-07 * Basic transaction ;comment\n\
                 ; a comment in a transaction
                 \tExpenses:Groceries    $45.00\n\
                 \tLiabilities:Credit\n\
-            ").map(|x| x.0);
+            ")).map(|x| x.0);
         assert_eq!(result, Ok(ParseTree::Transaction(
+            SourceLocation::new(1),
             Header::new(
                 Local.ymd(2016, 6, 7),
                 Status::Cleared,
@@ -984,24 +1542,21 @@ mod tests {
                 Some("comment".to_string())),
             vec![
                 RawPosting::new(
-                    vec![
-                        "Expenses".to_string(),
-                        "Groceries".to_string(),
-                    ],
-                    Some(Amount::new(
+                    AccountName::new("Expenses:Groceries"),
+                    Some((Amount::new(
                         d128!(45.00),
-                        Symbol::new("$".to_string(), QuoteOption::Unquoted),
-                        RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace))),
+                        Symbol::new("$".to_string(), QuoteOption::Unquoted)),
+                        RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+                            ThousandsSeparator::NoSeparator, 2))),
                     AmountSource::Provided,
-                    None),
+                    None)
+                    .with_line(3),
                 RawPosting::new(
-                    vec![
-                        "Liabilities".to_string(),
-                        "Credit".to_string(),
-                    ],
+                    AccountName::new("Liabilities:Credit"),
                     None,
                     AmountSource::Inferred,
                     None)
+                    .with_line(4)
             ]
         )));
     }
@@ -1044,13 +1599,13 @@ mod tests {
     #[test]
     fn ledger_single_transaction() {
         let result = parser(ledger)
-            .parse("; Preamble\n\
+            .parse(State::new("; Preamble\n\
                 \n\
                 2016-06-07 * Basic transaction ;comment\n\
                 \tExpenses:Groceries    $45.00\n\
                 \tLiabilities:Credit\n\
                 \n\
-            ").map(|x| x.0);
+            ")).map(|x| x.0);
         println!("{:?}", result);
         assert_eq!(result.is_ok(), true);
         assert_eq!(result.unwrap().len(), 1);
@@ -1059,7 +1614,7 @@ mod tests {
     #[test]
     fn ledger_small_sample() {
         let result = parser(ledger)
-            .parse("; Preamble\n\
+            .parse(State::new("; Preamble\n\
                 \n\
                 2016-06-07 * Basic transaction ;comment\n\
                 \tExpenses:Groceries    $45.00\n\
@@ -1073,10 +1628,155 @@ mod tests {
                 2016-06-07 * Basic transaction ;comment\n\
                 \tExpenses:Groceries    $45.00\n\
                 \tLiabilities:Credit\n\
-            ").map(|x| x.0);
+            ")).map(|x| x.0);
         println!("{:?}", result);
         assert_eq!(result.is_ok(), true);
         assert_eq!(result.unwrap().len(), 4);
     }
 
-}
\ No newline at end of file
+
+    #[test]
+    fn ledger_records_transaction_lines() {
+        let tree = parser(ledger)
+            .parse(State::new("; Preamble\n\
+                \n\
+                2016-06-07 * Basic transaction\n\
+                \tExpenses:Groceries    $45.00\n\
+                \tLiabilities:Credit\n\
+                P 2016-06-07 AAPL $23.33\n\
+                2016-06-08 * Basic transaction\n\
+                \tExpenses:Groceries    $45.00\n\
+                \tLiabilities:Credit\n\
+            ")).map(|x| x.0).unwrap();
+        let lines: Vec<usize> = tree.iter()
+            .filter_map(|item| {
+                match *item {
+                    ParseTree::Transaction(location, _, _) => Some(location.line()),
+                    ParseTree::Price(location, _, _) => Some(location.line()),
+                    _ => None,
+                }
+            })
+            .collect();
+        assert_eq!(lines, vec![3, 6, 7]);
+    }
+
+    #[test]
+    fn ledger_with_commodity_directive() {
+        let result = parser(ledger)
+            .parse(State::new("commodity $1,000.00\n\
+                \n\
+                2016-06-07 * Basic transaction\n\
+                \tExpenses:Groceries    $45\n\
+                \tLiabilities:Credit\n\
+            ")).map(|x| x.0);
+        let tree = result.unwrap();
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree[0], ParseTree::Commodity(Commodity::new(
+            Symbol::new("$", QuoteOption::Unquoted),
+            RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+                ThousandsSeparator::Comma, 2))));
+    }
+
+    #[test]
+    fn ledger_with_corporate_actions() {
+        let result = parser(ledger)
+            .parse(State::new("split 2016-03-01 \"MUTF2351\" 2:1\n\
+                rename 2016-04-01 \"MUTF2351\" \"MUTF9999\"\n\
+            ")).map(|x| x.0);
+        let tree = result.unwrap();
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree[1], ParseTree::CorporateAction(SourceLocation::new(2),
+            CorporateAction::rename(Local.ymd(2016, 4, 1),
+                Symbol::new("MUTF2351", QuoteOption::Quoted),
+                Symbol::new("MUTF9999", QuoteOption::Quoted))));
+    }
+
+
+    // FILES
+
+    #[test]
+    fn read_ledger_keeps_includes() {
+        let tree = read_ledger("test/data/journal/main.ledger").unwrap();
+        assert!(tree.contains(&ParseTree::Include("2016/june.ledger".to_string())));
+    }
+
+    #[test]
+    fn read_ledger_unparsed_input_is_error() {
+        let result = read_ledger("test/data/invalid.ledger");
+        assert_eq!(format!("{}", result.unwrap_err()),
+            "test/data/invalid.ledger: line 4: could not parse \"~ Monthly\"");
+    }
+
+    #[test]
+    fn read_ledger_syntax_error() {
+        let result = read_ledger("test/data/invalid-transaction.ledger");
+        assert_eq!(format!("{}", result.unwrap_err()),
+            "test/data/invalid-transaction.ledger: Parse error at line: 1, column: 12; \
+            Unexpected `M`; Expected `*` or `!`");
+    }
+
+    #[test]
+    fn read_located_pricedb_records_lines() {
+        let entries = read_located_pricedb("test/data/multiple.pricedb").unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(format!("{}", entries[2].0), "test/data/multiple.pricedb:3");
+        assert_eq!(entries[2].1.amount().quantity(), d128!(7.10));
+    }
+
+    #[test]
+    fn register_commodities_directive_overrides_first_use() {
+        let tree = parser(ledger)
+            .parse(State::new("2016-06-07 * Basic transaction\n\
+                \tExpenses:Groceries    $ 45\n\
+                \tLiabilities:Credit\n\
+                \n\
+                commodity $1,000.00\n\
+                P 2016-06-07 \"MUTF2351\" $4.56\n\
+                2016-06-08 * Fund purchase\n\
+                \tAssets:Investments    1,000.0 \"MUTF2351\"\n\
+                \tAssets:Savings    $-4,560.00\n\
+            ")).map(|x| x.0).unwrap();
+        let mut commodities = CommodityRegistry::new();
+        register_commodities(&tree, &mut commodities);
+        assert_eq!(
+            commodities.format(d128!(1234.5), Symbol::new("$", QuoteOption::Unquoted)),
+            "$1,234.50");
+        assert_eq!(
+            commodities.format(d128!(1234.5), Symbol::new("MUTF2351", QuoteOption::Quoted)),
+            "1,234.5 \"MUTF2351\"");
+    }
+
+    #[test]
+    fn register_commodities_renamed_renders_like_original() {
+        let tree = parser(ledger)
+            .parse(State::new("2016-06-08 * Fund purchase\n\
+                \tAssets:Investments    1,000.0 \"MUTF2351\"\n\
+                \tAssets:Savings    $-4,560.00\n\
+                rename 2016-07-01 \"MUTF2351\" \"MUTF9999\"\n\
+            ")).map(|x| x.0).unwrap();
+        let mut commodities = CommodityRegistry::new();
+        register_commodities(&tree, &mut commodities);
+        assert_eq!(
+            commodities.format(d128!(1234.5), Symbol::new("MUTF9999", QuoteOption::Quoted)),
+            "1,234.5 \"MUTF9999\"");
+    }
+
+    #[test]
+    fn register_account_types_from_directives() {
+        let tree = parser(ledger)
+            .parse(State::new("account Actifs ; type: Asset\n\
+                account Passifs:Carte ; type: Liability\n\
+                \n\
+                2016-06-07 * Basic transaction\n\
+                \tPassifs:Carte    $-45\n\
+                \tActifs:Banque\n\
+            ")).map(|x| x.0).unwrap();
+        let mut account_types = AccountTypes::default();
+        register_account_types(&tree, &mut account_types);
+        assert_eq!(account_types.classify(AccountName::new("Actifs:Banque")),
+            Some(AccountType::Asset));
+        assert_eq!(account_types.classify(AccountName::new("Passifs:Carte")),
+            Some(AccountType::Liability));
+        assert_eq!(account_types.classify(AccountName::new("Passifs")), None);
+    }
+}
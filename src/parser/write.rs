@@ -0,0 +1,263 @@
+use chrono::{Date, NaiveTime};
+use chrono::offset::Local;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use core::amount::{Amount, RenderedAmount};
+use core::commodity::CommodityRegistry;
+use core::price::Price;
+use core::symbol::Symbol;
+
+
+/// Different prices recorded for the same symbol on the same date and time of day.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PriceConflict {
+    date: Date<Local>,
+    time: Option<NaiveTime>,
+    symbol: Symbol,
+    amounts: Vec<RenderedAmount>,
+}
+
+impl PriceConflict {
+    pub fn date(&self) -> Date<Local> {
+        self.date
+    }
+
+    pub fn time(&self) -> Option<NaiveTime> {
+        self.time
+    }
+
+    pub fn symbol(&self) -> Symbol {
+        self.symbol
+    }
+
+    /// The conflicting prices, in the order they were given.
+    pub fn amounts(&self) -> &[RenderedAmount] {
+        &self.amounts
+    }
+}
+
+impl fmt::Display for PriceConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let amounts: Vec<String> = self.amounts.iter().map(|a| a.to_string()).collect();
+        write!(f, "{}", self.date.format("%Y-%m-%d"))?;
+        if let Some(time) = self.time {
+            write!(f, " {}", time.format("%H:%M:%S"))?;
+        }
+        write!(f, " {}: conflicting prices {}", self.symbol, amounts.join(", "))
+    }
+}
+
+/// Writes prices to a price DB file, sorted by symbol and then date and time of day, with exact
+/// duplicates removed. Prices that conflict with another price for the same symbol, date and
+/// time are all written, in the order given, and returned so the caller can resolve them.
+///
+/// Amounts are formatted using the commodity registry, but never with less precision than they
+/// have, so the file parses back to the same prices. The file is written to a temporary file
+/// alongside it and then renamed into place, so readers never see a partly written file. If
+/// writing fails the temporary file is removed.
+pub fn write_pricedb(file_path: &str, prices: &[Price], commodities: &CommodityRegistry)
+-> io::Result<Vec<PriceConflict>> {
+    let (prices, conflicts) = sort_and_dedupe(prices, commodities);
+
+    let mut contents = String::new();
+    for price in &prices {
+        contents.push_str(&format_price(price, commodities));
+        contents.push('\n');
+    }
+
+    let path = Path::new(file_path);
+    let file_name = path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name));
+    let written = write_file(&temp_path, &contents).and_then(|_| fs::rename(&temp_path, path));
+    if let Err(error) = written {
+        let _ = fs::remove_file(&temp_path);
+        return Err(error);
+    }
+
+    Ok(conflicts)
+}
+
+/// Renders a price as a price DB entry, including its time of day if it has one.
+/// e.g. P 2016-02-07 "MUTF2351" $5.41
+pub fn format_price(price: &Price, commodities: &CommodityRegistry) -> String {
+    format!("P {} {} {}", price.format_timestamp(), price.symbol(),
+        commodities.format_exact(price.amount().quantity(), price.amount().symbol()))
+}
+
+
+
+// HELPERS
+
+/// Writes the contents to a new file and waits for them to reach the disk.
+fn write_file(path: &Path, contents: &str) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()
+}
+
+/// Sorts prices by symbol then date and time, keeping prices on the same symbol, date and time
+/// in the order given, and drops exact duplicates. Returns the remaining prices and any
+/// conflicts.
+fn sort_and_dedupe(prices: &[Price], commodities: &CommodityRegistry)
+-> (Vec<Price>, Vec<PriceConflict>) {
+    let mut sorted = prices.to_vec();
+    sorted.sort_by_key(|price| (price.symbol(), price.sort_key()));
+
+    let mut deduped = Vec::new();
+    let mut conflicts = Vec::new();
+    let mut start = 0;
+    while start < sorted.len() {
+        let key = (sorted[start].symbol(), sorted[start].date(), sorted[start].time());
+        let end = start + sorted[start..].iter()
+            .take_while(|price| (price.symbol(), price.date(), price.time()) == key)
+            .count();
+
+        let mut amounts: Vec<Amount> = Vec::new();
+        for price in &sorted[start..end] {
+            if !amounts.contains(price.amount()) {
+                amounts.push(*price.amount());
+                deduped.push(price.clone());
+            }
+        }
+        if amounts.len() > 1 {
+            let amounts = amounts.into_iter().map(|a| RenderedAmount::new(a, commodities));
+            conflicts.push(PriceConflict {
+                date: key.1,
+                time: key.2,
+                symbol: key.0,
+                amounts: amounts.collect(),
+            });
+        }
+        start = end;
+    }
+    (deduped, conflicts)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::offset::TimeZone;
+    use decimal::d128;
+    use std::env;
+    use core::commodity::*;
+    use core::symbol::*;
+    use parser::parse::parse_pricedb;
+
+    fn price(day: u32, symbol: &str, quantity: d128) -> Price {
+        Price::new(Local.ymd(2016, 2, day), Symbol::new(symbol, QuoteOption::Quoted),
+            Amount::new(quantity, Symbol::new("$", QuoteOption::Unquoted)))
+    }
+
+    fn temp_path(name: &str) -> String {
+        env::temp_dir()
+            .join(format!("wealth_pulse_{}_{}.pricedb", ::std::process::id(), name))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn dollars() -> CommodityRegistry {
+        let mut commodities = CommodityRegistry::new();
+        commodities.observe(Commodity::new(
+            Symbol::new("$", QuoteOption::Unquoted),
+            RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace, ThousandsSeparator::Comma,
+                2)));
+        commodities
+    }
+
+    #[test]
+    fn write_pricedb_sorts_and_dedupes() {
+        let file_path = temp_path("sorts");
+        let prices = vec![
+            price(8, "MUTF2351", d128!(5.61)),
+            price(7, "MUTF2351", d128!(5.41)),
+            price(7, "AAPL", d128!(1234.5)),
+            price(8, "MUTF2351", d128!(5.61)),
+        ];
+        let conflicts = write_pricedb(&file_path, &prices, &dollars()).unwrap();
+        assert_eq!(conflicts, vec![]);
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "\
+            P 2016-02-07 \"AAPL\" $1,234.50\n\
+            P 2016-02-07 \"MUTF2351\" $5.41\n\
+            P 2016-02-08 \"MUTF2351\" $5.61\n");
+        fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn write_pricedb_reports_conflicts() {
+        let file_path = temp_path("conflicts");
+        let prices = vec![
+            price(7, "MUTF2351", d128!(5.41)),
+            price(7, "MUTF2351", d128!(5.42)),
+        ];
+        let conflicts = write_pricedb(&file_path, &prices, &dollars()).unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(format!("{}", conflicts[0]),
+            "2016-02-07 \"MUTF2351\": conflicting prices $5.41, $5.42");
+        fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn write_pricedb_keeps_times_of_day() {
+        let file_path = temp_path("times");
+        let prices = vec![
+            price(7, "MUTF2351", d128!(5.45)).with_time(Some(NaiveTime::from_hms(16, 0, 0))),
+            price(7, "MUTF2351", d128!(5.42)).with_time(Some(NaiveTime::from_hms(9, 30, 0))),
+            price(7, "MUTF2351", d128!(5.41)),
+        ];
+        let conflicts = write_pricedb(&file_path, &prices, &dollars()).unwrap();
+        assert_eq!(conflicts, vec![]);
+        let contents = fs::read_to_string(&file_path).unwrap();
+        assert_eq!(contents, "\
+            P 2016-02-07 09:30:00 \"MUTF2351\" $5.42\n\
+            P 2016-02-07 16:00:00 \"MUTF2351\" $5.45\n\
+            P 2016-02-07 \"MUTF2351\" $5.41\n");
+        assert_eq!(parse_pricedb(&file_path, &mut CommodityRegistry::new())[1], prices[0]);
+        fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn write_pricedb_into_missing_directory_is_error() {
+        let directory = temp_path("missing");
+        let file_path = Path::new(&directory).join("prices.pricedb");
+        let result = write_pricedb(&file_path.to_string_lossy(), &[price(7, "AAPL", d128!(1))],
+            &dollars());
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert!(!Path::new(&directory).exists());
+    }
+
+    #[test]
+    fn write_pricedb_removes_temp_file_on_error() {
+        let directory = temp_path("directory");
+        fs::create_dir_all(Path::new(&directory).join("nested")).unwrap();
+        let result = write_pricedb(&directory, &[price(7, "AAPL", d128!(1))], &dollars());
+        let temp_file = Path::new(&directory).with_file_name(
+            format!(".{}.tmp", Path::new(&directory).file_name().unwrap().to_string_lossy()));
+        assert!(result.is_err());
+        assert!(!temp_file.exists());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn format_price_keeps_extra_precision() {
+        let price = price(7, "MUTF2351", d128!(5.4123));
+        assert_eq!(format_price(&price, &dollars()), "P 2016-02-07 \"MUTF2351\" $5.4123");
+    }
+
+    #[test]
+    fn write_pricedb_round_trips() {
+        let mut commodities = CommodityRegistry::new();
+        let prices = parse_pricedb("test/data/multiple.pricedb", &mut commodities);
+        let file_path = temp_path("round_trip");
+        write_pricedb(&file_path, &prices, &commodities).unwrap();
+        assert_eq!(fs::read_to_string(&file_path).unwrap(),
+            fs::read_to_string("test/data/multiple.pricedb").unwrap());
+        let result = parse_pricedb(&file_path, &mut CommodityRegistry::new());
+        assert_eq!(result, prices);
+        fs::remove_file(&file_path).unwrap();
+    }
+}
@@ -0,0 +1,2 @@
+pub mod staleness;
+pub mod stats;
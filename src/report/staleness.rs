@@ -0,0 +1,211 @@
+use chrono::Date;
+use chrono::offset::Local;
+use std::fmt;
+use core::symbol::Symbol;
+use validate::journal::Journal;
+
+
+/// A stretch of a price series with no prices, between two priced dates.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PriceGap {
+    start: Date<Local>,
+    end: Date<Local>,
+}
+
+impl PriceGap {
+    /// The date of the last price before the gap.
+    pub fn start(&self) -> Date<Local> {
+        self.start
+    }
+
+    /// The date of the first price after the gap.
+    pub fn end(&self) -> Date<Local> {
+        self.end
+    }
+
+    pub fn days(&self) -> i64 {
+        self.end.signed_duration_since(self.start).num_days()
+    }
+}
+
+/// How current the prices of one held commodity are.
+#[derive(Clone, PartialEq, Debug)]
+pub struct CommodityStaleness {
+    symbol: Symbol,
+    last_price: Option<Date<Local>>,
+    days_old: Option<i64>,
+    gaps: Vec<PriceGap>,
+}
+
+impl CommodityStaleness {
+    pub fn symbol(&self) -> Symbol {
+        self.symbol
+    }
+
+    /// The date of the latest price on or before the report date, if there is one.
+    pub fn last_price(&self) -> Option<Date<Local>> {
+        self.last_price
+    }
+
+    /// How many days before the report date the latest price is.
+    pub fn days_old(&self) -> Option<i64> {
+        self.days_old
+    }
+
+    /// Gaps between consecutive prices longer than the report's threshold, oldest first.
+    pub fn gaps(&self) -> &[PriceGap] {
+        &self.gaps
+    }
+}
+
+/// The freshness of prices, in a target commodity, for every commodity held on a date.
+#[derive(Clone, PartialEq, Debug)]
+pub struct StalenessReport {
+    target: Symbol,
+    date: Date<Local>,
+    commodities: Vec<CommodityStaleness>,
+}
+
+impl StalenessReport {
+    /// Builds the report for the commodities held at the end of `date`, using every price on or
+    /// before it. Gaps longer than `gap_days` days are listed.
+    pub fn new(journal: &Journal, target: Symbol, date: Date<Local>, gap_days: i64)
+    -> StalenessReport {
+        let history = journal.adjusted_price_history(date);
+        let commodities = journal.holdings(date).amounts().iter()
+            .map(|amount| amount.symbol())
+            .filter(|symbol| *symbol != target)
+            .map(|symbol| {
+                let series = history.series(symbol, target);
+                let known = &series[..series.partition_point(|p| p.date() <= date)];
+                let last_price = known.last().map(|p| p.date());
+                let gaps = known.windows(2)
+                    .map(|pair| PriceGap { start: pair[0].date(), end: pair[1].date() })
+                    .filter(|gap| gap.days() > gap_days)
+                    .collect();
+                CommodityStaleness {
+                    symbol: symbol,
+                    last_price: last_price,
+                    days_old: last_price.map(|last| date.signed_duration_since(last).num_days()),
+                    gaps: gaps,
+                }
+            })
+            .collect();
+        StalenessReport {
+            target: target,
+            date: date,
+            commodities: commodities,
+        }
+    }
+
+    pub fn target(&self) -> Symbol {
+        self.target
+    }
+
+    pub fn date(&self) -> Date<Local> {
+        self.date
+    }
+
+    /// Each held commodity, ordered by symbol.
+    pub fn commodities(&self) -> &[CommodityStaleness] {
+        &self.commodities
+    }
+
+    /// The held commodities with no price at all in the target commodity.
+    pub fn unpriced(&self) -> Vec<Symbol> {
+        self.commodities.iter()
+            .filter(|c| c.last_price.is_none())
+            .map(|c| c.symbol)
+            .collect()
+    }
+}
+
+impl fmt::Display for StalenessReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let format = |date: Date<Local>| date.format("%Y-%m-%d").to_string();
+        let width = self.commodities.iter()
+            .map(|c| c.symbol.to_string().len())
+            .max()
+            .unwrap_or(0);
+        write!(f, "Prices in {} as of {}:", self.target, format(self.date))?;
+        for commodity in &self.commodities {
+            let symbol = commodity.symbol.to_string();
+            match (commodity.last_price, commodity.days_old) {
+                (Some(last_price), Some(days_old)) =>
+                    write!(f, "\n  {:width$}  last price {}, {} days old", symbol,
+                        format(last_price), days_old, width = width)?,
+                _ =>
+                    write!(f, "\n  {:width$}  no price in {}", symbol, self.target,
+                        width = width)?,
+            }
+            for gap in &commodity.gaps {
+                write!(f, "\n  {:width$}  gap of {} days from {} to {}", "", gap.days(),
+                    format(gap.start), format(gap.end), width = width)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::offset::TimeZone;
+    use core::symbol::*;
+    use parser::parse::parse_ledger_str;
+
+    fn journal() -> Journal {
+        let tree = parse_ledger_str("\
+            2016-01-04 * Buy\n\
+            \tAssets:Investments    10 \"MUTF2351\"\n\
+            \tAssets:Investments    2 AAPL\n\
+            \tAssets:Investments    1 GOOG\n\
+            \tEquity:Opening    -10 \"MUTF2351\"\n\
+            \tEquity:Opening    -2 AAPL\n\
+            \tEquity:Opening    -1 GOOG\n\
+            2016-01-05 * Sell\n\
+            \tAssets:Investments    -1 GOOG\n\
+            \tEquity:Opening    1 GOOG\n\
+            P 2016-01-04 \"MUTF2351\" $5.00\n\
+            P 2016-01-05 \"MUTF2351\" $5.10\n\
+            P 2016-01-25 \"MUTF2351\" $5.20\n\
+            P 2016-02-01 \"MUTF2351\" $5.25\n\
+            P 2016-02-09 \"MUTF2351\" $5.30\n\
+            P 2016-01-05 GOOG $700.00\n\
+            P 2016-01-05 AAPL 90.00 CAD\n").unwrap();
+        Journal::from_parse_tree(tree).unwrap()
+    }
+
+    fn report() -> StalenessReport {
+        StalenessReport::new(&journal(), Symbol::new("$", QuoteOption::Unquoted),
+            Local.ymd(2016, 2, 5), 7)
+    }
+
+    #[test]
+    fn staleness_report_for_held_commodities() {
+        let report = report();
+        let symbols: Vec<&str> = report.commodities().iter().map(|c| c.symbol().name()).collect();
+        assert_eq!(symbols, vec!["AAPL", "MUTF2351"]);
+        let fund = &report.commodities()[1];
+        assert_eq!(fund.last_price(), Some(Local.ymd(2016, 2, 1)));
+        assert_eq!(fund.days_old(), Some(4));
+        assert_eq!(fund.gaps().len(), 1);
+        assert_eq!(fund.gaps()[0].start(), Local.ymd(2016, 1, 5));
+        assert_eq!(fund.gaps()[0].days(), 20);
+    }
+
+    #[test]
+    fn staleness_report_lists_unpriced_commodities() {
+        assert_eq!(report().unpriced(), vec![Symbol::new("AAPL", QuoteOption::Unquoted)]);
+    }
+
+    #[test]
+    fn staleness_report_fmt() {
+        assert_eq!(format!("{}", report()), "\
+            Prices in $ as of 2016-02-05:\n\
+            \x20 AAPL        no price in $\n\
+            \x20 \"MUTF2351\"  last price 2016-02-01, 4 days old\n\
+            \x20             gap of 20 days from 2016-01-05 to 2016-01-25");
+    }
+}
@@ -0,0 +1,178 @@
+use chrono::{Date, DateTime, Datelike};
+use chrono::offset::Local;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use core::transaction::AmountSource;
+use validate::journal::Journal;
+
+
+/// Summary figures for a loaded journal.
+#[derive(Debug)]
+pub struct JournalStats {
+    last_modified: Option<DateTime<Local>>,
+    source_files: usize,
+    transactions: usize,
+    postings: usize,
+    inferred_postings: usize,
+    prices: usize,
+    first_date: Option<Date<Local>>,
+    last_date: Option<Date<Local>>,
+    accounts: usize,
+    payees: usize,
+    commodities: usize,
+    transactions_per_year: BTreeMap<i32, usize>,
+}
+
+impl JournalStats {
+    /// Collects statistics for a journal. The last modified time is the latest modification
+    /// time of the journal's source files; files that can no longer be read are skipped.
+    pub fn new(journal: &Journal) -> JournalStats {
+        let last_modified = journal.source_files().iter()
+            .filter_map(|file_path| fs::metadata(file_path).and_then(|m| m.modified()).ok())
+            .max()
+            .map(DateTime::<Local>::from);
+
+        let mut transactions_per_year = BTreeMap::new();
+        let mut payees = HashSet::new();
+        for transaction in journal.transactions() {
+            *transactions_per_year.entry(transaction.header().date().year()).or_insert(0) += 1;
+            payees.insert(transaction.header().payee().trim());
+        }
+
+        let postings = journal.postings();
+        let accounts: HashSet<_> = postings.iter().map(|p| p.posting().account()).collect();
+        let inferred_postings = postings.iter()
+            .filter(|p| p.posting().amount_source() == AmountSource::Inferred)
+            .count();
+
+        JournalStats {
+            last_modified: last_modified,
+            source_files: journal.source_files().len(),
+            transactions: journal.transactions().len(),
+            postings: postings.len(),
+            inferred_postings: inferred_postings,
+            prices: journal.prices().len(),
+            first_date: postings.first().map(|p| p.date()),
+            last_date: postings.last().map(|p| p.date()),
+            accounts: accounts.len(),
+            payees: payees.len(),
+            commodities: journal.commodities().len(),
+            transactions_per_year: transactions_per_year,
+        }
+    }
+
+    pub fn last_modified(&self) -> Option<DateTime<Local>> {
+        self.last_modified
+    }
+
+    pub fn source_files(&self) -> usize {
+        self.source_files
+    }
+
+    pub fn transactions(&self) -> usize {
+        self.transactions
+    }
+
+    pub fn postings(&self) -> usize {
+        self.postings
+    }
+
+    /// The number of postings whose amounts were inferred by balancing their transaction.
+    pub fn inferred_postings(&self) -> usize {
+        self.inferred_postings
+    }
+
+    pub fn prices(&self) -> usize {
+        self.prices
+    }
+
+    pub fn first_date(&self) -> Option<Date<Local>> {
+        self.first_date
+    }
+
+    pub fn last_date(&self) -> Option<Date<Local>> {
+        self.last_date
+    }
+
+    /// The number of distinct accounts that have postings.
+    pub fn accounts(&self) -> usize {
+        self.accounts
+    }
+
+    pub fn payees(&self) -> usize {
+        self.payees
+    }
+
+    pub fn commodities(&self) -> usize {
+        self.commodities
+    }
+
+    pub fn transactions_per_year(&self) -> &BTreeMap<i32, usize> {
+        &self.transactions_per_year
+    }
+}
+
+impl fmt::Display for JournalStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let date = |date: Option<Date<Local>>| {
+            date.map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_else(|| "-".to_string())
+        };
+        let last_modified = self.last_modified
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| "-".to_string());
+
+        writeln!(f, "Source files:      {}", self.source_files)?;
+        writeln!(f, "Last modified:     {}", last_modified)?;
+        writeln!(f, "Transactions:      {}", self.transactions)?;
+        writeln!(f, "Postings:          {} ({} inferred)", self.postings, self.inferred_postings)?;
+        writeln!(f, "Prices:            {}", self.prices)?;
+        writeln!(f, "First transaction: {}", date(self.first_date))?;
+        writeln!(f, "Last transaction:  {}", date(self.last_date))?;
+        writeln!(f, "Accounts:          {}", self.accounts)?;
+        writeln!(f, "Payees:            {}", self.payees)?;
+        writeln!(f, "Commodities:       {}", self.commodities)?;
+        write!(f, "Transactions per year:")?;
+        for (year, count) in &self.transactions_per_year {
+            write!(f, "\n  {}              {}", year, count)?;
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::offset::TimeZone;
+
+    fn stats() -> JournalStats {
+        let journal = Journal::load("test/data/journal/main.ledger").unwrap();
+        JournalStats::new(&journal)
+    }
+
+    #[test]
+    fn journal_stats_counts() {
+        let stats = stats();
+        assert_eq!(stats.source_files(), 2);
+        assert!(stats.last_modified().is_some());
+        assert_eq!(stats.transactions(), 3);
+        assert_eq!(stats.postings(), 6);
+        assert_eq!(stats.inferred_postings(), 2);
+        assert_eq!(stats.prices(), 2);
+        assert_eq!(stats.first_date(), Some(Local.ymd(2016, 6, 1)));
+        assert_eq!(stats.last_date(), Some(Local.ymd(2016, 6, 8)));
+        assert_eq!(stats.accounts(), 3);
+        assert_eq!(stats.payees(), 2);
+        assert_eq!(stats.commodities(), 1);
+        assert_eq!(stats.transactions_per_year().get(&2016), Some(&3));
+    }
+
+    #[test]
+    fn journal_stats_fmt() {
+        let result = format!("{}", stats());
+        assert!(result.contains("Postings:          6 (2 inferred)\n"));
+        assert!(result.contains("First transaction: 2016-06-01\n"));
+        assert!(result.ends_with("Transactions per year:\n  2016              3"));
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::fmt;
+use core::account::AccountName;
+use core::amount::{round, Amount, RenderedAmount, RoundingMode};
+use core::balance::Balance;
+use core::commodity::CommodityRegistry;
+use core::location::SourceLocation;
+use core::transaction::Transaction;
+
+
+/// A balance assertion that didn't hold: the account's balance in the asserted commodity was
+/// not what the ledger said it would be.
+#[derive(PartialEq, Debug)]
+pub struct AssertionError {
+    location: SourceLocation,
+    account: AccountName,
+    expected: RenderedAmount,
+    actual: RenderedAmount,
+}
+
+impl AssertionError {
+    pub fn location(&self) -> SourceLocation {
+        self.location
+    }
+
+    pub fn account(&self) -> AccountName {
+        self.account
+    }
+
+    pub fn expected(&self) -> &Amount {
+        self.expected.amount()
+    }
+
+    pub fn actual(&self) -> &Amount {
+        self.actual.amount()
+    }
+}
+
+impl fmt::Display for AssertionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: balance assertion failed for {}: expected {}, but the balance is {}",
+            self.location, self.account, self.expected, self.actual)
+    }
+}
+
+/// Checks every balance assertion, walking the transactions in date order and keeping a running
+/// balance for each account. Transactions on the same date are taken in the order given. An
+/// assertion holds if it matches the balance once rounded to the commodity's display precision.
+/// Failures are reported at the line of the posting carrying the assertion.
+pub fn check_assertions(transactions: &[Transaction], commodities: &CommodityRegistry)
+-> Vec<AssertionError> {
+    let mut ordered: Vec<&Transaction> = transactions.iter().collect();
+    ordered.sort_by_key(|t| t.header().date());
+
+    let mut balances: HashMap<AccountName, Balance> = HashMap::new();
+    let mut errors = Vec::new();
+    for transaction in ordered {
+        for posting in transaction.postings() {
+            let balance = balances.entry(posting.account()).or_default();
+            balance.add_amount(posting.amount());
+
+            if let Some(expected) = posting.assertion() {
+                let actual = Amount::new(balance.quantity(expected.symbol()), expected.symbol());
+                let difference = actual.quantity() - expected.quantity();
+                let holds =
+                    match commodities.precision(expected.symbol()) {
+                        Some(precision) =>
+                            round(difference, precision, RoundingMode::HalfEven).is_zero(),
+                        None => difference.is_zero(),
+                    };
+                if !holds {
+                    errors.push(AssertionError {
+                        location: transaction.posting_location(posting),
+                        account: posting.account(),
+                        expected: RenderedAmount::new(*expected, commodities),
+                        actual: RenderedAmount::new(actual, commodities),
+                    });
+                }
+            }
+        }
+    }
+    errors
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::offset::Local;
+    use chrono::offset::TimeZone;
+    use decimal::d128;
+    use core::commodity::*;
+    use core::symbol::*;
+    use core::transaction::*;
+
+    fn dollars(quantity: d128) -> Amount {
+        Amount::new(quantity, Symbol::new("$", QuoteOption::Unquoted))
+    }
+
+    fn transaction(line: usize, day: u32, quantity: d128, assertion: Option<d128>)
+    -> Transaction {
+        let header = Header::new(Local.ymd(2016, 6, day), Status::Cleared, None,
+            "Transfer".to_string(), None);
+        Transaction::new(SourceLocation::new(line), header, vec![
+            Posting::new(AccountName::new("Assets:Checking"), dollars(quantity),
+                AmountSource::Provided, None)
+                .with_assertion(assertion.map(dollars))
+                .with_line(Some(line + 1)),
+            Posting::new(AccountName::new("Equity:Transfers"), dollars(-quantity),
+                AmountSource::Inferred, None),
+        ])
+    }
+
+    #[test]
+    fn check_assertions_uses_date_order() {
+        let transactions = vec![
+            transaction(1, 9, d128!(10.00), Some(d128!(110.00))),
+            transaction(5, 8, d128!(100.00), Some(d128!(100.00))),
+        ];
+        let errors = check_assertions(&transactions, &CommodityRegistry::new());
+        assert_eq!(errors, vec![]);
+    }
+
+    #[test]
+    fn check_assertions_reports_failures() {
+        let transactions = vec![
+            transaction(1, 8, d128!(100.00), Some(d128!(100.00))),
+            transaction(5, 9, d128!(10.00), Some(d128!(120.00))),
+        ];
+        let mut commodities = CommodityRegistry::new();
+        commodities.declare(Commodity::new(
+            Symbol::new("$", QuoteOption::Unquoted),
+            RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace, ThousandsSeparator::Comma,
+                2)));
+        let errors = check_assertions(&transactions, &commodities);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(*errors[0].actual(), dollars(d128!(110.00)));
+        assert_eq!(format!("{}", errors[0]), "line 6: balance assertion failed for \
+            Assets:Checking: expected $120.00, but the balance is $110.00");
+    }
+}
@@ -0,0 +1,567 @@
+use decimal::d128;
+use std::collections::HashMap;
+use std::fmt;
+use core::account::AccountName;
+use core::amount::{round, Amount, RenderedAmount, RoundingMode};
+use core::balance::Balance;
+use core::commodity::CommodityRegistry;
+use core::location::SourceLocation;
+use core::price::{Price, PriceOrigin};
+use core::symbol::Symbol;
+use core::transaction::*;
+use parser::ast::*;
+
+
+/// Reasons a parsed transaction cannot be turned into a balanced transaction.
+#[derive(PartialEq, Debug)]
+pub enum BalanceError {
+    /// More than one posting was written without an amount, so the missing amounts can't be
+    /// worked out.
+    MultipleInferredPostings(SourceLocation, Header, Vec<AccountName>),
+    /// No posting was written with an amount.
+    NoAmounts(SourceLocation, Header),
+    /// The postings don't sum to zero. Holds what is left over in each commodity.
+    Unbalanced(SourceLocation, Header, Vec<RenderedAmount>),
+    /// The posting written without an amount asserts a balance in a commodity none of its
+    /// inferred amounts are in. Holds the account and the asserted commodity.
+    AssertionCommodity(SourceLocation, Header, AccountName, Symbol),
+}
+
+impl fmt::Display for BalanceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BalanceError::MultipleInferredPostings(location, ref header, ref accounts) => {
+                let accounts: Vec<&str> = accounts.iter().map(|a| a.as_str()).collect();
+                write!(f, "{}: {} {}: only one posting may omit its amount, but {} do ({})",
+                    location, header.date().format("%Y-%m-%d"), header.payee().trim(),
+                    accounts.len(), accounts.join(", "))
+            },
+            BalanceError::NoAmounts(location, ref header) =>
+                write!(f, "{}: {} {}: no posting has an amount",
+                    location, header.date().format("%Y-%m-%d"), header.payee().trim()),
+            BalanceError::Unbalanced(location, ref header, ref residuals) => {
+                let residuals: Vec<String> = residuals.iter().map(|a| a.to_string()).collect();
+                write!(f, "{}: {} {}: transaction does not balance, off by {}",
+                    location, header.date().format("%Y-%m-%d"), header.payee().trim(),
+                    residuals.join(", "))
+            },
+            BalanceError::AssertionCommodity(location, ref header, account, symbol) =>
+                write!(f, "{}: {} {}: {} asserts a balance in {}, which its inferred amount \
+                    is not in", location, header.date().format("%Y-%m-%d"),
+                    header.payee().trim(), account, symbol),
+        }
+    }
+}
+
+/// Turns a parsed transaction into a balanced transaction, filling in the amount of the posting
+/// written without one. That posting takes the negated sum of the other postings, and becomes
+/// one posting per commodity if the other postings involve several commodities. A balance
+/// assertion on that posting goes with the inferred posting in the asserted commodity.
+///
+/// Postings with a cost count towards the balance in the cost's commodity. When every amount is
+/// written, the postings must sum to zero in each commodity once rounded to its display
+/// precision: the precision of its `commodity` directive if it has one, otherwise the most decimal
+/// places it is written with in the transaction's amounts and costs.
+///
+/// A transaction with no costs whose postings leave amounts in exactly two commodities, one
+/// positive and one negative, is an implicit conversion. The exchange rate is inferred from the
+/// two amounts, rounded to enough decimal places to reproduce the other commodity's amount at
+/// that precision, e.g. 3.333 CAD for $3.00 against -10.00 CAD. It is attached as a
+/// per-unit cost to the postings in the first posting's commodity, and the transaction records
+/// the rate as an implied price. Residuals within tolerance are dropped before looking for a
+/// conversion.
+pub fn balance_transaction(location: SourceLocation, header: Header, raw_postings: Vec<RawPosting>,
+commodities: &CommodityRegistry) -> Result<Transaction, BalanceError> {
+    let inferred: Vec<AccountName> = raw_postings.iter()
+        .filter(|p| p.amount_source() == AmountSource::Inferred)
+        .map(|p| p.account())
+        .collect();
+    if inferred.len() > 1 {
+        return Err(BalanceError::MultipleInferredPostings(location, header, inferred));
+    }
+
+    let mut residual = Balance::new();
+    let mut first_weight = None;
+    for raw in &raw_postings {
+        if let Some(amount) = raw.amount() {
+            let weight =
+                match raw.cost() {
+                    Some(cost) => cost.total(amount.quantity()),
+                    None => *amount,
+                };
+            residual.add_amount(&weight);
+            first_weight = first_weight.or(Some(weight));
+        }
+    }
+    let first_weight =
+        match first_weight {
+            Some(amount) => amount,
+            None => return Err(BalanceError::NoAmounts(location, header)),
+        };
+
+    let precisions = tolerance_precisions(&raw_postings, commodities);
+    let significant: Vec<Amount> = residual.amounts().into_iter()
+        .filter(|a| !within_tolerance(a, &precisions))
+        .collect();
+    let conversion =
+        if inferred.is_empty() && raw_postings.iter().all(|p| p.cost().is_none()) {
+            implicit_conversion(first_weight.symbol(), &significant, &precisions)
+        } else {
+            None
+        };
+
+    if inferred.is_empty() && conversion.is_none() && !significant.is_empty() {
+        let unbalanced = significant.into_iter()
+            .map(|a| RenderedAmount::new(a, commodities))
+            .collect();
+        return Err(BalanceError::Unbalanced(location, header, unbalanced));
+    }
+
+    let mut inferred_amounts: Vec<Amount> = residual.amounts().iter()
+        .map(|a| Amount::new(-a.quantity(), a.symbol()))
+        .collect();
+    if inferred_amounts.is_empty() {
+        inferred_amounts.push(Amount::new(d128::zero(), first_weight.symbol()));
+    }
+    let mismatched_assertion = raw_postings.iter()
+        .filter(|p| p.amount_source() == AmountSource::Inferred)
+        .filter_map(|p| p.assertion().map(|assertion| (p.account(), assertion.symbol())))
+        .find(|&(_, symbol)| inferred_amounts.iter().all(|a| a.symbol() != symbol));
+    if let Some((account, symbol)) = mismatched_assertion {
+        return Err(BalanceError::AssertionCommodity(location, header, account, symbol));
+    }
+
+    let mut postings = Vec::new();
+    for raw in &raw_postings {
+        let comment = raw.comment().map(|c| c.to_string());
+        match raw.amount() {
+            Some(amount) => {
+                let cost =
+                    match conversion {
+                        Some((symbol, rate)) if amount.symbol() == symbol =>
+                            Some(Cost::PerUnit(rate)),
+                        _ => raw.cost().cloned(),
+                    };
+                postings.push(Posting::new(raw.account(), *amount, AmountSource::Provided,
+                    comment)
+                    .with_cost(cost)
+                    .with_assertion(raw.assertion().cloned())
+                    .with_line(raw.line()));
+            },
+            None => {
+                for &amount in &inferred_amounts {
+                    let assertion = raw.assertion()
+                        .filter(|assertion| assertion.symbol() == amount.symbol())
+                        .cloned();
+                    postings.push(Posting::new(raw.account(), amount, AmountSource::Inferred,
+                        comment.clone())
+                        .with_assertion(assertion)
+                        .with_line(raw.line()));
+                }
+            },
+        }
+    }
+
+    let implied_price = conversion.map(|(symbol, rate)| {
+        Price::new(header.date(), symbol, rate).with_origin(PriceOrigin::Transaction)
+    });
+    Ok(Transaction::new(location, header, postings).with_implied_price(implied_price))
+}
+
+/// Balances every transaction in a parsed ledger, skipping over prices and directives. Every
+/// transaction is checked, so the errors cover the whole ledger rather than stopping at the
+/// first transaction that fails.
+pub fn balance_transactions(tree: Vec<ParseTree>, commodities: &CommodityRegistry)
+-> Result<Vec<Transaction>, Vec<BalanceError>> {
+    let mut transactions = Vec::new();
+    let mut errors = Vec::new();
+    for item in tree {
+        if let ParseTree::Transaction(location, header, postings) = item {
+            match balance_transaction(location, header, postings, commodities) {
+                Ok(transaction) => transactions.push(transaction),
+                Err(error) => errors.push(error),
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(transactions)
+    } else {
+        Err(errors)
+    }
+}
+
+
+
+// HELPERS
+
+/// Works out the exchange rate for an implicit conversion: a residual in exactly two
+/// commodities with opposite signs. Returns the commodity being priced, which is `first` if it
+/// is one of the two, and its price per unit in the other commodity. The rate has as many
+/// decimal places as the other commodity is checked at, plus one for each whole digit of the
+/// priced quantity, so multiplying it back out is off by less than the other's tolerance.
+fn implicit_conversion(first: Symbol, amounts: &[Amount], precisions: &HashMap<Symbol, u32>)
+-> Option<(Symbol, Amount)> {
+    let opposite_signs = amounts.len() == 2
+        && amounts[0].quantity().is_negative() != amounts[1].quantity().is_negative();
+    if !opposite_signs {
+        return None;
+    }
+
+    let (priced, other) =
+        if amounts[1].symbol() == first {
+            (amounts[1], amounts[0])
+        } else {
+            (amounts[0], amounts[1])
+        };
+    let precision = precisions.get(&other.symbol()).cloned().unwrap_or(0)
+        + whole_digits(priced.quantity());
+    let rate = round(-other.quantity() / priced.quantity(), precision, RoundingMode::HalfEven);
+    Some((priced.symbol(), Amount::new(rate.reduce(), other.symbol())))
+}
+
+/// The number of digits before the decimal point, not counting a leading zero.
+fn whole_digits(quantity: d128) -> u32 {
+    let whole = round(quantity.abs(), 0, RoundingMode::Truncate);
+    if whole.is_zero() {
+        0
+    } else {
+        whole.to_string().len() as u32
+    }
+}
+
+/// The precision each commodity in a transaction's amounts and costs is checked at: its declared
+/// precision, or for undeclared commodities the most decimal places it is written with.
+fn tolerance_precisions(raw_postings: &[RawPosting], commodities: &CommodityRegistry)
+-> HashMap<Symbol, u32> {
+    let mut precisions: HashMap<Symbol, u32> = HashMap::new();
+    for raw in raw_postings {
+        let amount = raw.amount().map(|a| a.symbol()).into_iter().zip(raw.render_options());
+        let cost = raw.cost().map(|cost| cost.total(d128!(1)).symbol()).into_iter()
+            .zip(raw.cost_render_options());
+        for (symbol, render_opts) in amount.chain(cost) {
+            let precision = precisions.entry(symbol).or_insert(0);
+            *precision = ::std::cmp::max(*precision, render_opts.precision());
+        }
+    }
+    for (&symbol, precision) in precisions.iter_mut() {
+        if commodities.is_declared(symbol) {
+            *precision = commodities.precision(symbol).unwrap_or(*precision);
+        }
+    }
+    precisions
+}
+
+/// Whether a residual amount is small enough to disappear at the precision its commodity is
+/// checked at.
+fn within_tolerance(residual: &Amount, precisions: &HashMap<Symbol, u32>) -> bool {
+    match precisions.get(&residual.symbol()) {
+        Some(&precision) =>
+            round(residual.quantity(), precision, RoundingMode::HalfEven).is_zero(),
+        None => residual.quantity().is_zero(),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::offset::Local;
+    use chrono::offset::TimeZone;
+    use core::amount::decimal_places;
+    use core::commodity::*;
+    use core::symbol::*;
+    use parser::parse::{parse_ledger_str, register_commodities};
+
+    fn location() -> SourceLocation {
+        SourceLocation::new(3).in_file("test.ledger")
+    }
+
+    fn header() -> Header {
+        Header::new(Local.ymd(2016, 6, 7), Status::Cleared, None, "Basic transaction".to_string(),
+            None)
+    }
+
+    fn commodities() -> CommodityRegistry {
+        let mut commodities = CommodityRegistry::new();
+        commodities.observe(Commodity::new(
+            Symbol::new("$", QuoteOption::Unquoted),
+            RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+                ThousandsSeparator::NoSeparator, 2)));
+        commodities
+    }
+
+    fn raw_posting(account: &str, amount: Option<Amount>) -> RawPosting {
+        let source = if amount.is_some() { AmountSource::Provided } else { AmountSource::Inferred };
+        let amount = amount.map(|a| (a, written_with(a.quantity())));
+        RawPosting::new(AccountName::new(account), amount, source, None)
+    }
+
+    /// The render options a quantity is parsed with, so tolerances match the written amounts.
+    fn written_with(quantity: d128) -> RenderOptions {
+        RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+            ThousandsSeparator::NoSeparator, decimal_places(quantity))
+    }
+
+    fn rendered(amounts: Vec<Amount>) -> Vec<RenderedAmount> {
+        amounts.into_iter().map(|a| RenderedAmount::new(a, &commodities())).collect()
+    }
+
+    fn dollars(quantity: d128) -> Amount {
+        Amount::new(quantity, Symbol::new("$", QuoteOption::Unquoted))
+    }
+
+    fn fund(quantity: d128) -> Amount {
+        Amount::new(quantity, Symbol::new("MUTF2351", QuoteOption::Quoted))
+    }
+
+    fn balance(raw_postings: Vec<RawPosting>) -> Result<Transaction, BalanceError> {
+        balance_transaction(location(), header(), raw_postings, &commodities())
+    }
+
+    #[test]
+    fn balance_transaction_infers_missing_amount() {
+        let result = balance(vec![
+            raw_posting("Expenses:Groceries", Some(dollars(d128!(45.00)))),
+            raw_posting("Expenses:Household", Some(dollars(d128!(5.25)))),
+            raw_posting("Liabilities:Credit", None),
+        ]);
+        assert_eq!(result.unwrap().postings()[2], Posting::new(
+            AccountName::new("Liabilities:Credit"),
+            dollars(d128!(-50.25)),
+            AmountSource::Inferred,
+            None));
+    }
+
+    #[test]
+    fn balance_transaction_all_amounts_provided() {
+        let result = balance(vec![
+            raw_posting("Expenses:Groceries", Some(dollars(d128!(45.00)))),
+            raw_posting("Liabilities:Credit", Some(dollars(d128!(-45.00)))),
+        ]).unwrap();
+        assert_eq!(result.postings().len(), 2);
+        assert!(result.postings().iter().all(|p| p.amount_source() == AmountSource::Provided));
+        assert_eq!(result.location(), location());
+    }
+
+    #[test]
+    fn balance_transaction_expands_one_posting_per_commodity() {
+        let result = balance(vec![
+            raw_posting("Assets:Investments", Some(fund(d128!(13.508)))),
+            raw_posting("Expenses:Fees", Some(dollars(d128!(9.99)))),
+            raw_posting("Equity:Transfers", None),
+        ]).unwrap();
+        let inferred: Vec<Amount> = result.postings().iter()
+            .filter(|p| p.amount_source() == AmountSource::Inferred)
+            .map(|p| *p.amount())
+            .collect();
+        assert_eq!(inferred, vec![dollars(d128!(-9.99)), fund(d128!(-13.508))]);
+    }
+
+    #[test]
+    fn balance_transaction_infers_amount_in_cost_commodity() {
+        let result = balance(vec![
+            raw_posting("Assets:Investments", Some(fund(d128!(10))))
+                .with_cost(Some((Cost::PerUnit(dollars(d128!(5.42))),
+                    RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+                        ThousandsSeparator::NoSeparator, 2)))),
+            raw_posting("Assets:Savings", None),
+        ]).unwrap();
+        assert_eq!(*result.postings()[1].amount(), dollars(d128!(-54.20)));
+        assert_eq!(result.postings()[0].cost(), Some(&Cost::PerUnit(dollars(d128!(5.42)))));
+    }
+
+    #[test]
+    fn balance_transaction_balances_with_total_cost() {
+        let result = balance(vec![
+            raw_posting("Assets:Investments", Some(fund(d128!(-10))))
+                .with_cost(Some((Cost::Total(dollars(d128!(54.20))),
+                    RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+                        ThousandsSeparator::NoSeparator, 2)))),
+            raw_posting("Assets:Savings", Some(dollars(d128!(54.20)))),
+        ]);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn balance_transaction_infers_implicit_conversion() {
+        let cad = |quantity| Amount::new(quantity, Symbol::new("CAD", QuoteOption::Unquoted));
+        let result = balance(vec![
+            raw_posting("Assets:USD", Some(dollars(d128!(100.00)))),
+            raw_posting("Assets:CAD", Some(cad(d128!(-130.00)))),
+        ]).unwrap();
+        assert_eq!(result.postings()[0].cost(), Some(&Cost::PerUnit(cad(d128!(1.3)))));
+        assert_eq!(result.postings()[1].cost(), None);
+        assert_eq!(result.implied_price(), Some(&Price::new(
+            Local.ymd(2016, 6, 7), Symbol::new("$", QuoteOption::Unquoted), cad(d128!(1.3)))
+            .with_origin(PriceOrigin::Transaction)));
+    }
+
+    #[test]
+    fn balance_transaction_rounds_implicit_conversion_rate() {
+        let cad = |quantity| Amount::new(quantity, Symbol::new("CAD", QuoteOption::Unquoted));
+        let result = balance(vec![
+            raw_posting("Assets:USD", Some(dollars(d128!(3.00)))),
+            raw_posting("Assets:CAD", Some(cad(d128!(-10.00)))),
+        ]).unwrap();
+        assert_eq!(result.postings()[0].cost(), Some(&Cost::PerUnit(cad(d128!(3.333)))));
+        assert_eq!(result.implied_price().unwrap().amount(), &cad(d128!(3.333)));
+    }
+
+    #[test]
+    fn balance_transaction_two_commodities_same_sign_is_error() {
+        let result = balance(vec![
+            raw_posting("Assets:USD", Some(dollars(d128!(100.00)))),
+            raw_posting("Assets:Investments", Some(fund(d128!(1.5)))),
+        ]);
+        let residuals = rendered(vec![dollars(d128!(100.00)), fund(d128!(1.5))]);
+        assert_eq!(result.unwrap_err(), BalanceError::Unbalanced(location(), header(), residuals));
+    }
+
+    #[test]
+    fn balance_transaction_tolerates_residual_below_precision() {
+        let result = balance(vec![
+            raw_posting("Assets:Investments", Some(fund(d128!(10.333))))
+                .with_cost(Some((Cost::PerUnit(dollars(d128!(5.41))), written_with(d128!(5.41))))),
+            raw_posting("Assets:Savings", Some(dollars(d128!(-55.90)))),
+        ]);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn balance_transaction_tolerance_uses_declared_precision() {
+        let transaction = "\
+            2016-02-07 * Buy fund\n\
+            \tAssets:Investments    10.333 \"MUTF2351\" @ $5.4123\n\
+            \tAssets:Savings    $-55.93\n";
+        let declared = format!("commodity $1,000.00\n{}", transaction);
+        for &(ledger, balances) in &[(transaction, false), (declared.as_str(), true)] {
+            let tree = parse_ledger_str(ledger).unwrap();
+            let mut commodities = CommodityRegistry::new();
+            register_commodities(&tree, &mut commodities);
+            assert_eq!(balance_transactions(tree, &commodities).is_ok(), balances);
+        }
+    }
+
+    #[test]
+    fn balance_transaction_unbalanced_is_error() {
+        let result = balance(vec![
+            raw_posting("Expenses:Groceries", Some(dollars(d128!(45.00)))),
+            raw_posting("Assets:Investments", Some(fund(d128!(1.5)))),
+            raw_posting("Liabilities:Credit", Some(dollars(d128!(-44.99)))),
+        ]);
+        let error = result.unwrap_err();
+        assert_eq!(error, BalanceError::Unbalanced(location(), header(), rendered(vec![
+            dollars(d128!(0.01)),
+            fund(d128!(1.5)),
+        ])));
+        assert_eq!(format!("{}", error), "test.ledger:3: 2016-06-07 Basic transaction: \
+            transaction does not balance, off by $0.01, 1.5 \"MUTF2351\"");
+    }
+
+    #[test]
+    fn balance_transaction_residual_at_written_precision_is_error() {
+        let result = balance(vec![
+            raw_posting("Assets:Investments", Some(fund(d128!(1.5)))),
+            raw_posting("Assets:Investments", Some(fund(d128!(-1.499)))),
+        ]);
+        assert_eq!(result.unwrap_err(),
+            BalanceError::Unbalanced(location(), header(), rendered(vec![fund(d128!(0.001))])));
+    }
+
+    #[test]
+    fn balance_error_renders_residual_exactly() {
+        let result = balance(vec![
+            raw_posting("Expenses:Groceries", Some(dollars(d128!(45.004)))),
+            raw_posting("Liabilities:Credit", Some(dollars(d128!(-45.00)))),
+        ]);
+        assert!(format!("{}", result.unwrap_err()).ends_with("off by $0.004"));
+    }
+
+    #[test]
+    fn balance_transaction_two_inferred_postings_is_error() {
+        let result = balance(vec![
+            raw_posting("Expenses:Groceries", Some(dollars(d128!(45.00)))),
+            raw_posting("Liabilities:Credit", None),
+            raw_posting("Assets:Checking", None),
+        ]);
+        let error = result.unwrap_err();
+        assert_eq!(error, BalanceError::MultipleInferredPostings(location(), header(), vec![
+            AccountName::new("Liabilities:Credit"),
+            AccountName::new("Assets:Checking"),
+        ]));
+        assert_eq!(format!("{}", error), "test.ledger:3: 2016-06-07 Basic transaction: only one \
+            posting may omit its amount, but 2 do (Liabilities:Credit, Assets:Checking)");
+    }
+
+    #[test]
+    fn balance_transaction_no_amounts_is_error() {
+        let result = balance(vec![
+            raw_posting("Liabilities:Credit", None),
+        ]);
+        assert_eq!(result, Err(BalanceError::NoAmounts(location(), header())));
+    }
+
+    #[test]
+    fn balance_transaction_inferred_assertion_follows_commodity() {
+        let result = balance(vec![
+            raw_posting("Assets:Investments", Some(fund(d128!(10)))),
+            raw_posting("Assets:Checking", Some(dollars(d128!(-54.10)))),
+            raw_posting("Equity:Transfers", None).with_assertion(Some(dollars(d128!(100.00)))),
+        ]).unwrap();
+        let assertions: Vec<Option<&Amount>> = result.postings()[2..].iter()
+            .map(|p| p.assertion())
+            .collect();
+        assert_eq!(assertions, vec![Some(&dollars(d128!(100.00))), None]);
+    }
+
+    #[test]
+    fn balance_transaction_inferred_assertion_in_other_commodity_is_error() {
+        let result = balance(vec![
+            raw_posting("Expenses:Groceries", Some(dollars(d128!(45.00)))),
+            raw_posting("Assets:Savings", None).with_assertion(Some(fund(d128!(10)))),
+        ]);
+        assert_eq!(result, Err(BalanceError::AssertionCommodity(location(), header(),
+            AccountName::new("Assets:Savings"), Symbol::new("MUTF2351", QuoteOption::Quoted))));
+        assert_eq!(format!("{}", result.unwrap_err()),
+            "test.ledger:3: 2016-06-07 Basic transaction: Assets:Savings asserts a balance in \
+            \"MUTF2351\", which its inferred amount is not in");
+    }
+
+    #[test]
+    fn balance_transactions_skips_other_items() {
+        let tree = vec![
+            ParseTree::Account(location(), AccountName::new("Assets:Checking"), None),
+            ParseTree::Transaction(location(), header(), vec![
+                raw_posting("Expenses:Groceries", Some(dollars(d128!(45.00)))),
+                raw_posting("Liabilities:Credit", None),
+            ]),
+        ];
+        let result = balance_transactions(tree, &commodities()).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(*result[0].postings()[1].amount(), dollars(d128!(-45.00)));
+    }
+
+    #[test]
+    fn balance_transactions_reports_every_error() {
+        let tree = vec![
+            ParseTree::Transaction(SourceLocation::new(1), header(), vec![
+                raw_posting("Expenses:Groceries", Some(dollars(d128!(45.00)))),
+                raw_posting("Liabilities:Credit", Some(dollars(d128!(-40.00)))),
+            ]),
+            ParseTree::Transaction(SourceLocation::new(5), header(), vec![
+                raw_posting("Expenses:Groceries", Some(dollars(d128!(45.00)))),
+                raw_posting("Liabilities:Credit", None),
+            ]),
+            ParseTree::Transaction(SourceLocation::new(9), header(), vec![
+                raw_posting("Liabilities:Credit", None),
+            ]),
+        ];
+        let errors = balance_transactions(tree, &commodities()).unwrap_err();
+        assert_eq!(errors, vec![
+            BalanceError::Unbalanced(SourceLocation::new(1), header(),
+                rendered(vec![dollars(d128!(5.00))])),
+            BalanceError::NoAmounts(SourceLocation::new(9), header()),
+        ]);
+    }
+}
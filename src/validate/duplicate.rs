@@ -0,0 +1,234 @@
+use std::collections::BTreeSet;
+use core::location::SourceLocation;
+use core::transaction::Transaction;
+use validate::journal::Journal;
+use validate::strict::edit_distance;
+
+
+/// How far apart transactions can be and still be reported as duplicates.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DuplicateOptions {
+    window_days: i64,
+    min_confidence: f64,
+}
+
+impl DuplicateOptions {
+    /// `window_days` is the largest number of days between two transactions that can be
+    /// duplicates, and `min_confidence` the lowest confidence, from 0 to 1, worth reporting.
+    pub fn new(window_days: i64, min_confidence: f64) -> DuplicateOptions {
+        DuplicateOptions {
+            window_days: window_days,
+            min_confidence: min_confidence,
+        }
+    }
+
+    pub fn window_days(&self) -> i64 {
+        self.window_days
+    }
+
+    pub fn min_confidence(&self) -> f64 {
+        self.min_confidence
+    }
+}
+
+impl Default for DuplicateOptions {
+    fn default() -> DuplicateOptions {
+        DuplicateOptions::new(3, 0.6)
+    }
+}
+
+/// A pair of transactions that look like the same real-world transaction entered twice.
+/// Transactions are referred to by their index in `Journal::transactions`; `first` is the one
+/// read first.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Duplicate {
+    first: usize,
+    second: usize,
+    first_location: SourceLocation,
+    second_location: SourceLocation,
+    confidence: f64,
+}
+
+impl Duplicate {
+    pub fn first(&self) -> usize {
+        self.first
+    }
+
+    pub fn second(&self) -> usize {
+        self.second
+    }
+
+    pub fn first_location(&self) -> SourceLocation {
+        self.first_location
+    }
+
+    pub fn second_location(&self) -> SourceLocation {
+        self.second_location
+    }
+
+    /// How alike the two transactions are, from 0 to 1.
+    pub fn confidence(&self) -> f64 {
+        self.confidence
+    }
+}
+
+/// Finds likely duplicate transactions anywhere in the journal, including across included
+/// files. Pairs must be within the date window and share at least one posting amount; they
+/// are then scored on how many amounts, accounts and payee words they share and how close
+/// their dates are. Results are ordered by confidence, most confident first.
+pub fn find_duplicates(journal: &Journal, options: &DuplicateOptions) -> Vec<Duplicate> {
+    let transactions = journal.transactions();
+    let fingerprints: Vec<Fingerprint> = transactions.iter().map(Fingerprint::new).collect();
+    let mut order: Vec<usize> = (0..transactions.len()).collect();
+    order.sort_by_key(|&index| transactions[index].header().date());
+
+    let mut duplicates = Vec::new();
+    for (position, &a) in order.iter().enumerate() {
+        for &b in &order[position + 1..] {
+            let days = transactions[b].header().date()
+                .signed_duration_since(transactions[a].header().date())
+                .num_days();
+            if days > options.window_days {
+                break;
+            }
+
+            let confidence = fingerprints[a].similarity(&fingerprints[b], days, options);
+            if confidence >= options.min_confidence {
+                let (first, second) = if a < b { (a, b) } else { (b, a) };
+                duplicates.push(Duplicate {
+                    first: first,
+                    second: second,
+                    first_location: transactions[first].location(),
+                    second_location: transactions[second].location(),
+                    confidence: confidence,
+                });
+            }
+        }
+    }
+    duplicates.sort_by(|x, y| {
+        y.confidence.partial_cmp(&x.confidence).expect("confidence is a number")
+            .then(x.first.cmp(&y.first))
+            .then(x.second.cmp(&y.second))
+    });
+    duplicates
+}
+
+
+
+// HELPERS
+
+/// The parts of a transaction compared when looking for duplicates.
+struct Fingerprint {
+    amounts: Vec<String>,
+    accounts: BTreeSet<String>,
+    payee: String,
+}
+
+impl Fingerprint {
+    fn new(transaction: &Transaction) -> Fingerprint {
+        let mut amounts: Vec<String> = transaction.postings().iter()
+            .map(|p| format!("{} {}", p.amount().quantity().reduce(), p.amount().symbol()))
+            .collect();
+        amounts.sort();
+        Fingerprint {
+            amounts: amounts,
+            accounts: transaction.postings().iter()
+                .map(|p| p.account().as_str().to_string())
+                .collect(),
+            payee: normalize_payee(transaction.header().payee()),
+        }
+    }
+
+    /// Scores how alike two transactions are. Amounts carry the most weight since bank imports
+    /// rarely agree with hand-entered payees and accounts.
+    fn similarity(&self, other: &Fingerprint, days: i64, options: &DuplicateOptions) -> f64 {
+        let amounts = multiset_overlap(&self.amounts, &other.amounts);
+        if amounts == 0.0 {
+            return 0.0;
+        }
+        let accounts = self.accounts.intersection(&other.accounts).count() as f64
+            / self.accounts.union(&other.accounts).count() as f64;
+        let payee = string_similarity(&self.payee, &other.payee);
+        let date = 1.0 - days as f64 / (options.window_days + 1) as f64;
+
+        0.45 * amounts + 0.2 * accounts + 0.2 * payee + 0.15 * date
+    }
+}
+
+/// Lower-cases a payee and keeps only its words without digits, so reference numbers and
+/// punctuation added by banks don't count as differences. e.g. `GROCERY STORE #1234` becomes
+/// `grocery store`.
+fn normalize_payee(payee: &str) -> String {
+    let words: Vec<String> = payee
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty() && !word.chars().any(|c| c.is_numeric()))
+        .map(|word| word.to_lowercase())
+        .collect();
+    words.join(" ")
+}
+
+/// The share of items the two sorted lists have in common, counting repeats.
+fn multiset_overlap(a: &[String], b: &[String]) -> f64 {
+    let (mut i, mut j, mut common) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            common += 1;
+            i += 1;
+            j += 1;
+        } else if a[i] < b[j] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    (2 * common) as f64 / (a.len() + b.len()) as f64
+}
+
+/// One minus the edit distance between the strings, relative to the longer one.
+fn string_similarity(a: &str, b: &str) -> f64 {
+    let longest = ::std::cmp::max(a.chars().count(), b.chars().count());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - edit_distance(a, b) as f64 / longest as f64
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_payee_test() {
+        assert_eq!(normalize_payee("GROCERY STORE #1234"), "grocery store");
+        assert_eq!(normalize_payee("Grocery Store"), "grocery store");
+    }
+
+    #[test]
+    fn find_duplicates_across_included_files() {
+        let journal = Journal::load("test/data/duplicates/main.ledger").unwrap();
+        let duplicates = find_duplicates(&journal, &DuplicateOptions::default());
+        assert_eq!(duplicates.len(), 1);
+        let duplicate = &duplicates[0];
+        assert_eq!(format!("{}", duplicate.first_location()),
+            "test/data/duplicates/main.ledger:1");
+        assert_eq!(format!("{}", duplicate.second_location()),
+            "test/data/duplicates/bank.ledger:1");
+        assert!(duplicate.confidence() > 0.8 && duplicate.confidence() < 1.0);
+    }
+
+    #[test]
+    fn find_duplicates_respects_window() {
+        let journal = Journal::load("test/data/duplicates/main.ledger").unwrap();
+        let duplicates = find_duplicates(&journal, &DuplicateOptions::new(0, 0.6));
+        assert_eq!(duplicates, vec![]);
+    }
+
+    #[test]
+    fn find_duplicates_low_confidence_threshold_includes_weaker_matches() {
+        let journal = Journal::load("test/data/duplicates/main.ledger").unwrap();
+        let duplicates = find_duplicates(&journal, &DuplicateOptions::new(3, 0.3));
+        assert_eq!(duplicates.len(), 2);
+        assert!(duplicates[0].confidence() > duplicates[1].confidence());
+    }
+}
@@ -0,0 +1,602 @@
+use chrono::Date;
+use chrono::offset::Local;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use core::account::{AccountName, AccountTree, AccountType, AccountTypes};
+use core::balance::Balance;
+use core::commodity::{Commodity, CommodityRegistry};
+use core::amount::Amount;
+use core::corporate_action::CorporateActions;
+use core::location::SourceLocation;
+use core::price::{Price, PriceOrigin};
+use core::price_history::PriceHistory;
+use core::transaction::{Posting, Transaction};
+use parser::ast::ParseTree;
+use parser::parse::{read_ledger, read_located_pricedb, register_account_types,
+    register_commodities, ParseError};
+use validate::assertion::{check_assertions, AssertionError};
+use validate::balance::{balance_transactions, BalanceError};
+use validate::strict::{check_declarations, Strictness, UndeclaredError};
+
+
+/// Reasons a journal could not be loaded.
+#[derive(Debug)]
+pub enum LoadError {
+    /// A ledger or price DB file could not be read or parsed.
+    Parse(ParseError),
+    /// A file includes itself, directly or through other includes.
+    IncludeCycle(String),
+    /// In strict mode, some names were used without being declared.
+    Undeclared(Vec<UndeclaredError>),
+    /// Some transactions don't balance.
+    Unbalanced(Vec<BalanceError>),
+    /// Some balance assertions don't hold.
+    Assertions(Vec<AssertionError>),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadError::Parse(ref err) => write!(f, "{}", err),
+            LoadError::IncludeCycle(ref file_path) =>
+                write!(f, "{}: file includes itself", file_path),
+            LoadError::Undeclared(ref errors) => write_lines(f, errors),
+            LoadError::Unbalanced(ref errors) => write_lines(f, errors),
+            LoadError::Assertions(ref errors) => write_lines(f, errors),
+        }
+    }
+}
+
+impl From<ParseError> for LoadError {
+    fn from(err: ParseError) -> LoadError {
+        LoadError::Parse(err)
+    }
+}
+
+/// A posting along with the date of its transaction, and the index of that transaction in the
+/// journal.
+#[derive(Clone, PartialEq, Debug)]
+pub struct JournalPosting {
+    date: Date<Local>,
+    transaction: usize,
+    posting: Posting,
+}
+
+impl JournalPosting {
+    pub fn date(&self) -> Date<Local> {
+        self.date
+    }
+
+    /// The index of the posting's transaction in `Journal::transactions`.
+    pub fn transaction(&self) -> usize {
+        self.transaction
+    }
+
+    pub fn posting(&self) -> &Posting {
+        &self.posting
+    }
+}
+
+/// A validated ledger: every transaction balances and every balance assertion holds.
+///
+/// Transactions are kept in the order they were read. Postings and prices are sorted by date,
+/// with entries on the same date left in the order they were read.
+#[derive(Debug)]
+pub struct Journal {
+    transactions: Vec<Transaction>,
+    postings: Vec<JournalPosting>,
+    prices: Vec<Price>,
+    price_locations: Vec<SourceLocation>,
+    implied_prices: Vec<Price>,
+    price_history: PriceHistory,
+    corporate_actions: CorporateActions,
+    commodities: CommodityRegistry,
+    account_types: AccountTypes,
+    accounts: BTreeSet<AccountName>,
+    declared_accounts: BTreeMap<AccountName, SourceLocation>,
+    source_files: Vec<String>,
+}
+
+impl Journal {
+    /// Loads a ledger file and every file it includes, then validates it permissively.
+    pub fn load(file_path: &str) -> Result<Journal, LoadError> {
+        Journal::load_with(file_path, Strictness::Permissive)
+    }
+
+    /// Loads a ledger file and every file it includes, then validates it with the given
+    /// strictness.
+    pub fn load_with(file_path: &str, strictness: Strictness) -> Result<Journal, LoadError> {
+        let mut source_files = Vec::new();
+        let tree = expand_includes(file_path, &mut Vec::new(), &mut source_files)?;
+        let mut journal = Journal::from_parse_tree_with(tree, strictness)?;
+        journal.source_files = source_files;
+        Ok(journal)
+    }
+
+    /// Builds a journal from a parsed ledger whose includes have already been expanded,
+    /// validating it permissively.
+    pub fn from_parse_tree(tree: Vec<ParseTree>) -> Result<Journal, LoadError> {
+        Journal::from_parse_tree_with(tree, Strictness::Permissive)
+    }
+
+    /// Builds a journal from a parsed ledger whose includes have already been expanded.
+    /// Directives are applied first, so they take effect wherever they appear in the ledger.
+    pub fn from_parse_tree_with(tree: Vec<ParseTree>, strictness: Strictness)
+    -> Result<Journal, LoadError> {
+        let mut commodities = CommodityRegistry::new();
+        register_commodities(&tree, &mut commodities);
+        let mut account_types = AccountTypes::default();
+        register_account_types(&tree, &mut account_types);
+
+        let undeclared = check_declarations(&tree, strictness);
+        if !undeclared.is_empty() {
+            return Err(LoadError::Undeclared(undeclared));
+        }
+
+        let mut declared_accounts = BTreeMap::new();
+        let mut prices = Vec::new();
+        let mut corporate_actions = Vec::new();
+        for item in &tree {
+            match *item {
+                ParseTree::Account(location, account, _) => {
+                    declared_accounts.entry(account).or_insert(location);
+                },
+                ParseTree::Price(location, ref price, _) => prices.push((location, price.clone())),
+                ParseTree::CorporateAction(_, action) => corporate_actions.push(action),
+                _ => {},
+            }
+        }
+
+        let transactions = balance_transactions(tree, &commodities)
+            .map_err(LoadError::Unbalanced)?;
+        let assertion_errors = check_assertions(&transactions, &commodities);
+        if !assertion_errors.is_empty() {
+            return Err(LoadError::Assertions(assertion_errors));
+        }
+
+        let mut accounts: BTreeSet<AccountName> = declared_accounts.keys().cloned().collect();
+        let mut postings = Vec::new();
+        for (index, transaction) in transactions.iter().enumerate() {
+            for posting in transaction.postings() {
+                accounts.insert(posting.account());
+                postings.push(JournalPosting {
+                    date: transaction.header().date(),
+                    transaction: index,
+                    posting: posting.clone(),
+                });
+            }
+        }
+        postings.sort_by_key(|p| p.date);
+        prices.sort_by_key(|p| p.1.sort_key());
+        let (price_locations, prices): (Vec<_>, Vec<_>) = prices.into_iter().unzip();
+        let mut implied_prices: Vec<Price> = transactions.iter()
+            .flat_map(transaction_prices)
+            .collect();
+        implied_prices.sort_by_key(|p| p.sort_key());
+        let mut price_history = PriceHistory::from_prices(&implied_prices);
+        price_history.extend(&prices);
+
+        Ok(Journal {
+            transactions: transactions,
+            postings: postings,
+            prices: prices,
+            price_locations: price_locations,
+            implied_prices: implied_prices,
+            price_history: price_history,
+            corporate_actions: CorporateActions::new(corporate_actions),
+            commodities: commodities,
+            account_types: account_types,
+            accounts: accounts,
+            declared_accounts: declared_accounts,
+            source_files: Vec::new(),
+        })
+    }
+
+    /// Adds the prices from a price DB file to the journal's price history.
+    pub fn add_pricedb(&mut self, file_path: &str) -> Result<(), LoadError> {
+        let pricedb = read_located_pricedb(file_path)?;
+        let mut prices: Vec<(SourceLocation, Price)> = self.price_locations.drain(..)
+            .zip(self.prices.drain(..))
+            .collect();
+        for &(location, ref price, render_opts) in &pricedb {
+            self.commodities.observe(Commodity::new(price.amount().symbol(), render_opts));
+            prices.push((location, price.clone()));
+        }
+        prices.sort_by_key(|p| p.1.sort_key());
+        let (price_locations, prices) = prices.into_iter().unzip();
+        self.price_locations = price_locations;
+        self.prices = prices;
+        self.price_history.extend(pricedb.iter().map(|p| &p.1));
+        self.source_files.push(file_path.to_string());
+        Ok(())
+    }
+
+    pub fn transactions(&self) -> &[Transaction] {
+        &self.transactions
+    }
+
+    /// Every posting in the journal, sorted by date.
+    pub fn postings(&self) -> &[JournalPosting] {
+        &self.postings
+    }
+
+    /// Every price in the journal, sorted by date.
+    pub fn prices(&self) -> &[Price] {
+        &self.prices
+    }
+
+    /// Where each price was read from, in the same order as `prices`.
+    pub fn price_locations(&self) -> &[SourceLocation] {
+        &self.price_locations
+    }
+
+    /// Prices implied by transactions, from posting costs and implicit conversions between two
+    /// commodities, sorted by date.
+    pub fn implied_prices(&self) -> &[Price] {
+        &self.implied_prices
+    }
+
+    /// Every price, explicit or implied, indexed for lookups by date. On a date with both, an
+    /// explicit price is preferred over an implied one, and a price DB over the ledger. Use
+    /// `PriceHistory::restricted_to` to value with only some origins.
+    pub fn price_history(&self) -> &PriceHistory {
+        &self.price_history
+    }
+
+    /// Prices as of a date: every price recorded before a split or rename of its commodity is
+    /// restated in the commodity's units and symbol on that date, so each commodity has one
+    /// continuous series.
+    pub fn adjusted_price_history(&self, date: Date<Local>) -> PriceHistory {
+        self.corporate_actions.adjust_history(&self.price_history, date)
+    }
+
+    /// The splits and renames declared with `split` and `rename` directives, sorted by date.
+    pub fn corporate_actions(&self) -> &CorporateActions {
+        &self.corporate_actions
+    }
+
+    pub fn commodities(&self) -> &CommodityRegistry {
+        &self.commodities
+    }
+
+    pub fn account_types(&self) -> &AccountTypes {
+        &self.account_types
+    }
+
+    /// Every account that is declared or posted to, in alphabetical order.
+    pub fn accounts(&self) -> &BTreeSet<AccountName> {
+        &self.accounts
+    }
+
+    /// The accounts declared with account directives, in alphabetical order, along with where
+    /// each was first declared.
+    pub fn declared_accounts(&self) -> &BTreeMap<AccountName, SourceLocation> {
+        &self.declared_accounts
+    }
+
+    /// The files the journal was loaded from, in the order they were read.
+    pub fn source_files(&self) -> &[String] {
+        &self.source_files
+    }
+
+    /// The commodities held in asset and liability accounts at the end of a date. Quantities
+    /// posted before a split or rename are restated in the commodity's units and symbol on that
+    /// date, to line up with `adjusted_price_history`.
+    pub fn holdings(&self, date: Date<Local>) -> Balance {
+        let mut holdings = Balance::new();
+        for posting in self.postings.iter().take_while(|p| p.date <= date) {
+            match self.account_types.classify(posting.posting.account()) {
+                Some(AccountType::Asset) | Some(AccountType::Liability) => {
+                    let amount = self.corporate_actions.adjust_amount(posting.posting.amount(),
+                        posting.date, date);
+                    holdings.add_amount(&amount);
+                },
+                _ => {},
+            }
+        }
+        holdings
+    }
+
+    /// Builds the account tree for all postings in the journal.
+    pub fn account_tree(&self) -> AccountTree {
+        AccountTree::from_postings(self.postings.iter().map(|p| &p.posting))
+    }
+}
+
+
+
+// HELPERS
+
+/// The prices a transaction implies: the per-unit cost of each posting with a cost, and the rate
+/// of any implicit conversion, without repeats.
+fn transaction_prices(transaction: &Transaction) -> Vec<Price> {
+    let date = transaction.header().date();
+    let costs = transaction.postings().iter().filter_map(|posting| {
+        let quantity = posting.amount().quantity();
+        match posting.cost() {
+            Some(cost) if !quantity.is_zero() => {
+                let total = cost.total(quantity);
+                let per_unit = Amount::new((total.quantity() / quantity).reduce(), total.symbol());
+                Some(Price::new(date, posting.amount().symbol(), per_unit)
+                    .with_origin(PriceOrigin::Transaction))
+            },
+            _ => None,
+        }
+    });
+    let mut prices: Vec<Price> = Vec::new();
+    for price in costs.chain(transaction.implied_price().cloned()) {
+        if !prices.contains(&price) {
+            prices.push(price);
+        }
+    }
+    prices
+}
+
+fn write_lines<T: fmt::Display>(f: &mut fmt::Formatter, items: &[T]) -> fmt::Result {
+    for (index, item) in items.iter().enumerate() {
+        if index > 0 {
+            writeln!(f)?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+/// Reads a ledger file, replacing each include directive with the contents of the included
+/// file. `stack` holds the files currently being read, to catch files that include themselves.
+fn expand_includes(file_path: &str, stack: &mut Vec<PathBuf>, source_files: &mut Vec<String>)
+-> Result<Vec<ParseTree>, LoadError> {
+    let canonical = fs::canonicalize(file_path).unwrap_or_else(|_| PathBuf::from(file_path));
+    if stack.contains(&canonical) {
+        return Err(LoadError::IncludeCycle(file_path.to_string()));
+    }
+
+    let tree = read_ledger(file_path)?;
+    source_files.push(file_path.to_string());
+    stack.push(canonical);
+
+    let directory = Path::new(file_path).parent().unwrap_or_else(|| Path::new(""));
+    let mut expanded = Vec::new();
+    for item in tree {
+        match item {
+            ParseTree::Include(include_path) => {
+                let include_path = directory.join(include_path);
+                let included = expand_includes(&include_path.to_string_lossy(), stack,
+                    source_files)?;
+                expanded.extend(included);
+            },
+            _ => expanded.push(item),
+        }
+    }
+
+    stack.pop();
+    Ok(expanded)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::offset::TimeZone;
+    use decimal::d128;
+    use core::amount::Amount;
+    use core::commodity::*;
+    use core::location::SourceLocation;
+    use core::symbol::*;
+    use core::transaction::*;
+    use parser::ast::RawPosting;
+    use parser::parse::parse_ledger_str;
+
+    #[test]
+    fn load_follows_includes() {
+        let journal = Journal::load("test/data/journal/main.ledger").unwrap();
+        assert_eq!(journal.source_files(), &[
+            "test/data/journal/main.ledger".to_string(),
+            "test/data/journal/2016/june.ledger".to_string(),
+        ]);
+        assert_eq!(journal.transactions().len(), 3);
+        assert_eq!(journal.prices().len(), 2);
+        let location = journal.transactions()[1].location();
+        assert_eq!(location.file(), Some("test/data/journal/2016/june.ledger"));
+        assert_eq!(location.line(), 1);
+    }
+
+    #[test]
+    fn load_sorts_postings_by_date() {
+        let journal = Journal::load("test/data/journal/main.ledger").unwrap();
+        let dates: Vec<Date<Local>> = journal.postings().iter().map(|p| p.date()).collect();
+        assert_eq!(dates, vec![
+            Local.ymd(2016, 6, 1), Local.ymd(2016, 6, 1),
+            Local.ymd(2016, 6, 7), Local.ymd(2016, 6, 7),
+            Local.ymd(2016, 6, 8), Local.ymd(2016, 6, 8),
+        ]);
+        assert_eq!(journal.postings()[0].transaction(), 0);
+        assert_eq!(*journal.postings()[3].posting().amount(),
+            Amount::new(d128!(-45.00), Symbol::new("$", QuoteOption::Unquoted)));
+    }
+
+    #[test]
+    fn load_collects_accounts_and_directives() {
+        let journal = Journal::load("test/data/journal/main.ledger").unwrap();
+        assert!(journal.accounts().contains(&AccountName::new("Assets:Checking")));
+        assert!(journal.accounts().contains(&AccountName::new("Assets:Unused")));
+        assert_eq!(journal.declared_accounts().len(), 1);
+        assert!(journal.commodities().is_declared(Symbol::new("$", QuoteOption::Unquoted)));
+        assert_eq!(journal.account_tree().find("Expenses:Groceries").unwrap()
+            .balance().quantity(Symbol::new("$", QuoteOption::Unquoted)), d128!(95.00));
+    }
+
+    #[test]
+    fn holdings_sums_asset_and_liability_postings() {
+        let journal = Journal::load("test/data/journal/main.ledger").unwrap();
+        let dollars = Symbol::new("$", QuoteOption::Unquoted);
+        assert!(journal.holdings(Local.ymd(2016, 5, 31)).is_zero());
+        assert_eq!(journal.holdings(Local.ymd(2016, 6, 1)).quantity(dollars), d128!(1000.00));
+        assert_eq!(journal.holdings(Local.ymd(2016, 6, 30)).quantity(dollars), d128!(905.00));
+    }
+
+    #[test]
+    fn holdings_and_prices_follow_corporate_actions() {
+        let tree = parse_ledger_str("\
+            P 2016-02-07 \"MUTF2351\" $10.82\n\
+            2016-02-07 * Buy fund\n\
+            \tAssets:Investments    10 \"MUTF2351\"\n\
+            \tAssets:Checking    $-108.20\n\
+            split 2016-03-01 \"MUTF2351\" 2:1\n\
+            rename 2016-04-01 \"MUTF2351\" \"MUTF9999\"\n\
+            P 2016-04-08 \"MUTF9999\" $5.61\n").unwrap();
+        let journal = Journal::from_parse_tree(tree).unwrap();
+        let fund = Symbol::new("MUTF2351", QuoteOption::Quoted);
+        let renamed = Symbol::new("MUTF9999", QuoteOption::Quoted);
+        let dollars = Symbol::new("$", QuoteOption::Unquoted);
+        assert_eq!(journal.corporate_actions().actions().len(), 2);
+        assert_eq!(journal.holdings(Local.ymd(2016, 2, 29)).quantity(fund), d128!(10));
+        assert_eq!(journal.holdings(Local.ymd(2016, 3, 1)).quantity(fund), d128!(20));
+        let holdings = journal.holdings(Local.ymd(2016, 4, 30));
+        assert_eq!(holdings.quantity(fund), d128!(0));
+        assert_eq!(holdings.quantity(renamed), d128!(20));
+
+        let history = journal.adjusted_price_history(Local.ymd(2016, 4, 30))
+            .restricted_to(&[PriceOrigin::Ledger]);
+        let series: Vec<d128> = history.series(renamed, dollars).iter()
+            .map(|p| p.amount().quantity())
+            .collect();
+        assert_eq!(series, vec![d128!(5.41), d128!(5.61)]);
+        assert_eq!(journal.price_history().series(fund, dollars).len(), 2);
+        assert_eq!(journal.transactions()[0].postings()[0].amount().quantity(), d128!(10));
+    }
+
+    #[test]
+    fn load_with_strict_reports_undeclared_names() {
+        let result = Journal::load_with("test/data/journal/main.ledger", Strictness::Strict);
+        match result {
+            Err(LoadError::Undeclared(errors)) => {
+                let names: Vec<&str> = errors.iter().map(|e| e.name()).collect();
+                assert_eq!(names, vec!["Assets:Checking", "Equity:Opening", "Expenses:Groceries",
+                    "AAPL"]);
+                assert_eq!(errors[2].location().file(), Some("test/data/journal/2016/june.ledger"));
+            },
+            other => panic!("expected undeclared names, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn load_locates_corporate_actions_in_included_files() {
+        let journal = Journal::load("test/data/journal/actions.ledger").unwrap();
+        assert_eq!(journal.corporate_actions().actions().len(), 2);
+
+        let result = Journal::load_with("test/data/journal/actions.ledger", Strictness::Strict);
+        match result {
+            Err(LoadError::Undeclared(errors)) => {
+                assert_eq!(errors.len(), 1);
+                assert_eq!(format!("{}", errors[0]),
+                    "test/data/journal/2016/actions.ledger:4: undeclared commodity MUTF9999");
+            },
+            other => panic!("expected undeclared names, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn from_parse_tree_records_implied_prices() {
+        let tree = parse_ledger_str("\
+            2016-06-07 * Currency exchange\n\
+            \tAssets:USD    $100.00\n\
+            \tAssets:CAD    -130.00 CAD\n").unwrap();
+        let journal = Journal::from_parse_tree(tree).unwrap();
+        assert_eq!(journal.implied_prices(), &[Price::new(
+            Local.ymd(2016, 6, 7),
+            Symbol::new("$", QuoteOption::Unquoted),
+            Amount::new(d128!(1.3), Symbol::new("CAD", QuoteOption::Unquoted)))
+            .with_origin(PriceOrigin::Transaction)]);
+        assert!(journal.prices().is_empty());
+        assert_eq!(journal.price_history().len(), 1);
+    }
+
+    #[test]
+    fn from_parse_tree_records_prices_implied_by_costs() {
+        let tree = parse_ledger_str("\
+            P 2016-02-07 \"MUTF2351\" $5.40\n\
+            2016-02-07 * Buy fund\n\
+            \tAssets:Investments    10 \"MUTF2351\" @ $5.41\n\
+            \tAssets:Checking\n\
+            2016-02-09 * Sell fund\n\
+            \tAssets:Investments    -4 \"MUTF2351\" @@ $28.40\n\
+            \tAssets:Checking\n").unwrap();
+        let journal = Journal::from_parse_tree(tree).unwrap();
+        let fund = Symbol::new("MUTF2351", QuoteOption::Quoted);
+        let dollars = Symbol::new("$", QuoteOption::Unquoted);
+        assert_eq!(journal.implied_prices(), &[
+            Price::new(Local.ymd(2016, 2, 7), fund, Amount::new(d128!(5.41), dollars))
+                .with_origin(PriceOrigin::Transaction),
+            Price::new(Local.ymd(2016, 2, 9), fund, Amount::new(d128!(7.1), dollars))
+                .with_origin(PriceOrigin::Transaction),
+        ]);
+        assert_eq!(journal.prices()[0].origin(), PriceOrigin::Ledger);
+
+        let history = journal.price_history();
+        let latest = history.latest(fund, dollars, Local.ymd(2016, 2, 7)).unwrap();
+        assert_eq!(latest.origin(), PriceOrigin::Ledger);
+        let latest = history.latest(fund, dollars, Local.ymd(2016, 2, 9)).unwrap();
+        assert_eq!(latest.origin(), PriceOrigin::Transaction);
+        let explicit = history.restricted_to(&[PriceOrigin::PriceDb, PriceOrigin::Ledger]);
+        let latest = explicit.latest(fund, dollars, Local.ymd(2016, 2, 9)).unwrap();
+        assert_eq!(latest.amount().quantity(), d128!(5.40));
+    }
+
+    #[test]
+    fn add_pricedb_extends_price_history() {
+        let mut journal = Journal::load("test/data/journal/main.ledger").unwrap();
+        journal.add_pricedb("test/data/multiple.pricedb").unwrap();
+        let fund = Symbol::new("MUTF2351", QuoteOption::Quoted);
+        let dollars = Symbol::new("$", QuoteOption::Unquoted);
+        let latest = journal.price_history().latest(fund, dollars, Local.ymd(2016, 2, 8)).unwrap();
+        assert_eq!(latest.date(), Local.ymd(2016, 2, 8));
+        assert_eq!(journal.price_history().len(), journal.prices().len());
+        assert_eq!(journal.price_locations().len(), journal.prices().len());
+        assert!(journal.price_locations().iter()
+            .any(|location| location.file() == Some("test/data/multiple.pricedb")));
+    }
+
+    #[test]
+    fn load_include_cycle_is_error() {
+        let result = Journal::load("test/data/journal/cycle.ledger");
+        match result {
+            Err(LoadError::IncludeCycle(file_path)) =>
+                assert_eq!(file_path, "test/data/journal/cycle.ledger"),
+            other => panic!("expected include cycle, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn load_missing_include_is_error() {
+        let result = Journal::load("test/data/journal/missing.ledger");
+        match result {
+            Err(LoadError::Parse(ParseError::Io(file_path, _))) =>
+                assert_eq!(file_path, "test/data/journal/does-not-exist.ledger"),
+            other => panic!("expected missing include, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn from_parse_tree_reports_failed_assertions() {
+        let dollars = Symbol::new("$", QuoteOption::Unquoted);
+        let render_opts = RenderOptions::new(SymbolPosition::Left, Spacing::NoSpace,
+            ThousandsSeparator::NoSeparator, 0);
+        let header = Header::new(Local.ymd(2016, 6, 1), Status::Cleared, None,
+            "Opening".to_string(), None);
+        let tree = vec![ParseTree::Transaction(SourceLocation::new(1), header, vec![
+            RawPosting::new(AccountName::new("Assets:Checking"),
+                Some((Amount::new(d128!(100), dollars), render_opts)),
+                AmountSource::Provided, None)
+                .with_assertion(Some(Amount::new(d128!(90), dollars))),
+            RawPosting::new(AccountName::new("Equity:Opening"), None, AmountSource::Inferred,
+                None),
+        ])];
+        match Journal::from_parse_tree(tree) {
+            Err(LoadError::Assertions(errors)) => assert_eq!(errors.len(), 1),
+            other => panic!("expected failed assertion, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,464 @@
+use chrono::Date;
+use chrono::offset::Local;
+use rust_core::str::FromStr;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use core::account::AccountName;
+use core::location::SourceLocation;
+use core::symbol::Symbol;
+use validate::journal::Journal;
+
+
+/// The checks the linter can make.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Rule {
+    /// A transaction dated earlier than the one before it in the same file.
+    OutOfOrder,
+    /// A transaction or price dated after today.
+    FutureDated,
+    /// A transaction with the same date, payee and postings as an earlier one.
+    Duplicate,
+    /// A posting to an account that has sub-accounts.
+    ParentPosting,
+    /// An account declared with a directive but never posted to.
+    UnusedAccount,
+    /// A price for a commodity no posting ever holds.
+    UnheldCommodityPrice,
+    /// Payees that differ only by case.
+    PayeeCase,
+}
+
+impl Rule {
+    pub fn all() -> [Rule; 7] {
+        [Rule::OutOfOrder, Rule::FutureDated, Rule::Duplicate, Rule::ParentPosting,
+            Rule::UnusedAccount, Rule::UnheldCommodityPrice, Rule::PayeeCase]
+    }
+
+    /// The name used to refer to the rule in configuration and output.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Rule::OutOfOrder => "out-of-order",
+            Rule::FutureDated => "future-dated",
+            Rule::Duplicate => "duplicate",
+            Rule::ParentPosting => "parent-posting",
+            Rule::UnusedAccount => "unused-account",
+            Rule::UnheldCommodityPrice => "unheld-commodity-price",
+            Rule::PayeeCase => "payee-case",
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Rule, String> {
+        Rule::all().iter()
+            .find(|rule| rule.name() == s)
+            .cloned()
+            .ok_or_else(|| format!("unknown lint rule: {}", s))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Severity, String> {
+        match &s.to_lowercase()[..] {
+            "warning" => Ok(Severity::Warning),
+            "error" => Ok(Severity::Error),
+            _ => Err(format!("unknown severity: {}", s)),
+        }
+    }
+}
+
+/// Which rules the linter runs, and how severe each one's findings are. Every rule is enabled
+/// as a warning by default.
+#[derive(Clone, Debug)]
+pub struct LintConfig {
+    rules: HashMap<Rule, Option<Severity>>,
+}
+
+impl LintConfig {
+    pub fn new() -> LintConfig {
+        LintConfig {
+            rules: Rule::all().iter().map(|rule| (*rule, Some(Severity::Warning))).collect(),
+        }
+    }
+
+    /// Enables a rule, reporting its findings with the given severity.
+    pub fn enable(&mut self, rule: Rule, severity: Severity) {
+        self.rules.insert(rule, Some(severity));
+    }
+
+    pub fn disable(&mut self, rule: Rule) {
+        self.rules.insert(rule, None);
+    }
+
+    /// The severity of a rule's findings, or `None` if the rule is disabled.
+    pub fn severity(&self, rule: Rule) -> Option<Severity> {
+        self.rules.get(&rule).cloned().unwrap_or(None)
+    }
+}
+
+impl Default for LintConfig {
+    fn default() -> LintConfig {
+        LintConfig::new()
+    }
+}
+
+/// A finding from the linter, located at the entry it is about where there is one.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Diagnostic {
+    rule: Rule,
+    severity: Severity,
+    location: Option<SourceLocation>,
+    message: String,
+}
+
+impl Diagnostic {
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn location(&self) -> Option<SourceLocation> {
+        self.location
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Renders the diagnostic as a single tab-separated record for other programs to read:
+    /// severity, rule, file, line and message. Unknown fields are left empty.
+    pub fn to_record(&self) -> String {
+        let file = self.location.and_then(|l| l.file()).unwrap_or("");
+        let line = self.location.map(|l| l.line().to_string()).unwrap_or_default();
+        let message = self.message.replace(['\t', '\n'], " ");
+        format!("{}\t{}\t{}\t{}\t{}", self.severity, self.rule, file, line, message)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(location) = self.location {
+            write!(f, "{}: ", location)?;
+        }
+        write!(f, "{}[{}]: {}", self.severity, self.rule, self.message)
+    }
+}
+
+/// Runs the enabled lint rules over a journal. `today` decides which entries are in the future.
+/// Diagnostics are grouped by rule, in the order the rules are listed in `Rule`.
+pub fn lint(journal: &Journal, config: &LintConfig, today: Date<Local>) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for rule in Rule::all().iter() {
+        let severity =
+            match config.severity(*rule) {
+                Some(severity) => severity,
+                None => continue,
+            };
+        let findings =
+            match *rule {
+                Rule::OutOfOrder => out_of_order(journal),
+                Rule::FutureDated => future_dated(journal, today),
+                Rule::Duplicate => duplicates(journal),
+                Rule::ParentPosting => parent_postings(journal),
+                Rule::UnusedAccount => unused_accounts(journal),
+                Rule::UnheldCommodityPrice => unheld_commodity_prices(journal),
+                Rule::PayeeCase => payee_case(journal),
+            };
+        diagnostics.extend(findings.into_iter().map(|(location, message)| {
+            Diagnostic {
+                rule: *rule,
+                severity: severity,
+                location: location,
+                message: message,
+            }
+        }));
+    }
+    diagnostics
+}
+
+
+
+// RULES
+
+type Finding = (Option<SourceLocation>, String);
+
+fn out_of_order(journal: &Journal) -> Vec<Finding> {
+    let mut latest: HashMap<Option<&'static str>, Date<Local>> = HashMap::new();
+    let mut findings = Vec::new();
+    for transaction in journal.transactions() {
+        let date = transaction.header().date();
+        let location = transaction.location();
+        match latest.get(&location.file()).cloned() {
+            Some(previous) if date < previous => {
+                findings.push((Some(location), format!("{} comes after a transaction dated {}",
+                    date.format("%Y-%m-%d"), previous.format("%Y-%m-%d"))));
+            },
+            _ => {
+                latest.insert(location.file(), date);
+            },
+        }
+    }
+    findings
+}
+
+fn future_dated(journal: &Journal, today: Date<Local>) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for transaction in journal.transactions() {
+        let date = transaction.header().date();
+        if date > today {
+            findings.push((Some(transaction.location()),
+                format!("transaction is dated in the future ({})", date.format("%Y-%m-%d"))));
+        }
+    }
+    for (price, location) in journal.prices().iter().zip(journal.price_locations()) {
+        if price.date() > today {
+            findings.push((Some(*location), format!("price for {} is dated in the future ({})",
+                price.symbol(), price.date().format("%Y-%m-%d"))));
+        }
+    }
+    findings
+}
+
+fn duplicates(journal: &Journal) -> Vec<Finding> {
+    let mut seen: HashMap<String, SourceLocation> = HashMap::new();
+    let mut findings = Vec::new();
+    for transaction in journal.transactions() {
+        let mut postings: Vec<String> = transaction.postings().iter()
+            .map(|p| format!("{} {} {}", p.account(), p.amount().quantity(), p.amount().symbol()))
+            .collect();
+        postings.sort();
+        let key = format!("{} {} {}", transaction.header().date().format("%Y-%m-%d"),
+            transaction.header().payee().trim().to_lowercase(), postings.join(","));
+        match seen.get(&key) {
+            Some(first) => findings.push((Some(transaction.location()),
+                format!("looks like a duplicate of the transaction at {}", first))),
+            None => {
+                seen.insert(key, transaction.location());
+            },
+        }
+    }
+    findings
+}
+
+fn parent_postings(journal: &Journal) -> Vec<Finding> {
+    let parents: HashSet<AccountName> = journal.accounts().iter()
+        .flat_map(|account| Ancestors(account.parent()))
+        .collect();
+    let mut reported = HashSet::new();
+    let mut findings = Vec::new();
+    for posting in journal.postings() {
+        let account = posting.posting().account();
+        if parents.contains(&account) && reported.insert(account) {
+            let transaction = &journal.transactions()[posting.transaction()];
+            findings.push((Some(transaction.posting_location(posting.posting())),
+                format!("posting to {}, which has sub-accounts", account)));
+        }
+    }
+    findings
+}
+
+/// Declared accounts are used if they or any of their sub-accounts are posted to.
+fn unused_accounts(journal: &Journal) -> Vec<Finding> {
+    let used: HashSet<AccountName> = journal.postings().iter()
+        .flat_map(|p| Ancestors(Some(p.posting().account())))
+        .collect();
+    journal.declared_accounts().iter()
+        .filter(|&(account, _)| !used.contains(account))
+        .map(|(account, location)| {
+            (Some(*location), format!("account {} is declared but never used", account))
+        })
+        .collect()
+}
+
+/// Reported once per commodity, at its first price. Commodities are matched by name, so prices
+/// for `"AAPL"` count as held by postings of `AAPL`.
+fn unheld_commodity_prices(journal: &Journal) -> Vec<Finding> {
+    let held: HashSet<&str> = journal.postings().iter()
+        .map(|p| p.posting().amount().symbol().name())
+        .collect();
+    let mut unheld: BTreeMap<Symbol, SourceLocation> = BTreeMap::new();
+    for (price, location) in journal.prices().iter().zip(journal.price_locations()) {
+        if !held.contains(price.symbol().name()) {
+            unheld.entry(price.symbol()).or_insert(*location);
+        }
+    }
+    unheld.into_iter()
+        .map(|(symbol, location)| {
+            (Some(location),
+                format!("prices are recorded for {}, which is never held", symbol))
+        })
+        .collect()
+}
+
+fn payee_case(journal: &Journal) -> Vec<Finding> {
+    let mut variants: BTreeMap<String, Vec<(&str, SourceLocation)>> = BTreeMap::new();
+    for transaction in journal.transactions() {
+        let payee = transaction.header().payee().trim();
+        let spellings = variants.entry(payee.to_lowercase()).or_default();
+        if spellings.iter().all(|&(spelling, _)| spelling != payee) {
+            spellings.push((payee, transaction.location()));
+        }
+    }
+    variants.values()
+        .filter(|spellings| spellings.len() > 1)
+        .map(|spellings| {
+            let names: Vec<String> = spellings.iter().map(|s| format!("\"{}\"", s.0)).collect();
+            (Some(spellings[1].1), format!("payees differ only by case: {}", names.join(", ")))
+        })
+        .collect()
+}
+
+
+
+// HELPERS
+
+/// Iterates over an account and its ancestors, nearest first.
+struct Ancestors(Option<AccountName>);
+
+impl Iterator for Ancestors {
+    type Item = AccountName;
+
+    fn next(&mut self) -> Option<AccountName> {
+        let current = self.0?;
+        self.0 = current.parent();
+        Some(current)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::offset::TimeZone;
+    use parser::parse::parse_ledger_str;
+
+    const LEDGER: &str = "\
+        account Assets:Checking\n\
+        account Assets:Unused\n\
+        \n\
+        2016-06-08 * Grocery Store\n\
+        \tExpenses:Groceries    $45.00\n\
+        \tAssets:Checking\n\
+        \n\
+        2016-06-07 * grocery store\n\
+        \tExpenses:Groceries    $45.00\n\
+        \tAssets:Checking\n\
+        \n\
+        2016-06-08 * Grocery Store\n\
+        \tExpenses:Groceries    $45.00\n\
+        \tAssets:Checking\n\
+        \n\
+        2016-06-09 * Grocery Store\n\
+        \tExpenses:Groceries:Fruit    $5.00\n\
+        \tAssets:Checking\n\
+        \n\
+        P 2016-06-08 AAPL $98.83\n\
+        \n\
+        2017-01-01 * Future\n\
+        \tExpenses:Groceries:Fruit    $5.00\n\
+        \tAssets:Checking\n\
+        \n\
+        account Expenses ; type: Expense\n\
+        P 2017-01-02 AAPL $99.00\n";
+
+    fn journal() -> Journal {
+        Journal::from_parse_tree(parse_ledger_str(LEDGER).unwrap()).unwrap()
+    }
+
+    fn messages(diagnostics: &[Diagnostic], rule: Rule) -> Vec<String> {
+        diagnostics.iter()
+            .filter(|d| d.rule() == rule)
+            .map(|d| format!("{}", d))
+            .collect()
+    }
+
+    #[test]
+    fn lint_reports_each_rule() {
+        let diagnostics = lint(&journal(), &LintConfig::new(), Local.ymd(2016, 12, 31));
+        assert_eq!(messages(&diagnostics, Rule::OutOfOrder), vec![
+            "line 8: warning[out-of-order]: 2016-06-07 comes after a transaction dated 2016-06-08"]);
+        assert_eq!(messages(&diagnostics, Rule::FutureDated), vec![
+            "line 22: warning[future-dated]: transaction is dated in the future (2017-01-01)",
+            "line 27: warning[future-dated]: price for AAPL is dated in the future (2017-01-02)"]);
+        assert_eq!(messages(&diagnostics, Rule::Duplicate), vec![
+            "line 12: warning[duplicate]: looks like a duplicate of the transaction at line 4"]);
+        assert_eq!(messages(&diagnostics, Rule::ParentPosting), vec![
+            "line 9: warning[parent-posting]: posting to Expenses:Groceries, which has \
+            sub-accounts"]);
+        assert_eq!(messages(&diagnostics, Rule::UnusedAccount), vec![
+            "line 2: warning[unused-account]: account Assets:Unused is declared but never used"]);
+        assert_eq!(messages(&diagnostics, Rule::UnheldCommodityPrice), vec![
+            "line 20: warning[unheld-commodity-price]: prices are recorded for AAPL, which is \
+            never held"]);
+        assert_eq!(messages(&diagnostics, Rule::PayeeCase), vec![
+            "line 8: warning[payee-case]: payees differ only by case: \"Grocery Store\", \
+            \"grocery store\""]);
+    }
+
+    #[test]
+    fn lint_matches_held_commodities_by_name() {
+        let tree = parse_ledger_str("\
+            2016-06-07 * Buy shares\n\
+            \tAssets:Investments    1 AAPL @ $90.00\n\
+            \tAssets:Checking\n\
+            \n\
+            P 2016-06-07 \"AAPL\" $90.00\n").unwrap();
+        let journal = Journal::from_parse_tree(tree).unwrap();
+        let diagnostics = lint(&journal, &LintConfig::new(), Local.ymd(2016, 12, 31));
+        assert_eq!(messages(&diagnostics, Rule::UnheldCommodityPrice), Vec::<String>::new());
+    }
+
+    #[test]
+    fn lint_config_disables_rules_and_sets_severity() {
+        let mut config = LintConfig::new();
+        for rule in Rule::all().iter() {
+            config.disable(*rule);
+        }
+        config.enable(Rule::Duplicate, Severity::Error);
+        let diagnostics = lint(&journal(), &config, Local.ymd(2016, 12, 31));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity(), Severity::Error);
+    }
+
+    #[test]
+    fn diagnostic_to_record() {
+        let diagnostics = lint(&journal(), &LintConfig::new(), Local.ymd(2016, 12, 31));
+        assert_eq!(diagnostics[0].to_record(),
+            "warning\tout-of-order\t\t8\t2016-06-07 comes after a transaction dated 2016-06-08");
+    }
+
+    #[test]
+    fn rule_from_str() {
+        assert_eq!("payee-case".parse(), Ok(Rule::PayeeCase));
+        assert!("payee".parse::<Rule>().is_err());
+        assert_eq!("Error".parse(), Ok(Severity::Error));
+    }
+}
@@ -0,0 +1,7 @@
+pub mod assertion;
+pub mod balance;
+pub mod duplicate;
+pub mod journal;
+pub mod lint;
+pub mod price_check;
+pub mod strict;
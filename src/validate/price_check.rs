@@ -0,0 +1,324 @@
+use rust_core::str::FromStr;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use core::commodity::{Commodity, RenderOptions};
+use core::location::SourceLocation;
+use core::price::Price;
+use core::symbol::Symbol;
+
+
+/// A price DB entry: its location, the price, and how its amount was written.
+pub type PriceEntry = (SourceLocation, Price, RenderOptions);
+
+/// The kinds of suspicious price entries.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PriceIssue {
+    /// The price moved further from the previous price than the threshold allows.
+    Jump,
+    /// The price is zero or negative.
+    NonPositive,
+    /// The price is written with a different number of decimal places than usual.
+    Precision,
+}
+
+impl fmt::Display for PriceIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PriceIssue::Jump => write!(f, "jump"),
+            PriceIssue::NonPositive => write!(f, "non-positive"),
+            PriceIssue::Precision => write!(f, "precision"),
+        }
+    }
+}
+
+/// How large a move between consecutive prices is suspicious.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PriceCheckOptions {
+    max_change: f64,
+}
+
+impl PriceCheckOptions {
+    /// `max_change` is the largest move between consecutive prices, as a fraction of the earlier
+    /// price, that isn't reported. e.g. 0.2 for 20%
+    pub fn new(max_change: f64) -> PriceCheckOptions {
+        PriceCheckOptions {
+            max_change: max_change,
+        }
+    }
+
+    pub fn max_change(&self) -> f64 {
+        self.max_change
+    }
+}
+
+impl Default for PriceCheckOptions {
+    fn default() -> PriceCheckOptions {
+        PriceCheckOptions::new(0.2)
+    }
+}
+
+/// A suspicious price entry, with the likely explanations for it.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PriceFinding {
+    issue: PriceIssue,
+    location: SourceLocation,
+    message: String,
+    suggestions: Vec<String>,
+}
+
+impl PriceFinding {
+    pub fn issue(&self) -> PriceIssue {
+        self.issue
+    }
+
+    pub fn location(&self) -> SourceLocation {
+        self.location
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Likely explanations, most specific first.
+    pub fn suggestions(&self) -> &[String] {
+        &self.suggestions
+    }
+}
+
+impl fmt::Display for PriceFinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: warning[{}]: {}\n    could be {}", self.location, self.issue, self.message,
+            self.suggestions.join(", or "))
+    }
+}
+
+/// Checks price DB entries for suspicious prices: moves between consecutive prices of a
+/// commodity larger than the threshold, zero or negative prices, and prices written with a
+/// different number of decimal places than most prices of that commodity.
+///
+/// A price that jumps away and is followed by one back in line with the price before it is
+/// reported as a one-off, and the price after it is compared with the price before it.
+/// Prices in messages are rendered the way they are written in the price DB. Findings are
+/// sorted by line.
+pub fn check_prices(entries: &[PriceEntry], options: &PriceCheckOptions) -> Vec<PriceFinding> {
+    let mut series: BTreeMap<(Symbol, Symbol), Vec<&PriceEntry>> = BTreeMap::new();
+    for entry in entries {
+        series.entry((entry.1.symbol(), entry.1.amount().symbol())).or_default().push(entry);
+    }
+
+    let mut findings = Vec::new();
+    for entries in series.values_mut() {
+        entries.sort_by_key(|entry| entry.1.sort_key());
+        findings.extend(non_positive_prices(entries));
+        findings.extend(price_jumps(entries, options.max_change));
+        findings.extend(unusual_precision(entries));
+    }
+    findings.sort_by_key(|finding| finding.location.line());
+    findings
+}
+
+
+
+// HELPERS
+
+fn non_positive_prices(entries: &[&PriceEntry]) -> Vec<PriceFinding> {
+    entries.iter()
+        .filter(|entry| !is_positive(&entry.1))
+        .map(|&entry| {
+            let (location, ref price, _) = *entry;
+            let negative = price.amount().quantity().is_negative();
+            PriceFinding {
+                issue: PriceIssue::NonPositive,
+                location: location,
+                message: format!("{} is priced at {} on {}", price.symbol(),
+                    describe(entry), price.date().format("%Y-%m-%d")),
+                suggestions: if negative {
+                    vec!["a stray minus sign".to_string()]
+                } else {
+                    vec!["a missing quote recorded as zero".to_string(),
+                        "a price with its digits left out".to_string()]
+                },
+            }
+        })
+        .collect()
+}
+
+fn price_jumps(entries: &[&PriceEntry], max_change: f64) -> Vec<PriceFinding> {
+    let positive: Vec<_> = entries.iter().filter(|entry| is_positive(&entry.1)).collect();
+    let mut findings = Vec::new();
+    let mut previous: Option<&PriceEntry> = None;
+    for (index, &&entry) in positive.iter().enumerate() {
+        let (location, ref price, _) = *entry;
+        let before = match previous {
+            Some(before) => before,
+            None => {
+                previous = Some(entry);
+                continue;
+            },
+        };
+        let ratio = ratio(price, &before.1);
+        if (ratio - 1.0).abs() <= max_change {
+            previous = Some(entry);
+            continue;
+        }
+
+        let next = positive.get(index + 1).map(|&&next| next);
+        let back_in_line =
+            next.filter(|next| (self::ratio(&next.1, &before.1) - 1.0).abs() <= max_change);
+        let mut suggestions = Vec::new();
+        if let Some(next) = back_in_line {
+            suggestions.push(format!("a typo, since the next price ({} on {}) is back in line",
+                describe(next), next.1.date().format("%Y-%m-%d")));
+        } else {
+            previous = Some(entry);
+        }
+        suggestions.extend(jump_explanations(ratio));
+        findings.push(PriceFinding {
+            issue: PriceIssue::Jump,
+            location: location,
+            message: format!("{} {} {:.1}% from {} on {} to {} on {}", price.symbol(),
+                if ratio > 1.0 { "rose" } else { "fell" }, (ratio - 1.0).abs() * 100.0,
+                describe(before), before.1.date().format("%Y-%m-%d"), describe(entry),
+                price.date().format("%Y-%m-%d")),
+            suggestions: suggestions,
+        });
+    }
+    findings
+}
+
+/// Explains a move between two prices by the ratio of the later price to the earlier one.
+fn jump_explanations(ratio: f64) -> Vec<String> {
+    let near = |value: f64, target: f64| (value / target - 1.0).abs() < 0.03;
+    let mut explanations = Vec::new();
+    if [10.0, 100.0, 1000.0].iter().any(|&power| near(ratio, power) || near(1.0 / ratio, power)) {
+        explanations.push("a misplaced decimal point".to_string());
+    }
+    for n in 2..11 {
+        if near(1.0 / ratio, n as f64) {
+            explanations.push(format!("a {}-for-1 split", n));
+        } else if near(ratio, n as f64) {
+            explanations.push(format!("a 1-for-{} reverse split", n));
+        }
+    }
+    explanations.push("a genuine market move".to_string());
+    explanations
+}
+
+fn unusual_precision(entries: &[&PriceEntry]) -> Vec<PriceFinding> {
+    let mut counts: HashMap<u32, usize> = HashMap::new();
+    for entry in entries {
+        *counts.entry(entry.2.precision()).or_insert(0) += 1;
+    }
+    let usual = match counts.iter().max_by_key(|&(_, count)| *count) {
+        Some((&precision, &count)) if entries.len() >= 3 && count * 2 > entries.len() => precision,
+        _ => return Vec::new(),
+    };
+
+    entries.iter()
+        .filter(|entry| entry.2.precision() != usual)
+        .map(|&entry| {
+            let (location, ref price, render_opts) = *entry;
+            let precision = render_opts.precision();
+            PriceFinding {
+                issue: PriceIssue::Precision,
+                location: location,
+                message: format!("{} is priced at {} with {} decimal places where {} is usual",
+                    price.symbol(), describe(entry), precision, usual),
+                suggestions: if precision < usual {
+                    vec!["a dropped digit".to_string(),
+                        "a rounded quote from another source".to_string()]
+                } else {
+                    vec!["an extra digit".to_string(),
+                        "a more precise quote from another source".to_string()]
+                },
+            }
+        })
+        .collect()
+}
+
+fn is_positive(price: &Price) -> bool {
+    let quantity = price.amount().quantity();
+    !quantity.is_zero() && !quantity.is_negative()
+}
+
+/// The ratio of one price to another, as a float for comparing against thresholds.
+fn ratio(price: &Price, other: &Price) -> f64 {
+    let ratio = price.amount().quantity() / other.amount().quantity();
+    f64::from_str(&ratio.to_string()).unwrap_or(1.0)
+}
+
+/// Renders an entry's price the way it is written in the price DB.
+fn describe(entry: &PriceEntry) -> String {
+    let amount = entry.1.amount();
+    Commodity::new(amount.symbol(), entry.2).format(amount.quantity())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::parse::read_located_pricedb;
+
+    fn findings() -> Vec<PriceFinding> {
+        let entries = read_located_pricedb("test/data/suspicious.pricedb").unwrap();
+        check_prices(&entries, &PriceCheckOptions::default())
+    }
+
+    #[test]
+    fn check_prices_finds_issues_in_line_order() {
+        let findings = findings();
+        let found: Vec<(usize, PriceIssue)> = findings.iter()
+            .map(|f| (f.location().line(), f.issue()))
+            .collect();
+        assert_eq!(found, vec![
+            (3, PriceIssue::Jump),
+            (5, PriceIssue::Jump),
+            (6, PriceIssue::Precision),
+            (7, PriceIssue::NonPositive),
+            (9, PriceIssue::NonPositive),
+        ]);
+    }
+
+    #[test]
+    fn check_prices_one_off_jump_suggests_typo() {
+        let findings = findings();
+        assert_eq!(findings[0].message(),
+            "\"MUTF2351\" rose 903.7% from $5.43 on 2016-02-02 to $54.50 on 2016-02-03");
+        assert_eq!(findings[0].suggestions(), &[
+            "a typo, since the next price ($5.44 on 2016-02-04) is back in line".to_string(),
+            "a misplaced decimal point".to_string(),
+            "a 1-for-10 reverse split".to_string(),
+            "a genuine market move".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn check_prices_halving_suggests_split() {
+        let findings = findings();
+        assert_eq!(findings[1].suggestions(), &[
+            "a 2-for-1 split".to_string(),
+            "a genuine market move".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn check_prices_fmt() {
+        let findings = findings();
+        assert_eq!(format!("{}", findings[2]),
+            "test/data/suspicious.pricedb:6: warning[precision]: \"MUTF2351\" is priced at $2.7 \
+            with 1 decimal places where 2 is usual\n    \
+            could be a dropped digit, or a rounded quote from another source");
+        assert_eq!(format!("{}", findings[4]),
+            "test/data/suspicious.pricedb:9: warning[non-positive]: AAPL is priced at $-96.35 on \
+            2016-02-02\n    could be a stray minus sign");
+    }
+
+    #[test]
+    fn check_prices_threshold() {
+        let entries = read_located_pricedb("test/data/multiple.pricedb").unwrap();
+        let findings = check_prices(&entries, &PriceCheckOptions::default());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].location().line(), 3);
+        assert!(check_prices(&entries, &PriceCheckOptions::new(0.3)).is_empty());
+    }
+}
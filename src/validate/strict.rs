@@ -0,0 +1,313 @@
+use std::collections::HashSet;
+use std::fmt;
+use core::account::AccountName;
+use core::amount::Amount;
+use core::corporate_action::ActionKind;
+use core::location::SourceLocation;
+use core::symbol::Symbol;
+use core::transaction::Cost;
+use parser::ast::ParseTree;
+
+
+/// How strictly a ledger's names are checked when it is loaded.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Strictness {
+    /// Accounts, commodities and payees are created by using them.
+    #[default]
+    Permissive,
+    /// Accounts and commodities must be declared with `account` and `commodity` directives
+    /// before they can be used.
+    Strict,
+    /// As `Strict`, and payees must also be declared with `payee` directives.
+    StrictWithPayees,
+}
+
+/// The kinds of names that can be declared.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum NameKind {
+    Account,
+    Commodity,
+    Payee,
+}
+
+impl fmt::Display for NameKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NameKind::Account => write!(f, "account"),
+            NameKind::Commodity => write!(f, "commodity"),
+            NameKind::Payee => write!(f, "payee"),
+        }
+    }
+}
+
+/// A name used without being declared, along with the closest declared name of the same kind,
+/// if there is one near enough to be a likely typo.
+#[derive(PartialEq, Debug)]
+pub struct UndeclaredError {
+    location: SourceLocation,
+    kind: NameKind,
+    name: String,
+    suggestion: Option<String>,
+}
+
+impl UndeclaredError {
+    pub fn location(&self) -> SourceLocation {
+        self.location
+    }
+
+    pub fn kind(&self) -> NameKind {
+        self.kind
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn suggestion(&self) -> Option<&str> {
+        self.suggestion.as_ref().map(|s| &s[..])
+    }
+}
+
+impl fmt::Display for UndeclaredError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: undeclared {} {}", self.location, self.kind, self.name)?;
+        if let Some(ref suggestion) = self.suggestion {
+            write!(f, " (did you mean {}?)", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks that every account, commodity and, if asked, payee used in a parsed ledger has been
+/// declared by a directive. Directives apply wherever they appear in the ledger. Names used in
+/// postings are reported at the posting's line. Permissive mode accepts everything.
+pub fn check_declarations(tree: &[ParseTree], strictness: Strictness) -> Vec<UndeclaredError> {
+    if strictness == Strictness::Permissive {
+        return Vec::new();
+    }
+
+    let mut checker = Checker {
+        accounts: HashSet::new(),
+        commodities: HashSet::new(),
+        payees: HashSet::new(),
+        errors: Vec::new(),
+        reported: HashSet::new(),
+    };
+    for item in tree {
+        match *item {
+            ParseTree::Account(_, account, _) => {
+                checker.accounts.insert(account);
+            },
+            ParseTree::Commodity(ref commodity) => {
+                checker.commodities.insert(commodity.symbol().name());
+            },
+            ParseTree::Payee(ref payee) => {
+                checker.payees.insert(payee);
+            },
+            _ => {},
+        }
+    }
+
+    for item in tree {
+        match *item {
+            ParseTree::Price(location, ref price, _) => {
+                checker.commodity(location, price.symbol());
+                checker.commodity(location, price.amount().symbol());
+            },
+            ParseTree::Transaction(location, ref header, ref postings) => {
+                if strictness == Strictness::StrictWithPayees {
+                    checker.payee(location, header.payee().trim());
+                }
+                for posting in postings {
+                    let location = posting.line().map_or(location, |line| location.at_line(line));
+                    checker.account(location, posting.account());
+                    let amounts = posting.amount().into_iter()
+                        .chain(posting.cost().map(cost_amount))
+                        .chain(posting.assertion());
+                    for amount in amounts {
+                        checker.commodity(location, amount.symbol());
+                    }
+                }
+            },
+            ParseTree::CorporateAction(location, ref action) => {
+                checker.commodity(location, action.symbol());
+                if let ActionKind::Rename(to) = action.kind() {
+                    checker.commodity(location, to);
+                }
+            },
+            _ => {},
+        }
+    }
+    checker.errors
+}
+
+
+
+// HELPERS
+
+fn cost_amount(cost: &Cost) -> &Amount {
+    match *cost {
+        Cost::PerUnit(ref amount) | Cost::Total(ref amount) => amount,
+    }
+}
+
+/// Collects undeclared names, reporting each name only once, at its first use. Commodities are
+/// known by name, so a declaration covers the symbol whether or not it is quoted.
+struct Checker<'a> {
+    accounts: HashSet<AccountName>,
+    commodities: HashSet<&'static str>,
+    payees: HashSet<&'a str>,
+    errors: Vec<UndeclaredError>,
+    reported: HashSet<(NameKind, &'a str)>,
+}
+
+impl<'a> Checker<'a> {
+    fn account(&mut self, location: SourceLocation, account: AccountName) {
+        let name = account.as_str();
+        if !self.accounts.contains(&account) && self.first_use(NameKind::Account, name) {
+            let suggestion = suggest(name, self.accounts.iter().map(|a| a.as_str()));
+            self.report(location, NameKind::Account, name, suggestion);
+        }
+    }
+
+    fn commodity(&mut self, location: SourceLocation, symbol: Symbol) {
+        let name = symbol.name();
+        if !self.commodities.contains(name) && self.first_use(NameKind::Commodity, name) {
+            let suggestion = suggest(name, self.commodities.iter().cloned());
+            self.report(location, NameKind::Commodity, name, suggestion);
+        }
+    }
+
+    fn payee(&mut self, location: SourceLocation, payee: &'a str) {
+        if !self.payees.contains(payee) && self.first_use(NameKind::Payee, payee) {
+            let suggestion = suggest(payee, self.payees.iter().cloned());
+            self.report(location, NameKind::Payee, payee, suggestion);
+        }
+    }
+
+    /// Whether an undeclared name is being used for the first time.
+    fn first_use(&mut self, kind: NameKind, name: &'a str) -> bool {
+        self.reported.insert((kind, name))
+    }
+
+    fn report(&mut self, location: SourceLocation, kind: NameKind, name: &str,
+    suggestion: Option<String>) {
+        self.errors.push(UndeclaredError {
+            location: location,
+            kind: kind,
+            name: name.to_string(),
+            suggestion: suggestion,
+        });
+    }
+}
+
+/// Finds the declared name closest to `name`, if it is close enough to be a typo: at most a
+/// third of the name's characters need to change. Ties go to the first name alphabetically.
+fn suggest<'b, I>(name: &str, declared: I) -> Option<String>
+where I: Iterator<Item=&'b str> {
+    let max_distance = ::std::cmp::max(1, name.chars().count() / 3);
+    declared
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate.to_string())
+}
+
+/// The Levenshtein distance between two strings: the number of single character insertions,
+/// deletions and substitutions needed to turn one into the other.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..b.len() + 1).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + if a_char == *b_char { 0 } else { 1 };
+            let insertion = current[j] + 1;
+            let deletion = previous[j + 1] + 1;
+            current.push(substitution.min(insertion).min(deletion));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::parse::parse_ledger_str;
+
+    fn check(ledger: &str, strictness: Strictness) -> Vec<UndeclaredError> {
+        let tree = parse_ledger_str(ledger).unwrap();
+        check_declarations(&tree, strictness)
+    }
+
+    const LEDGER: &str = "\
+        account Expenses:Groceries\n\
+        account Liabilities:Credit\n\
+        commodity $1,000.00\n\
+        payee Grocery Store\n\
+        \n\
+        2016-06-07 * Grocery Stor\n\
+        \tExpenses:Grocereis    $45.00\n\
+        \tLiabilities:Credit\n\
+        \n\
+        2016-06-08 * Grocery Store\n\
+        \tExpenses:Grocereis    45 CAD\n\
+        \tLiabilities:Credit\n";
+
+    #[test]
+    fn edit_distance_test() {
+        assert_eq!(edit_distance("Groceries", "Grocereis"), 2);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn check_declarations_permissive_accepts_everything() {
+        assert_eq!(check(LEDGER, Strictness::Permissive), vec![]);
+    }
+
+    #[test]
+    fn check_declarations_strict_reports_first_use_with_suggestion() {
+        let errors = check(LEDGER, Strictness::Strict);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(format!("{}", errors[0]),
+            "line 7: undeclared account Expenses:Grocereis (did you mean Expenses:Groceries?)");
+        assert_eq!(format!("{}", errors[1]), "line 11: undeclared commodity CAD");
+    }
+
+    #[test]
+    fn check_declarations_strict_with_payees() {
+        let errors = check(LEDGER, Strictness::StrictWithPayees);
+        assert_eq!(errors[0].kind(), NameKind::Payee);
+        assert_eq!(errors[0].name(), "Grocery Stor");
+        assert_eq!(errors[0].suggestion(), Some("Grocery Store"));
+        assert_eq!(errors.len(), 3);
+    }
+
+    #[test]
+    fn check_declarations_strict_matches_commodities_by_name() {
+        let ledger = "\
+            account Assets:Investments\n\
+            account Assets:Checking\n\
+            commodity 1,000.00 AAPL\n\
+            commodity $1,000.00\n\
+            \n\
+            2016-06-07 * Buy shares\n\
+            \tAssets:Investments    1 \"AAPL\" @ $98.83\n\
+            \tAssets:Checking\n";
+        assert_eq!(check(ledger, Strictness::Strict), vec![]);
+    }
+
+    #[test]
+    fn check_declarations_strict_corporate_actions() {
+        let ledger = "\
+            commodity 1,000.0 \"MUTF2351\"\n\
+            split 2016-03-01 \"MUTF2351\" 2:1\n\
+            rename 2016-04-01 \"MUTF2351\" \"MUTF9999\"\n";
+        let errors = check(ledger, Strictness::Strict);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(format!("{}", errors[0]), "line 3: undeclared commodity MUTF9999");
+    }
+}